@@ -18,7 +18,7 @@ version="$GITHUB_REF_NAME"
 rm -rf --one-file-system release
 release_dir=release/"$version"
 mkdir -p "$release_dir"/"$target"
-for filename in lsbom mkbom; do
+for filename in lsbom mkbom bomdiff bomcompact bomdump bomfsck; do
     cp -vn target/"$target"/release/"$filename" "$release_dir"/"$target"/
 done
 cd "$release_dir"
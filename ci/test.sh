@@ -8,8 +8,8 @@ clean() {
 }
 
 test_all() {
-    cargo test --workspace --quiet --no-run
-    cargo test --workspace --no-fail-fast -- --nocapture
+    cargo test --workspace --all-features --quiet --no-run
+    cargo test --workspace --all-features --no-fail-fast -- --nocapture
 }
 
 test_coverage_preamble() {
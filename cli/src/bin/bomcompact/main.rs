@@ -0,0 +1,77 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Error;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use stuckliste::receipt::Receipt;
+use stuckliste::Bom;
+use stuckliste::CompactStats;
+use tempfile::NamedTempFile;
+
+#[derive(Parser)]
+#[clap(
+    arg_required_else_help = true,
+    about = "Remove unreachable blocks from BOM file",
+    long_about = "Remove unreachable blocks from BOM file.\n\n\
+        Only the blocks that are reachable from the named blocks are kept.\n\
+        Unknown named blocks are kept as is."
+)]
+struct Args {
+    /// Write the compacted file to the specified path instead of replacing the input file.
+    #[arg(short = 'o', value_name = "file")]
+    output: Option<PathBuf>,
+    /// Input BOM file.
+    #[arg(value_name = "bom")]
+    bom: PathBuf,
+}
+
+fn main() -> ExitCode {
+    match do_main() {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn do_main() -> Result<(), Error> {
+    let args = Args::parse();
+    let file = std::fs::read(&args.bom)
+        .map_err(|e| Error::other(format!("failed to read {}: {}", args.bom.display(), e)))?;
+    let bom = Bom::read(&file[..])
+        .map_err(|e| Error::other(format!("failed to read {}: {}", args.bom.display(), e)))?;
+    let output = args.output.as_deref().unwrap_or(&args.bom);
+    // write to a temporary file in the same directory to replace the output atomically
+    let directory = match output.parent() {
+        Some(parent) if parent != Path::new("") => parent,
+        _ => Path::new("."),
+    };
+    let mut temp = NamedTempFile::new_in(directory)?;
+    // preserve the permissions of the input file
+    temp.as_file()
+        .set_permissions(std::fs::metadata(&args.bom)?.permissions())?;
+    let stats = compact(&bom, &file, temp.as_file_mut())?;
+    temp.as_file().sync_all()?;
+    temp.persist(output)
+        .map_err(|e| Error::other(format!("failed to write {}: {}", output.display(), e)))?;
+    println!(
+        "reclaimed {} bytes ({} -> {}), removed {} blocks",
+        stats.reclaimed(),
+        stats.old_len,
+        stats.new_len,
+        stats.old_num_blocks.saturating_sub(stats.new_num_blocks)
+    );
+    Ok(())
+}
+
+fn compact(bom: &Bom, file: &[u8], output: &mut File) -> Result<CompactStats, Error> {
+    let mut writer = BufWriter::new(output);
+    let stats = bom.compact(file, writer.by_ref(), Receipt::block_layout)?;
+    writer.flush()?;
+    Ok(stats)
+}
@@ -0,0 +1,317 @@
+use std::fmt::Write;
+use std::fs::File;
+use std::io::Error;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use stuckliste::receipt::diff;
+use stuckliste::receipt::Change;
+use stuckliste::receipt::DiffEntry;
+use stuckliste::receipt::EntryType;
+use stuckliste::receipt::Receipt;
+use stuckliste::receipt::ReceiptDiff;
+
+#[derive(Parser)]
+#[clap(
+    arg_required_else_help = true,
+    about = "Compare two BOM files",
+    long_about = "Compare two BOM files.\n\n\
+        Exits with status 0 if the files are equal, 1 if they differ and 2 on error.\n\n\
+        Text output uses one line per path: `A` (added), `D` (removed), `R` (moved),\n\
+        `T` (type changed), `M` (metadata changed)."
+)]
+struct Args {
+    /// Print the differences in JSON format.
+    #[arg(long = "json")]
+    json: bool,
+    /// Old BOM file.
+    #[arg(value_name = "OLD")]
+    old: PathBuf,
+    /// New BOM file.
+    #[arg(value_name = "NEW")]
+    new: PathBuf,
+}
+
+fn main() -> ExitCode {
+    match do_main() {
+        Ok(exit_code) => exit_code,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn do_main() -> Result<ExitCode, Error> {
+    let args = Args::parse();
+    let old = read_receipt(&args.old)?;
+    let new = read_receipt(&args.new)?;
+    let diff = diff(&old, &new);
+    let mut output = String::with_capacity(4096);
+    if args.json {
+        write_json(&mut output, &diff).map_err(Error::other)?;
+    } else {
+        write_text(&mut output, &diff).map_err(Error::other)?;
+    }
+    print!("{}", output);
+    Ok(if diff.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn read_receipt(path: &Path) -> Result<Receipt, Error> {
+    let file = File::open(path)
+        .map_err(|e| Error::other(format!("failed to open {}: {}", path.display(), e)))?;
+    Receipt::read(file)
+        .map_err(|e| Error::other(format!("failed to read {}: {}", path.display(), e)))
+}
+
+fn write_text(output: &mut String, diff: &ReceiptDiff) -> std::fmt::Result {
+    for entry in diff.added() {
+        writeln!(output, "A {}", entry.path.display())?;
+    }
+    for entry in diff.removed() {
+        writeln!(output, "D {}", entry.path.display())?;
+    }
+    for moved in diff.moved() {
+        write!(
+            output,
+            "R {} -> {}",
+            moved.old_path.display(),
+            moved.new_path.display()
+        )?;
+        write_text_changes(output, &moved.changes)?;
+    }
+    for change in diff.type_changed() {
+        writeln!(
+            output,
+            "T {}: {} -> {}",
+            change.path.display(),
+            entry_type_name(change.old),
+            entry_type_name(change.new)
+        )?;
+    }
+    for change in diff.changed() {
+        write!(output, "M {}", change.path.display())?;
+        write_text_changes(output, &change.changes)?;
+    }
+    Ok(())
+}
+
+fn write_text_changes(output: &mut String, changes: &[Change]) -> std::fmt::Result {
+    for (i, change) in changes.iter().enumerate() {
+        let separator = if i == 0 { ": " } else { ", " };
+        write!(output, "{}{}", separator, change)?;
+    }
+    output.push('\n');
+    Ok(())
+}
+
+fn write_json(output: &mut String, diff: &ReceiptDiff) -> std::fmt::Result {
+    output.push_str("{\"added\":");
+    write_json_entries(output, diff.added())?;
+    output.push_str(",\"removed\":");
+    write_json_entries(output, diff.removed())?;
+    output.push_str(",\"moved\":[");
+    for (i, moved) in diff.moved().iter().enumerate() {
+        if i != 0 {
+            output.push(',');
+        }
+        output.push_str("{\"old_path\":");
+        write_json_path(output, &moved.old_path)?;
+        output.push_str(",\"new_path\":");
+        write_json_path(output, &moved.new_path)?;
+        output.push_str(",\"changes\":");
+        write_json_changes(output, &moved.changes)?;
+        output.push('}');
+    }
+    output.push_str("],\"type_changed\":[");
+    for (i, change) in diff.type_changed().iter().enumerate() {
+        if i != 0 {
+            output.push(',');
+        }
+        output.push_str("{\"path\":");
+        write_json_path(output, &change.path)?;
+        write!(
+            output,
+            ",\"old\":\"{}\",\"new\":\"{}\"}}",
+            entry_type_name(change.old),
+            entry_type_name(change.new)
+        )?;
+    }
+    output.push_str("],\"changed\":[");
+    for (i, change) in diff.changed().iter().enumerate() {
+        if i != 0 {
+            output.push(',');
+        }
+        output.push_str("{\"path\":");
+        write_json_path(output, &change.path)?;
+        output.push_str(",\"changes\":");
+        write_json_changes(output, &change.changes)?;
+        output.push('}');
+    }
+    output.push_str("]}\n");
+    Ok(())
+}
+
+fn write_json_entries(output: &mut String, entries: &[DiffEntry]) -> std::fmt::Result {
+    output.push('[');
+    for (i, entry) in entries.iter().enumerate() {
+        if i != 0 {
+            output.push(',');
+        }
+        output.push_str("{\"path\":");
+        write_json_path(output, &entry.path)?;
+        write!(
+            output,
+            ",\"type\":\"{}\"}}",
+            entry_type_name(entry.metadata.entry_type())
+        )?;
+    }
+    output.push(']');
+    Ok(())
+}
+
+fn write_json_changes(output: &mut String, changes: &[Change]) -> std::fmt::Result {
+    output.push('[');
+    for (i, change) in changes.iter().enumerate() {
+        if i != 0 {
+            output.push(',');
+        }
+        write!(output, "{{\"field\":\"{}\"", change.name())?;
+        match change {
+            // Modes are printed in octal as in `lsbom` output.
+            Change::Mode { old, new } => {
+                write!(output, ",\"old\":\"{:o}\",\"new\":\"{:o}\"", old, new)?
+            }
+            Change::Uid { old, new }
+            | Change::Gid { old, new }
+            | Change::Checksum { old, new }
+            | Change::Mtime { old, new } => write!(output, ",\"old\":{},\"new\":{}", old, new)?,
+            Change::Size { old, new } => write!(output, ",\"old\":{},\"new\":{}", old, new)?,
+            Change::Rdev { old, new } => write!(output, ",\"old\":{},\"new\":{}", old, new)?,
+            Change::Target { old, new } => {
+                output.push_str(",\"old\":");
+                write_json_path(output, old)?;
+                output.push_str(",\"new\":");
+                write_json_path(output, new)?;
+            }
+            Change::ArchSize {
+                cpu_type,
+                cpu_sub_type,
+                old,
+                new,
+            }
+            | Change::ArchChecksum {
+                cpu_type,
+                cpu_sub_type,
+                old,
+                new,
+            } => write!(
+                output,
+                ",\"cpu_type\":{},\"cpu_sub_type\":{},\"old\":{},\"new\":{}",
+                cpu_type, cpu_sub_type, old, new
+            )?,
+            // Digests are printed in hexadecimal as in `shasum` output.
+            Change::Sha256 { old, new } => {
+                output.push_str(",\"old\":");
+                write_json_digest(output, old)?;
+                output.push_str(",\"new\":");
+                write_json_digest(output, new)?;
+            }
+            Change::ArchSha256 {
+                cpu_type,
+                cpu_sub_type,
+                old,
+                new,
+            } => {
+                write!(
+                    output,
+                    ",\"cpu_type\":{},\"cpu_sub_type\":{},\"old\":",
+                    cpu_type, cpu_sub_type
+                )?;
+                write_json_digest(output, old)?;
+                output.push_str(",\"new\":");
+                write_json_digest(output, new)?;
+            }
+            Change::ArchAdded {
+                cpu_type,
+                cpu_sub_type,
+            }
+            | Change::ArchRemoved {
+                cpu_type,
+                cpu_sub_type,
+            } => write!(
+                output,
+                ",\"cpu_type\":{},\"cpu_sub_type\":{}",
+                cpu_type, cpu_sub_type
+            )?,
+        }
+        output.push('}');
+    }
+    output.push(']');
+    Ok(())
+}
+
+/// Write the digest as JSON string in hexadecimal.
+fn write_json_digest(output: &mut String, digest: &[u8]) -> std::fmt::Result {
+    output.push('"');
+    for byte in digest.iter() {
+        write!(output, "{:02x}", byte)?;
+    }
+    output.push('"');
+    Ok(())
+}
+
+/// Write the path as JSON string.
+///
+/// Paths that are not valid UTF-8 are converted lossily.
+fn write_json_path(output: &mut String, path: &Path) -> std::fmt::Result {
+    let path = path.to_string_lossy();
+    output.push('"');
+    for ch in path.chars() {
+        match ch {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            ch if ch.is_control() => write!(output, "\\u{:04x}", ch as u32)?,
+            ch => output.push(ch),
+        }
+    }
+    output.push('"');
+    Ok(())
+}
+
+fn entry_type_name(entry_type: EntryType) -> &'static str {
+    match entry_type {
+        EntryType::File => "file",
+        EntryType::Directory => "directory",
+        EntryType::Link => "link",
+        EntryType::Device => "device",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_path() {
+        for (expected, path) in [
+            (r#""./a""#, "./a"),
+            (r#""./\"a\\b\"""#, "./\"a\\b\""),
+            (r#""./a\tb\n""#, "./a\tb\n"),
+            (r#""./\u001b""#, "./\u{1b}"),
+        ] {
+            let mut output = String::new();
+            write_json_path(&mut output, Path::new(path)).unwrap();
+            assert_eq!(expected, output);
+        }
+    }
+}
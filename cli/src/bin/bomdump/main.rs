@@ -0,0 +1,317 @@
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::ffi::CString;
+use std::fmt::Write;
+use std::io::Error;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use stuckliste::BigEndianRead;
+use stuckliste::Block;
+use stuckliste::Blocks;
+use stuckliste::BomHeader;
+use stuckliste::NamedBlocks;
+use stuckliste::RawTree;
+use stuckliste::RawTreeNode;
+
+#[derive(Parser)]
+#[clap(
+    arg_required_else_help = true,
+    about = "Print the low-level structure of BOM file",
+    long_about = "Print the low-level structure of BOM file.\n\n\
+        By default prints the header, the block index, the named blocks and the free blocks.\n\
+        Blocks can be specified either by index or by name."
+)]
+struct Args {
+    /// Decode the tree stored in the specified block.
+    ///
+    /// Can be specified multiple times.
+    #[arg(short = 't', long = "tree", value_name = "block")]
+    trees: Vec<String>,
+    /// Print hex dump of the specified block.
+    ///
+    /// Can be specified multiple times.
+    #[arg(short = 'x', long = "hex", value_name = "block")]
+    hex: Vec<String>,
+    /// BOM file.
+    #[arg(value_name = "bom")]
+    bom: PathBuf,
+}
+
+fn main() -> ExitCode {
+    match do_main() {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn do_main() -> Result<(), Error> {
+    let args = Args::parse();
+    let file = std::fs::read(&args.bom)
+        .map_err(|e| Error::other(format!("failed to read {}: {}", args.bom.display(), e)))?;
+    // Print as much as possible even if some parts are corrupted.
+    let (magic, version, header) = read_raw_header(&file)?;
+    let blocks = block_slice(&header.blocks, &file).and_then(Blocks::read_be);
+    let named_blocks = block_slice(&header.named_blocks, &file).and_then(NamedBlocks::read_be);
+    let mut output = String::with_capacity(4096);
+    if args.trees.is_empty() && args.hex.is_empty() {
+        write_header(&mut output, &magic, version, &header).map_err(Error::other)?;
+        match blocks.as_ref() {
+            Ok(blocks) => write_blocks(&mut output, blocks).map_err(Error::other)?,
+            Err(e) => writeln!(output, "blocks: {}", e).map_err(Error::other)?,
+        }
+        match named_blocks.as_ref() {
+            Ok(named_blocks) => {
+                write_named_blocks(&mut output, named_blocks).map_err(Error::other)?
+            }
+            Err(e) => writeln!(output, "named blocks: {}", e).map_err(Error::other)?,
+        }
+        print!("{}", output);
+        return Ok(());
+    }
+    let blocks = blocks.map_err(|e| Error::other(format!("failed to read blocks: {}", e)))?;
+    let named_blocks = named_blocks.ok();
+    for block in args.trees.iter() {
+        let i = parse_block(block, named_blocks.as_ref())?;
+        write_tree(&mut output, i, &blocks, &file).map_err(Error::other)?;
+    }
+    for block in args.hex.iter() {
+        let i = parse_block(block, named_blocks.as_ref())?;
+        write_hex_dump(&mut output, i, &blocks, &file).map_err(Error::other)?;
+    }
+    print!("{}", output);
+    Ok(())
+}
+
+/// Decode the header fields without validating them.
+fn read_raw_header(file: &[u8]) -> Result<([u8; 8], u32, BomHeader), Error> {
+    let header = file
+        .get(..HEADER_LEN)
+        .ok_or_else(|| Error::other("the file is too small to contain the header"))?;
+    let mut magic = [0_u8; 8];
+    magic.copy_from_slice(&header[..8]);
+    let field = |i: usize| u32::read_be(&header[i..]);
+    let header = BomHeader {
+        num_non_null_blocks: field(12)?,
+        blocks: Block {
+            offset: field(16)?,
+            len: field(20)?,
+        },
+        named_blocks: Block {
+            offset: field(24)?,
+            len: field(28)?,
+        },
+    };
+    Ok((magic, field(8)?, header))
+}
+
+fn block_slice<'a>(block: &Block, file: &'a [u8]) -> Result<&'a [u8], Error> {
+    let i = block.offset as usize;
+    let j = i + block.len as usize;
+    file.get(i..j)
+        .ok_or_else(|| Error::other("block is out of file bounds"))
+}
+
+/// Parse block index or find the named block.
+fn parse_block(block: &str, named_blocks: Option<&NamedBlocks>) -> Result<u32, Error> {
+    if let Ok(i) = block.parse::<u32>() {
+        return Ok(i);
+    }
+    let name = CString::new(block).map_err(|_| Error::other("invalid block name"))?;
+    named_blocks
+        .and_then(|named_blocks| named_blocks.get(&name))
+        .ok_or_else(|| Error::other(format!("named block `{}` not found", block)))
+}
+
+fn write_header(
+    output: &mut String,
+    magic: &[u8],
+    version: u32,
+    header: &BomHeader,
+) -> std::fmt::Result {
+    writeln!(output, "header")?;
+    writeln!(output, "  magic               {}", magic.escape_ascii())?;
+    writeln!(output, "  version             {}", version)?;
+    writeln!(
+        output,
+        "  num_non_null_blocks {}",
+        header.num_non_null_blocks
+    )?;
+    writeln!(
+        output,
+        "  blocks              offset {} len {}",
+        header.blocks.offset, header.blocks.len
+    )?;
+    writeln!(
+        output,
+        "  named_blocks        offset {} len {}",
+        header.named_blocks.offset, header.named_blocks.len
+    )?;
+    Ok(())
+}
+
+fn write_blocks(output: &mut String, blocks: &Blocks) -> std::fmt::Result {
+    writeln!(output, "blocks")?;
+    for (i, block) in blocks.iter().enumerate() {
+        writeln!(
+            output,
+            "  {:<8} offset {} len {}",
+            i, block.offset, block.len
+        )?;
+    }
+    writeln!(output, "free blocks")?;
+    for block in blocks.free_blocks().iter() {
+        writeln!(output, "  offset {} len {}", block.offset, block.len)?;
+    }
+    Ok(())
+}
+
+fn write_named_blocks(output: &mut String, named_blocks: &NamedBlocks) -> std::fmt::Result {
+    writeln!(output, "named blocks")?;
+    let mut named_blocks: Vec<_> = named_blocks.iter().collect();
+    named_blocks.sort_unstable();
+    for (name, i) in named_blocks.into_iter() {
+        writeln!(output, "  {:<16} {}", name.to_string_lossy(), i)?;
+    }
+    Ok(())
+}
+
+/// Print the tree header and all reachable nodes in breadth-first order.
+fn write_tree(output: &mut String, i: u32, blocks: &Blocks, file: &[u8]) -> std::fmt::Result {
+    let tree = match blocks.get_slice(i, file).and_then(RawTree::read_be) {
+        Ok(tree) => tree,
+        Err(e) => return writeln!(output, "tree {}: {}", i, e),
+    };
+    writeln!(
+        output,
+        "tree {}: root {}, block_len {}, num_entries {}",
+        i, tree.root, tree.block_len, tree.num_entries
+    )?;
+    let mut visited = HashSet::new();
+    let mut nodes = VecDeque::new();
+    nodes.push_back(tree.root);
+    while let Some(i) = nodes.pop_front() {
+        if i == 0 || !visited.insert(i) {
+            continue;
+        }
+        let node = match blocks.get_slice(i, file).and_then(RawTreeNode::read_be) {
+            Ok(node) => node,
+            Err(e) => {
+                writeln!(output, "  node {}: {}", i, e)?;
+                continue;
+            }
+        };
+        writeln!(
+            output,
+            "  node {}: {}, {} entries, next {}, prev {}",
+            i,
+            if node.is_data { "data" } else { "meta" },
+            node.entries.len(),
+            node.next,
+            node.prev
+        )?;
+        for (j, (key, value)) in node.entries.iter().enumerate() {
+            writeln!(output, "    {:<6} key {} value {}", j, key, value)?;
+            if !node.is_data {
+                nodes.push_back(*key);
+            }
+        }
+        nodes.push_back(node.next);
+        nodes.push_back(node.prev);
+    }
+    Ok(())
+}
+
+/// Print the contents of the block in `xxd`-like format with file offsets.
+fn write_hex_dump(output: &mut String, i: u32, blocks: &Blocks, file: &[u8]) -> std::fmt::Result {
+    let (block, data) = match blocks
+        .get(i)
+        .ok_or_else(|| Error::other("invalid block index"))
+        .and_then(|block| Ok((block, blocks.get_slice(i, file)?)))
+    {
+        Ok(x) => x,
+        Err(e) => return writeln!(output, "block {}: {}", i, e),
+    };
+    writeln!(
+        output,
+        "block {}: offset {} len {}",
+        i, block.offset, block.len
+    )?;
+    for (j, line) in data.chunks(16).enumerate() {
+        write!(output, "  {:08x} ", block.offset as usize + j * 16)?;
+        for k in 0..16 {
+            match line.get(k) {
+                Some(byte) => write!(output, " {:02x}", byte)?,
+                None => output.push_str("   "),
+            }
+        }
+        output.push_str("  |");
+        for byte in line.iter() {
+            let ch = if byte.is_ascii_graphic() || *byte == b' ' {
+                *byte as char
+            } else {
+                '.'
+            };
+            output.push(ch);
+        }
+        output.push_str("|\n");
+    }
+    Ok(())
+}
+
+const HEADER_LEN: usize = 32;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_header() {
+        let mut file = b"BOMStorx\x00\x00\x00\x02".to_vec();
+        for field in [3_u32, 512, 16, 1000, 8] {
+            file.extend_from_slice(&field.to_be_bytes());
+        }
+        let (magic, version, header) = read_raw_header(&file).unwrap();
+        let mut output = String::new();
+        write_header(&mut output, &magic, version, &header).unwrap();
+        assert_eq!(
+            "header\n  \
+            magic               BOMStorx\n  \
+            version             2\n  \
+            num_non_null_blocks 3\n  \
+            blocks              offset 512 len 16\n  \
+            named_blocks        offset 1000 len 8\n",
+            output
+        );
+        assert!(read_raw_header(&file[..31]).is_err());
+    }
+
+    #[test]
+    fn hex_dump() {
+        let mut file = vec![0_u8; 8];
+        file.extend_from_slice(b"BOMStore\x00\x01\x7f");
+        file.extend_from_slice(b"xyz");
+        let mut blocks = Blocks::new();
+        let mut writer = std::io::Cursor::new(Vec::new());
+        writer.set_position(8);
+        blocks
+            .append(&mut writer, |writer| {
+                std::io::Write::write_all(writer, &file[8..])
+            })
+            .unwrap();
+        let mut output = String::new();
+        write_hex_dump(&mut output, 1, &blocks, &file).unwrap();
+        assert_eq!(
+            "block 1: offset 8 len 14\n  \
+            00000008  42 4f 4d 53 74 6f 72 65 00 01 7f 78 79 7a        |BOMStore...xyz|\n",
+            output
+        );
+        output.clear();
+        write_hex_dump(&mut output, 2, &blocks, &file).unwrap();
+        assert_eq!("block 2: invalid block index\n", output);
+    }
+}
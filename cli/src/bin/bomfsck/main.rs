@@ -0,0 +1,95 @@
+use std::io::Error;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use stuckliste::receipt::Receipt;
+use stuckliste::BigEndianRead;
+use stuckliste::BlockLayout;
+use stuckliste::Bom;
+use stuckliste::NamedBlocks;
+
+#[derive(Parser)]
+#[clap(
+    arg_required_else_help = true,
+    about = "Check the integrity of BOM file",
+    long_about = "Check the integrity of BOM file.\n\n\
+        Exits with status 0 if no problems were found, 1 if there are problems and 2 on error.\n\n\
+        Receipts are detected automatically and their paths are checked as well;\n\
+        the other files are checked only on the block level."
+)]
+struct Args {
+    /// BOM file.
+    #[arg(value_name = "bom")]
+    bom: PathBuf,
+}
+
+fn main() -> ExitCode {
+    match do_main() {
+        Ok(exit_code) => exit_code,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn do_main() -> Result<ExitCode, Error> {
+    let args = Args::parse();
+    let file = std::fs::read(&args.bom)
+        .map_err(|e| Error::other(format!("failed to read {}: {}", args.bom.display(), e)))?;
+    let findings = if is_receipt(&file) {
+        Receipt::check(&file[..])
+    } else {
+        Bom::check(&file[..], |_| BlockLayout::Opaque)
+    };
+    for finding in findings.iter() {
+        println!("{}", finding);
+    }
+    Ok(if findings.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Returns `true` if the named blocks table contains the paths of the receipt.
+///
+/// Only the named blocks table is decoded, the other header fields may be invalid.
+fn is_receipt(file: &[u8]) -> bool {
+    let field = |i: usize| {
+        file.get(i..i + 4)
+            .and_then(|bytes| u32::read_be(bytes).ok())
+    };
+    let (Some(offset), Some(len)) = (field(24), field(28)) else {
+        return false;
+    };
+    let offset = offset as usize;
+    file.get(offset..offset.saturating_add(len as usize))
+        .and_then(|slice| NamedBlocks::read_be(slice).ok())
+        .is_some_and(|named_blocks| named_blocks.get(Receipt::PATHS).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use stuckliste::receipt::PathComponentVec;
+
+    use super::*;
+
+    #[test]
+    fn detect_receipt() {
+        let mut writer = Cursor::new(Vec::new());
+        Receipt::new(PathComponentVec::new(Vec::new()))
+            .write(&mut writer)
+            .unwrap();
+        let mut file = writer.into_inner();
+        assert!(is_receipt(&file));
+        // wrong magic and version
+        file[..12].fill(0);
+        assert!(Bom::read(&file[..]).is_err());
+        assert!(is_receipt(&file));
+        assert!(!is_receipt(&file[..16]));
+    }
+}
@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::io::Error;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use chrono::DateTime;
+use chrono::Local;
+use nix::unistd::Gid;
+use nix::unistd::Group;
+use nix::unistd::Uid;
+use nix::unistd::User;
+use stuckliste::receipt::FileType;
+use stuckliste::receipt::Metadata;
+
+use crate::LSBOM_TIME;
+
+/// Output format specified via `-p` option.
+///
+/// Fields are separated by tabs. If a field is not applicable to the entry
+/// (e.g. a checksum of a directory) it is left empty to keep the columns aligned.
+pub struct Format {
+    fields: Vec<Field>,
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+impl Format {
+    /// Write the entry to `line` according to the format.
+    ///
+    /// For executables `cpu_type` selects the architecture which size and checksum are printed.
+    /// Returns `false` if the executable does not have such an architecture.
+    pub fn write(
+        &mut self,
+        line: &mut String,
+        path: &Path,
+        metadata: &Metadata,
+        cpu_type: Option<u32>,
+    ) -> Result<bool, Error> {
+        let (size, checksum) = match (metadata, cpu_type) {
+            (Metadata::Executable(exe), Some(cpu_type)) => {
+                match exe.arches().iter().find(|arch| arch.cpu_type() == cpu_type) {
+                    Some(arch) => (Some(arch.size() as u64), Some(arch.checksum())),
+                    None => return Ok(false),
+                }
+            }
+            (Metadata::File(..) | Metadata::Executable(..) | Metadata::Link(..), _) => {
+                (Some(metadata.size()), Some(metadata.checksum()))
+            }
+            (Metadata::Entry(..), _) => (None, None),
+            (_, _) => (Some(metadata.size()), None),
+        };
+        let has_common = !matches!(metadata, Metadata::Entry(..));
+        let target = match metadata {
+            Metadata::Link(link) => Some(link.target()),
+            _ => None,
+        };
+        for (i, field) in self.fields.iter().enumerate() {
+            if i != 0 {
+                line.push('\t');
+            }
+            match field {
+                Field::Checksum => {
+                    if let Some(checksum) = checksum {
+                        write!(line, "{}", checksum).map_err(Error::other)?;
+                    }
+                }
+                Field::FileName => {
+                    write!(line, "{}", path.display()).map_err(Error::other)?;
+                }
+                Field::QuotedFileName => {
+                    write!(line, "\"{}\"", path.display()).map_err(Error::other)?;
+                }
+                Field::Gid if has_common => {
+                    write!(line, "{}", metadata.gid()).map_err(Error::other)?;
+                }
+                Field::GroupName if has_common => {
+                    let name = group_name(&mut self.groups, metadata.gid());
+                    line.push_str(name);
+                }
+                Field::Mode if has_common => {
+                    write!(line, "{:o}", metadata.mode()).map_err(Error::other)?;
+                }
+                Field::SymbolicMode if has_common => {
+                    write_symbolic_mode(line, metadata.mode());
+                }
+                Field::Size => {
+                    if let Some(size) = size {
+                        write!(line, "{}", size).map_err(Error::other)?;
+                    }
+                }
+                Field::FormattedSize => {
+                    if let Some(size) = size {
+                        write_formatted_size(line, size)?;
+                    }
+                }
+                Field::Mtime if has_common => {
+                    write!(line, "{}", metadata.mtime()).map_err(Error::other)?;
+                }
+                Field::FormattedMtime if has_common => {
+                    let timestamp: DateTime<Local> =
+                        metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH).into();
+                    write!(line, "{}", timestamp.format(LSBOM_TIME)).map_err(Error::other)?;
+                }
+                Field::Uid if has_common => {
+                    write!(line, "{}", metadata.uid()).map_err(Error::other)?;
+                }
+                Field::UserName if has_common => {
+                    let name = user_name(&mut self.users, metadata.uid());
+                    line.push_str(name);
+                }
+                Field::UidGid if has_common => {
+                    write!(line, "{}/{}", metadata.uid(), metadata.gid()).map_err(Error::other)?;
+                }
+                Field::UserGroupName if has_common => {
+                    line.push_str(user_name(&mut self.users, metadata.uid()));
+                    line.push('/');
+                    line.push_str(group_name(&mut self.groups, metadata.gid()));
+                }
+                Field::LinkName => {
+                    if let Some(target) = target {
+                        write!(line, "{}", target.display()).map_err(Error::other)?;
+                    }
+                }
+                Field::QuotedLinkName => {
+                    if let Some(target) = target {
+                        write!(line, "\"{}\"", target.display()).map_err(Error::other)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields: Vec<Field> = Vec::with_capacity(s.len());
+        for ch in s.chars() {
+            let field = Field::new(ch)
+                .ok_or_else(|| Error::other(format!("unknown `-p` parameter: `{}`", ch)))?;
+            if fields.contains(&field) {
+                return Err(Error::other(format!(
+                    "`-p` parameter `{}` can only be used once",
+                    ch
+                )));
+            }
+            fields.push(field);
+        }
+        if fields.is_empty() {
+            return Err(Error::other("empty `-p` parameters"));
+        }
+        Ok(Self {
+            fields,
+            users: Default::default(),
+            groups: Default::default(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    /// `c`: 32-bit checksum.
+    Checksum,
+    /// `f`: file name.
+    FileName,
+    /// `F`: file name with quotes.
+    QuotedFileName,
+    /// `g`: group id.
+    Gid,
+    /// `G`: group name.
+    GroupName,
+    /// `m`: file mode.
+    Mode,
+    /// `M`: symbolic file mode.
+    SymbolicMode,
+    /// `s`: file size.
+    Size,
+    /// `S`: formatted file size.
+    FormattedSize,
+    /// `t`: modification time.
+    Mtime,
+    /// `T`: formatted modification time.
+    FormattedMtime,
+    /// `u`: user id.
+    Uid,
+    /// `U`: user name.
+    UserName,
+    /// `/`: user id/group id.
+    UidGid,
+    /// `?`: user name/group name.
+    UserGroupName,
+    /// `l`: link name.
+    LinkName,
+    /// `L`: link name with quotes.
+    QuotedLinkName,
+}
+
+impl Field {
+    fn new(ch: char) -> Option<Self> {
+        use Field::*;
+        let field = match ch {
+            'c' => Checksum,
+            'f' => FileName,
+            'F' => QuotedFileName,
+            'g' => Gid,
+            'G' => GroupName,
+            'm' => Mode,
+            'M' => SymbolicMode,
+            's' => Size,
+            'S' => FormattedSize,
+            't' => Mtime,
+            'T' => FormattedMtime,
+            'u' => Uid,
+            'U' => UserName,
+            '/' => UidGid,
+            '?' => UserGroupName,
+            'l' => LinkName,
+            'L' => QuotedLinkName,
+            _ => return None,
+        };
+        Some(field)
+    }
+}
+
+/// Write file mode in `ls -l` format.
+fn write_symbolic_mode(line: &mut String, mode: u16) {
+    let file_type = match FileType::new(mode) {
+        Ok(FileType::Directory) => 'd',
+        Ok(FileType::Symlink) => 'l',
+        Ok(FileType::BlockDevice) => 'b',
+        Ok(FileType::CharDevice) => 'c',
+        Ok(FileType::Regular) | Err(_) => '-',
+    };
+    line.push(file_type);
+    // (read, write, execute, special bit, special char)
+    for (shift, special, special_char) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = (mode >> shift) & 0o7;
+        line.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        line.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        let execute = bits & 0o1 != 0;
+        let ch = match (mode & special != 0, execute) {
+            (true, true) => special_char,
+            (true, false) => special_char.to_ascii_uppercase(),
+            (false, true) => 'x',
+            (false, false) => '-',
+        };
+        line.push(ch);
+    }
+}
+
+/// Write file size in human-readable format similar to `ls -lh`.
+fn write_formatted_size(line: &mut String, size: u64) -> Result<(), Error> {
+    const UNITS: [char; 6] = ['K', 'M', 'G', 'T', 'P', 'E'];
+    if size < 1024 {
+        return write!(line, "{}B", size).map_err(Error::other);
+    }
+    let mut value = size as f64;
+    let mut unit = UNITS[0];
+    for u in UNITS {
+        value /= 1024.0;
+        unit = u;
+        if value < 1024.0 {
+            break;
+        }
+    }
+    if value < 10.0 {
+        write!(line, "{:.1}{}", value, unit)
+    } else {
+        write!(line, "{:.0}{}", value, unit)
+    }
+    .map_err(Error::other)
+}
+
+fn user_name(cache: &mut HashMap<u32, String>, uid: u32) -> &str {
+    cache.entry(uid).or_insert_with(|| {
+        User::from_uid(Uid::from_raw(uid))
+            .ok()
+            .flatten()
+            .map(|user| user.name)
+            .unwrap_or_else(|| uid.to_string())
+    })
+}
+
+fn group_name(cache: &mut HashMap<u32, String>, gid: u32) -> &str {
+    cache.entry(gid).or_insert_with(|| {
+        Group::from_gid(Gid::from_raw(gid))
+            .ok()
+            .flatten()
+            .map(|group| group.name)
+            .unwrap_or_else(|| gid.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbolic_mode() {
+        for (expected, mode) in [
+            ("-rw-r--r--", 0o100644),
+            ("drwxr-xr-x", 0o40755),
+            ("lrwxrwxrwx", 0o120777),
+            ("-rwsr-sr-t", 0o107755),
+            ("-rwSr-Sr-T", 0o107644),
+            ("crw-rw----", 0o20660),
+            ("brw-rw----", 0o60660),
+        ] {
+            let mut line = String::new();
+            write_symbolic_mode(&mut line, mode);
+            assert_eq!(expected, line);
+        }
+    }
+
+    #[test]
+    fn formatted_size() {
+        for (expected, size) in [
+            ("0B", 0),
+            ("1023B", 1023),
+            ("1.0K", 1024),
+            ("1.5K", 1536),
+            ("10K", 10 * 1024),
+            ("1.0M", 1024 * 1024),
+            ("5.0G", 5 * 1024 * 1024 * 1024),
+        ] {
+            let mut line = String::new();
+            write_formatted_size(&mut line, size).unwrap();
+            assert_eq!(expected, line);
+        }
+    }
+
+    #[test]
+    fn user_and_group_names() {
+        let mut cache = HashMap::new();
+        assert_eq!("root", user_name(&mut cache, 0));
+        // unknown ids are printed as numbers
+        assert_eq!("4294967294", user_name(&mut cache, u32::MAX - 1));
+        let mut cache = HashMap::new();
+        assert_eq!("4294967294", group_name(&mut cache, u32::MAX - 1));
+    }
+
+    #[test]
+    fn parse() {
+        assert!("fmugsc".parse::<Format>().is_ok());
+        assert!("cfFgGmMsStTuU/?lL".parse::<Format>().is_ok());
+        assert!("ff".parse::<Format>().is_err());
+        assert!("x".parse::<Format>().is_err());
+        assert!("".parse::<Format>().is_err());
+    }
+}
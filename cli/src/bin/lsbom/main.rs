@@ -11,7 +11,13 @@ use chrono::Local;
 use clap::Parser;
 use stuckliste::receipt::FileType;
 use stuckliste::receipt::Metadata;
+use stuckliste::receipt::Problem;
 use stuckliste::receipt::Receipt;
+use stuckliste::receipt::Verifier;
+
+use self::format::*;
+
+mod format;
 
 #[derive(Parser)]
 #[clap(arg_required_else_help = true, about = "List contents of a BOM file")]
@@ -44,8 +50,27 @@ struct Args {
     #[arg(long = "arch", value_name = "architecture")]
     arch: Option<String>,
     /// Format the output according to the supplied string.
+    ///
+    /// Supported parameters: `c` (checksum), `f` (file name), `F` (quoted file name),
+    /// `g` (group id), `G` (group name), `m` (file mode), `M` (symbolic file mode),
+    /// `s` (file size), `S` (formatted size), `t` (modification time),
+    /// `T` (formatted modification time), `u` (user id), `U` (user name),
+    /// `/` (user id/group id), `?` (user name/group name), `l` (link name),
+    /// `L` (quoted link name).
     #[arg(short = 'p', value_name = "parameters")]
     format: Option<String>,
+    /// Compare the BOM with the files under the specified directory instead of listing them.
+    ///
+    /// Missing files, extra files and metadata mismatches are printed one per line.
+    /// Exits with non-zero status if there are any.
+    #[arg(long = "verify", value_name = "ROOT")]
+    verify: Option<PathBuf>,
+    /// Do not compare modification times when verifying.
+    #[arg(long = "ignore-mtime", requires = "verify")]
+    ignore_mtime: bool,
+    /// Do not compare user and group ids when verifying.
+    #[arg(long = "ignore-owner", requires = "verify")]
+    ignore_owner: bool,
     /// BOM files.
     #[arg(
         trailing_var_arg = true,
@@ -82,7 +107,7 @@ impl Args {
 
 fn main() -> ExitCode {
     match do_main() {
-        Ok(_) => ExitCode::SUCCESS,
+        Ok(exit_code) => exit_code,
         Err(e) => {
             eprintln!("{e}");
             ExitCode::FAILURE
@@ -95,23 +120,53 @@ fn do_main() -> Result<ExitCode, Error> {
     if args.files.is_empty() {
         return Err(Error::other("no files specified"));
     }
-    if args.format.is_some() {
-        return Err(Error::other("`-p` option is not supported"));
+    if let Some(root) = args.verify.as_deref() {
+        let verifier = Verifier::new()
+            .check_mtime(!args.ignore_mtime)
+            .check_owner(!args.ignore_owner);
+        let mut ok = true;
+        for path in args.files.iter() {
+            ok &= verify_bom(path, root, &verifier)
+                .map_err(|e| Error::other(format!("failed to verify {}: {}", path.display(), e)))?;
+        }
+        return Ok(if ok {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        });
     }
+    let mut format = args
+        .format
+        .as_deref()
+        .map(str::parse::<Format>)
+        .transpose()?;
     for path in args.files.iter() {
-        print_bom(path, &args)
+        print_bom(path, &args, format.as_mut())
             .map_err(|e| Error::other(format!("failed to read {}: {}", path.display(), e)))?;
     }
     Ok(ExitCode::SUCCESS)
 }
 
-fn print_bom(path: &Path, args: &Args) -> Result<(), Error> {
+fn print_bom(path: &Path, args: &Args, format: Option<&mut Format>) -> Result<(), Error> {
     use std::fmt::Write;
     let file = File::open(path)?;
     let bom = Receipt::read(file)?;
     let entries = bom.entries()?;
     let list = args.list();
     let mut line = String::with_capacity(4096);
+    if let Some(format) = format {
+        let cpu_type = args.arch.as_deref().map(arch_to_cpu_type).transpose()?;
+        for (path, metadata) in entries.iter() {
+            if !is_listed(metadata, list) {
+                continue;
+            }
+            line.clear();
+            if format.write(&mut line, path, metadata, cpu_type)? {
+                println!("{}", line);
+            }
+        }
+        return Ok(());
+    }
     for (path, metadata) in entries.iter() {
         line.clear();
         let print = match &metadata {
@@ -210,6 +265,48 @@ fn print_bom(path: &Path, args: &Args) -> Result<(), Error> {
     Ok(())
 }
 
+/// Print the problems and return `true` if there are none.
+fn verify_bom(path: &Path, root: &Path, verifier: &Verifier) -> Result<bool, Error> {
+    let file = File::open(path)?;
+    let bom = Receipt::read(file)?;
+    let problems = verifier.verify(&bom, root)?;
+    for problem in problems.iter() {
+        match problem {
+            Problem::Missing { path } => println!("{}: missing", path.display()),
+            Problem::Extra { path } => println!("{}: extra", path.display()),
+            Problem::TypeMismatch {
+                path,
+                expected,
+                actual,
+            } => println!("{}: type {:?} -> {:?}", path.display(), expected, actual),
+            Problem::Mismatch { path, changes } => {
+                let mut line = format!("{}:", path.display());
+                for (i, change) in changes.iter().enumerate() {
+                    let separator = if i == 0 { " " } else { ", " };
+                    line.push_str(separator);
+                    line.push_str(&change.to_string());
+                }
+                println!("{}", line);
+            }
+        }
+    }
+    Ok(problems.is_empty())
+}
+
+fn is_listed(metadata: &Metadata, list: List) -> bool {
+    match metadata {
+        Metadata::File(..) | Metadata::Executable(..) => list.contains(List::Files),
+        Metadata::Link(..) => list.contains(List::Symlinks),
+        Metadata::Directory(..) => list.contains(List::Directories),
+        Metadata::Device(..) => match FileType::new(metadata.mode()) {
+            Ok(FileType::BlockDevice) => list.contains(List::BlockDevices),
+            Ok(FileType::CharDevice) => list.contains(List::CharDevices),
+            _ => false,
+        },
+        Metadata::Entry(..) => true,
+    }
+}
+
 fn write_common(
     line: &mut String,
     path: &Path,
@@ -242,6 +339,7 @@ fn write_common(
 }
 
 bitflags! {
+    #[derive(Clone, Copy)]
     struct List: u8 {
         const Files        = 0b00000001;
         const BlockDevices = 0b00000010;
@@ -276,4 +374,4 @@ const CPU_TYPE_POWERPC: u32 = 18;
 const CPU_ARCH_ABI64: u32 = 0x01000000;
 const CPU_ARCH_ABI64_32: u32 = 0x02000000;
 
-const LSBOM_TIME: &str = "%a %b %d %H:%M:%S %Y";
+pub(crate) const LSBOM_TIME: &str = "%a %b %d %H:%M:%S %Y";
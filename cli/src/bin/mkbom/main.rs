@@ -1,9 +1,11 @@
 use std::fs::File;
+use std::io::BufReader;
 use std::io::Error;
 use std::path::PathBuf;
 use std::process::ExitCode;
 
 use clap::Parser;
+use stuckliste::receipt::FileList;
 use stuckliste::receipt::ReceiptBuilder;
 
 #[derive(Parser)]
@@ -11,9 +13,53 @@ struct Args {
     /// Create simplified BOM that contains only paths.
     #[arg(short = 's')]
     paths_only: bool,
-    /// File list.
+    /// File list in `lsbom` format to create BOM from instead of the directory.
     #[arg(short = 'i', value_name = "file")]
     file_list: Option<PathBuf>,
+    /// Set the owner's user id of every path.
+    #[arg(short = 'u', value_name = "uid")]
+    uid: Option<u32>,
+    /// Set the owner's group id of every path.
+    #[arg(short = 'g', value_name = "gid")]
+    gid: Option<u32>,
+    /// Clear the specified permission bits (octal) of every path except symbolic links.
+    #[arg(long = "umask", value_name = "mask", value_parser = parse_mode)]
+    umask: Option<u16>,
+    /// Produce the same output for the same input.
+    ///
+    /// Scans directory entries in the order of their names.
+    /// If `SOURCE_DATE_EPOCH` environment variable is set,
+    /// modification times that are newer are replaced with its value.
+    #[arg(long = "reproducible")]
+    reproducible: bool,
+    /// Store SHA-256 digest of every file in a separate named block.
+    ///
+    /// The block is ignored by Apple's tools. Conflicts with `-i`.
+    #[arg(long = "sha256", conflicts_with = "file_list")]
+    sha256: bool,
+    /// Do not include the paths that match the glob pattern.
+    ///
+    /// Patterns without `/` match file names in any directory, e.g. `.DS_Store`.
+    /// Patterns with `/` match paths relative to the directory, e.g. `/build` or `src/**/*.o`.
+    /// Patterns that end with `/` match only directories, e.g. `.git/`.
+    /// Excluded directories are not scanned.
+    /// Can be specified multiple times. Conflicts with `-i`.
+    #[arg(long = "exclude", value_name = "pattern", conflicts_with = "file_list")]
+    exclude: Vec<String>,
+    /// Include the paths that match the glob pattern even if they are excluded.
+    ///
+    /// Can be specified multiple times. Conflicts with `-i`.
+    #[arg(long = "include", value_name = "pattern", conflicts_with = "file_list")]
+    include: Vec<String>,
+    /// Do not include the paths that match the patterns from the `.gitignore`-style file.
+    ///
+    /// Can be specified multiple times. Conflicts with `-i`.
+    #[arg(
+        long = "ignore-file",
+        value_name = "file",
+        conflicts_with = "file_list"
+    )]
+    ignore_files: Vec<PathBuf>,
     /// Input directory.
     #[arg(value_name = "directory")]
     directory: Option<PathBuf>,
@@ -34,20 +80,71 @@ fn main() -> ExitCode {
 
 fn do_main() -> Result<ExitCode, Error> {
     let args = Args::parse();
-    if args.directory.is_none() && args.file_list.is_none() {
-        return Err(Error::other("neither directory nor file list is specified"));
+    let mut builder = ReceiptBuilder::new().paths_only(args.paths_only);
+    if let Some(uid) = args.uid {
+        builder = builder.uid(uid);
     }
-    let Some(output_path) = args.bom else {
-        return Err(Error::other("output file is not specified"));
+    if let Some(gid) = args.gid {
+        builder = builder.gid(gid);
+    }
+    if let Some(umask) = args.umask {
+        builder = builder.umask(umask);
+    }
+    if args.reproducible {
+        builder = builder.reproducible(true);
+        if let Some(epoch) = source_date_epoch()? {
+            builder = builder.max_mtime(epoch);
+        }
+    }
+    builder = builder.sha256(args.sha256);
+    for pattern in args.exclude {
+        builder = builder.exclude(pattern);
+    }
+    for pattern in args.include {
+        builder = builder.include(pattern);
+    }
+    for path in args.ignore_files {
+        builder = builder.ignore_file(path);
+    }
+    let (receipt, output_path) = match (args.file_list, args.directory, args.bom) {
+        // `mkbom -i file bom`: the only positional argument is the output file
+        (Some(file_list), Some(output_path), None) => {
+            let file = BufReader::new(File::open(&file_list)?);
+            let file_list = FileList::read(file).map_err(|e| {
+                Error::other(format!("failed to read {}: {}", file_list.display(), e))
+            })?;
+            (builder.create_from_file_list(file_list)?, output_path)
+        }
+        (Some(..), Some(..), Some(..)) => {
+            return Err(Error::other(
+                "either directory or file list should be specified, not both",
+            ));
+        }
+        (None, Some(directory), Some(output_path)) => (builder.create(&directory)?, output_path),
+        (None, None, _) => {
+            return Err(Error::other("neither directory nor file list is specified"));
+        }
+        (Some(..), None, _) | (None, Some(..), None) => {
+            return Err(Error::other("output file is not specified"));
+        }
     };
-    if let Some(directory) = args.directory {
-        let bom = ReceiptBuilder::new()
-            .paths_only(args.paths_only)
-            .create(&directory)?;
-        let file = File::create(&output_path)?;
-        bom.write(file)?;
-        Ok(ExitCode::SUCCESS)
-    } else {
-        Ok(ExitCode::FAILURE)
+    let file = File::create(&output_path)?;
+    receipt.write(file)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn source_date_epoch() -> Result<Option<u32>, Error> {
+    match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|e| Error::other(format!("invalid SOURCE_DATE_EPOCH `{}`: {}", value, e))),
+        Err(std::env::VarError::NotPresent) => Ok(None),
+        Err(e) => Err(Error::other(format!("invalid SOURCE_DATE_EPOCH: {}", e))),
     }
 }
+
+fn parse_mode(s: &str) -> Result<u16, String> {
+    u16::from_str_radix(s, 8).map_err(|e| e.to_string())
+}
@@ -41,6 +41,44 @@ fn compare_mkbom_s() {
     );
 }
 
+#[cfg_attr(
+    not(target_os = "macos"),
+    ignore = "Only MacOS has the original `lsbom` with `-p` argument"
+)]
+#[test]
+fn compare_lsbom_formatted_size() {
+    do_not_truncate_assertions();
+    let workdir = TempDir::new().unwrap();
+    let bom = workdir.path().join("their.bom");
+    arbtest(|u| {
+        let directory = random_directory(u)?;
+        remove_file(&bom).ok();
+        let status = Command::new("mkbom")
+            .arg(directory.path())
+            .arg(&bom)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let our_output = get_test_bin("lsbom")
+            .args(["-p", "fS"])
+            .arg(&bom)
+            .output()
+            .unwrap();
+        let their_output = Command::new("lsbom")
+            .args(["-p", "fS"])
+            .arg(&bom)
+            .output()
+            .unwrap();
+        assert!(our_output.status.success());
+        assert!(their_output.status.success());
+        similar_asserts::assert_eq!(
+            normalize_output(&our_output.stdout),
+            normalize_output(&their_output.stdout)
+        );
+        Ok(())
+    });
+}
+
 fn compare_mkbom_and_lsbom<F1, F2, F3, F4>(
     mut our_mkbom: F1,
     mut our_lsbom: F2,
@@ -0,0 +1,22 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use libfuzzer_sys::fuzz_target;
+use stuckliste::BlockLayout;
+use stuckliste::Bom;
+use stuckliste::BomReader;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Bom::check(data, |_| BlockLayout::Opaque);
+    let Ok(bom) = Bom::read(data) else {
+        return;
+    };
+    let Ok(mut reader) = BomReader::new(Cursor::new(data)) else {
+        return;
+    };
+    for (_name, i) in bom.named_blocks().iter() {
+        let _ = reader.read_block(i);
+        let _ = reader.read_tree_entries(i);
+    }
+});
@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use stuckliste::receipt::Receipt;
+use stuckliste::receipt::ReceiptView;
+use stuckliste::ReadOptions;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Receipt::check(data);
+    if let Ok(receipt) = Receipt::read(data) {
+        let _ = receipt.entries();
+    }
+    let _ = Receipt::read_with_options(data, ReadOptions::new().strict(true));
+    if let Ok(view) = ReceiptView::new(data) {
+        for component in view.iter().flatten() {
+            let _ = view.path(&component);
+            let _ = component.metadata.to_metadata();
+        }
+    }
+});
@@ -7,6 +7,7 @@ use std::io::Write;
 use crate::BigEndianRead;
 use crate::BigEndianWrite;
 use crate::Blocks;
+use crate::ErrorContext;
 
 /// Read values from BOM blocks.
 pub trait BlockRead<C = ()> {
@@ -43,7 +44,7 @@ impl<T: BigEndianRead, C> BlockRead<C> for T {
     where
         Self: Sized,
     {
-        BigEndianRead::read_be(blocks.slice(i, file)?)
+        BigEndianRead::read_be(blocks.slice(i, file)?).in_block(i)
     }
 }
 
@@ -66,8 +67,9 @@ impl<C> BlockRead<C> for CString {
         _context: &mut C,
     ) -> Result<Self, Error> {
         let block = blocks.slice(i, file)?;
-        let c_str =
-            CStr::from_bytes_with_nul(block).map_err(|_| Error::other("invalid c-string"))?;
+        let c_str = CStr::from_bytes_with_nul(block)
+            .map_err(|_| crate::Error::invalid_data("invalid c-string"))
+            .in_block(i)?;
         Ok(c_str.into())
     }
 }
@@ -95,11 +97,13 @@ impl<C, T: BlockRead<C>> BlockRead<C> for Option<T> {
         context: &mut C,
     ) -> Result<Self, Error> {
         let reader = blocks.slice(i, file)?;
-        let i = u32::read_be(reader)?;
-        if blocks.slice(i, file)?.is_empty() {
+        let j = u32::read_be(reader).in_block(i)?;
+        if blocks.slice(j, file).in_block(i)?.is_empty() {
             Ok(None)
         } else {
-            let value = T::read_block(i, file, blocks, context)?;
+            let value = T::read_block(j, file, blocks, context)
+                .in_block(j)
+                .in_block(i)?;
             Ok(value.into())
         }
     }
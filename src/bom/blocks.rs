@@ -5,6 +5,7 @@ use std::io::Read;
 use std::io::Seek;
 use std::io::Write;
 
+use crate::capacity_hint;
 use crate::BigEndianRead;
 use crate::BigEndianWrite;
 
@@ -23,6 +24,7 @@ pub struct Blocks {
 }
 
 impl Blocks {
+    /// Create block index that contains only the null block.
     pub fn new() -> Self {
         Self {
             // start with the null block
@@ -34,19 +36,40 @@ impl Blocks {
         }
     }
 
+    /// Get the contents of the block with index `index`.
     pub fn slice<'a>(&mut self, index: u32, file: &'a [u8]) -> Result<&'a [u8], Error> {
-        let block = self
-            .blocks
-            .get(index as usize)
-            .ok_or_else(|| Error::other("invalid block index"))?;
+        let slice = self.get_slice(index, file)?;
         #[cfg(test)]
         self.unread_blocks.remove(&(index as usize));
-        let slice = block.slice(file);
         Ok(slice)
     }
 
-    pub fn block(&self, i: u32) -> &Block {
-        &self.blocks[i as usize]
+    /// Get the contents of the block without marking it as read.
+    ///
+    /// Returns an error if the block does not exist or is out of the file bounds.
+    pub fn get_slice<'a>(&self, index: u32, file: &'a [u8]) -> Result<&'a [u8], Error> {
+        let block = self
+            .blocks
+            .get(index as usize)
+            .ok_or_else(|| crate::Error::invalid_block_index(index))?;
+        block
+            .slice(file)
+            .map_err(|_| crate::Error::block_out_of_range(index, block))
+    }
+
+    /// Get block by its index.
+    pub fn get(&self, index: u32) -> Option<&Block> {
+        self.blocks.get(index as usize)
+    }
+
+    /// Iterate over all blocks including the null ones in the order of their indices.
+    pub fn iter(&self) -> impl Iterator<Item = &Block> {
+        self.blocks.iter()
+    }
+
+    /// Get the free list, i.e. the space that can be reused for new blocks.
+    pub fn free_blocks(&self) -> &[Block] {
+        &self.null_blocks
     }
 
     /// No. of non-null blocks.
@@ -57,6 +80,7 @@ impl Blocks {
         self.blocks.iter().filter(|b| !b.is_null()).count()
     }
 
+    /// Write a new block at the current position of `writer` using `f` and return its index.
     pub fn append<W: Write + Seek, F: FnOnce(&mut W) -> Result<(), Error>>(
         &mut self,
         writer: W,
@@ -68,6 +92,7 @@ impl Blocks {
         Ok(index)
     }
 
+    /// Add a new empty block at the current position of `writer` and return its index.
     pub fn append_null<W: Write + Seek>(&mut self, mut writer: W) -> Result<u32, Error> {
         let index = self.next_block_index();
         let offset = writer.stream_position()? as u32;
@@ -75,11 +100,91 @@ impl Blocks {
         Ok(index)
     }
 
+    /// Add a new block reusing the index of a removed block if any.
+    pub(crate) fn insert(&mut self, block: Block) -> u32 {
+        // the first block is always null
+        match self.blocks.iter().skip(1).position(|b| b.is_null()) {
+            Some(i) => {
+                self.blocks[i + 1] = block;
+                (i + 1) as u32
+            }
+            None => {
+                let index = self.next_block_index();
+                self.blocks.push(block);
+                index
+            }
+        }
+    }
+
+    /// Replace the block with index `index`.
+    pub(crate) fn set(&mut self, index: u32, block: Block) -> Result<(), Error> {
+        match self.blocks.get_mut(index as usize) {
+            Some(b) if index != 0 => {
+                *b = block;
+                Ok(())
+            }
+            _ => Err(crate::Error::invalid_block_index(index)),
+        }
+    }
+
+    /// Mark the block as null and return the space that it occupied.
+    pub(crate) fn remove(&mut self, index: u32) -> Result<Block, Error> {
+        match self.blocks.get_mut(index as usize) {
+            Some(b) if index != 0 => Ok(std::mem::replace(b, Block::null())),
+            _ => Err(crate::Error::invalid_block_index(index)),
+        }
+    }
+
+    /// Add the space occupied by `block` to the free list.
+    ///
+    /// Adjacent free blocks are merged.
+    pub(crate) fn free(&mut self, block: Block) {
+        if block.len == 0 {
+            return;
+        }
+        self.null_blocks.push(block);
+        self.null_blocks.sort_unstable_by_key(|b| b.offset);
+        let mut free_blocks: Vec<Block> = Vec::with_capacity(self.null_blocks.len());
+        for block in self.null_blocks.drain(..) {
+            match free_blocks.last_mut() {
+                Some(last) if !last.is_null() && last.offset + last.len == block.offset => {
+                    last.len += block.len;
+                }
+                _ => free_blocks.push(block),
+            }
+        }
+        self.null_blocks = free_blocks;
+    }
+
+    /// Take `len` bytes from the smallest free block that is large enough.
+    ///
+    /// Returns the offset of the allocated space.
+    pub(crate) fn allocate(&mut self, len: u32) -> Option<u32> {
+        if len == 0 {
+            return None;
+        }
+        let (i, block) = self
+            .null_blocks
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, b)| b.len >= len)
+            .min_by_key(|(_, b)| b.len)?;
+        let offset = block.offset;
+        block.offset += len;
+        block.len -= len;
+        if block.len == 0 {
+            self.null_blocks.remove(i);
+        }
+        Some(offset)
+    }
+
+    /// Get the index of the block that will be added next.
     pub fn next_block_index(&self) -> u32 {
         let index = self.blocks.len();
         index as u32
     }
 
+    /// Get the index of the last block.
     pub fn last_block_index(&self) -> Option<u32> {
         let len = self.blocks.len();
         (len != 0).then_some(len as u32 - 1)
@@ -89,13 +194,13 @@ impl Blocks {
 impl BigEndianRead for Blocks {
     fn read_be<R: Read>(mut reader: R) -> Result<Self, Error> {
         let num_blocks = u32::read_be(reader.by_ref())? as usize;
-        let mut blocks = Vec::with_capacity(num_blocks);
+        let mut blocks = Vec::with_capacity(capacity_hint(num_blocks));
         for _ in 0..num_blocks {
             let block = Block::read_be(reader.by_ref())?;
             blocks.push(block);
         }
         let num_free_blocks = u32::read_be(reader.by_ref())? as usize;
-        let mut null_blocks = Vec::with_capacity(num_free_blocks);
+        let mut null_blocks = Vec::with_capacity(capacity_hint(num_free_blocks));
         for _ in 0..num_free_blocks {
             let block = Block::read_be(reader.by_ref())?;
             null_blocks.push(block);
@@ -138,7 +243,7 @@ impl Default for Blocks {
 }
 
 /// A block of data.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[cfg_attr(test, derive(arbitrary::Arbitrary, PartialEq, Eq))]
 pub struct Block {
     /// Byte offset from the start of the file.
@@ -148,20 +253,27 @@ pub struct Block {
 }
 
 impl Block {
-    pub fn slice<'a>(&self, file: &'a [u8]) -> &'a [u8] {
+    /// Get the contents of the block.
+    ///
+    /// Returns an error if the block is out of the file bounds.
+    pub fn slice<'a>(&self, file: &'a [u8]) -> Result<&'a [u8], Error> {
         let i = self.offset as usize;
-        let j = i + self.len as usize;
-        &file[i..j]
+        let j = i.saturating_add(self.len as usize);
+        file.get(i..j)
+            .ok_or_else(|| Error::other("block is out of file bounds"))
     }
 
+    /// Returns `true` if both the offset and the size are zero.
     pub fn is_null(&self) -> bool {
         self.offset == 0 && self.len == 0
     }
 
+    /// Create null block.
     pub fn null() -> Self {
         Self { offset: 0, len: 0 }
     }
 
+    /// Write the block at the current position of `writer` using `f`.
     pub fn from_write<W: Write + Seek, F: FnOnce(&mut W) -> Result<(), Error>>(
         mut writer: W,
         f: F,
@@ -208,6 +320,10 @@ mod tests {
     }
 
     impl Blocks {
+        pub(crate) fn num_unread_blocks(&self) -> usize {
+            self.unread_blocks.len()
+        }
+
         fn print_unread_blocks(&self) {
             for i in self.unread_blocks.iter() {
                 eprintln!("unread block {}: {:?}", i, self.blocks.get(*i));
@@ -0,0 +1,514 @@
+use std::collections::HashSet;
+use std::ffi::CStr;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+use crate::BigEndianRead;
+use crate::Block;
+use crate::BlockLayout;
+use crate::Blocks;
+use crate::Bom;
+use crate::NamedBlocks;
+use crate::RawTree;
+use crate::RawTreeNode;
+use crate::BOM_MAGIC;
+
+/// A problem found by [`Bom::check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// The index of the block that has the problem if applicable.
+    pub block: Option<u32>,
+    /// Human-readable description.
+    pub message: String,
+}
+
+impl Finding {
+    pub(crate) fn new<S: Into<String>>(block: Option<u32>, message: S) -> Self {
+        Self {
+            block,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.block {
+            Some(i) => write!(f, "block {}: {}", i, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl Bom {
+    /// Check the integrity of BOM file and return all problems found.
+    ///
+    /// Checks the header, the block index and the named blocks,
+    /// then traces the blocks starting from the named blocks
+    /// using the layout returned by `layout` for each name
+    /// (e.g. [`Receipt::block_layout`](crate::receipt::Receipt::block_layout))
+    /// and checks the structure of each tree.
+    /// Unlike reading the file the check does not stop on the first problem.
+    pub fn check<F>(file: &[u8], mut layout: F) -> Vec<Finding>
+    where
+        F: FnMut(&CStr) -> BlockLayout,
+    {
+        let mut checker = Checker {
+            file,
+            findings: Vec::new(),
+        };
+        let Some((blocks, named_blocks)) = checker.check_header() else {
+            return checker.findings;
+        };
+        let mut named_blocks: Vec<_> = named_blocks
+            .iter()
+            .map(|(name, i)| (name.to_owned(), i))
+            .collect();
+        named_blocks.sort_unstable();
+        let mut visited = HashSet::new();
+        for (name, i) in named_blocks.into_iter() {
+            if blocks.get(i).is_none() {
+                checker.push(
+                    None,
+                    format!(
+                        "named block `{}` points to non-existent block {}",
+                        name.to_string_lossy(),
+                        i
+                    ),
+                );
+                continue;
+            }
+            checker.trace(&blocks, i, &layout(&name), &mut visited);
+        }
+        checker.findings
+    }
+}
+
+struct Checker<'a> {
+    file: &'a [u8],
+    findings: Vec<Finding>,
+}
+
+impl Checker<'_> {
+    fn push<S: Into<String>>(&mut self, block: Option<u32>, message: S) {
+        self.findings.push(Finding::new(block, message));
+    }
+
+    /// Check the header and the blocks that it references.
+    fn check_header(&mut self) -> Option<(Blocks, NamedBlocks)> {
+        let Some(header) = self.file.get(..HEADER_LEN) else {
+            self.push(None, "the file is too small to contain the header");
+            return None;
+        };
+        if header[..BOM_MAGIC.len()] != BOM_MAGIC[..] {
+            self.push(None, "invalid magic");
+        }
+        let field = |i: usize| u32::read_be(&header[i..]).unwrap_or(0);
+        let version = field(8);
+        if version != Bom::VERSION {
+            self.push(None, format!("unsupported version {}", version));
+        }
+        let num_non_null_blocks = field(12);
+        let tables = [
+            (
+                "block index",
+                Block {
+                    offset: field(16),
+                    len: field(20),
+                },
+            ),
+            (
+                "named blocks",
+                Block {
+                    offset: field(24),
+                    len: field(28),
+                },
+            ),
+        ];
+        let mut slices = Vec::with_capacity(tables.len());
+        for (name, block) in tables.iter() {
+            let slice = self.file.get(block.offset as usize..block.end() as usize);
+            if slice.is_none() {
+                self.push(
+                    None,
+                    format!(
+                        "{} (offset {}, len {}) is out of file bounds",
+                        name, block.offset, block.len
+                    ),
+                );
+            }
+            slices.push(slice);
+        }
+        let blocks = match slices[0].map(Blocks::read_be) {
+            Some(Ok(blocks)) => blocks,
+            Some(Err(e)) => {
+                self.push(None, format!("invalid block index: {}", e));
+                return None;
+            }
+            None => return None,
+        };
+        let actual = blocks.num_non_null_blocks();
+        if num_non_null_blocks as usize != actual {
+            self.push(
+                None,
+                format!(
+                    "the header says there are {} non-null blocks, but there are {}",
+                    num_non_null_blocks, actual
+                ),
+            );
+        }
+        self.check_blocks(&blocks, &tables);
+        let named_blocks = match slices[1].map(NamedBlocks::read_be) {
+            Some(Ok(named_blocks)) => named_blocks,
+            Some(Err(e)) => {
+                self.push(None, format!("invalid named blocks: {}", e));
+                NamedBlocks::new()
+            }
+            None => NamedBlocks::new(),
+        };
+        Some((blocks, named_blocks))
+    }
+
+    /// Check that the blocks are within the file bounds and do not overlap.
+    fn check_blocks(&mut self, blocks: &Blocks, tables: &[(&str, Block)]) {
+        let file_len = self.file.len() as u64;
+        let mut ranges = Vec::new();
+        ranges.push(("header".to_string(), 0, Bom::LEN as u64));
+        for (name, block) in tables.iter() {
+            ranges.push((name.to_string(), block.offset as u64, block.end()));
+        }
+        for (i, block) in blocks.iter().enumerate() {
+            if block.end() > file_len {
+                self.push(
+                    Some(i as u32),
+                    format!(
+                        "offset {} and len {} are out of file bounds ({} bytes)",
+                        block.offset, block.len, file_len
+                    ),
+                );
+            }
+            ranges.push((format!("block {}", i), block.offset as u64, block.end()));
+        }
+        for block in blocks.free_blocks().iter() {
+            ranges.push((
+                format!("free block at offset {}", block.offset),
+                block.offset as u64,
+                block.end(),
+            ));
+        }
+        ranges.retain(|(_, start, end)| start != end);
+        ranges.sort_unstable_by_key(|(_, start, end)| (*start, *end));
+        let mut last: Option<&(String, u64, u64)> = None;
+        for range in ranges.iter() {
+            if let Some(last) = last {
+                if range.1 < last.2 {
+                    self.push(None, format!("{} overlaps with {}", range.0, last.0));
+                }
+            }
+            if last.is_none_or(|last| range.2 > last.2) {
+                last = Some(range);
+            }
+        }
+    }
+
+    /// Check the blocks reachable from block `i` with the specified layout.
+    fn trace(&mut self, blocks: &Blocks, i: u32, layout: &BlockLayout, visited: &mut HashSet<u32>) {
+        let mut queue = vec![(i, layout)];
+        while let Some((i, layout)) = queue.pop() {
+            if i == 0 || !visited.insert(i) {
+                continue;
+            }
+            let data = match blocks.get_slice(i, self.file) {
+                Ok(data) => data,
+                Err(e) => {
+                    self.push(Some(i), e.to_string());
+                    continue;
+                }
+            };
+            if data.is_empty() {
+                // empty blocks denote missing values, e.g. `None`
+                continue;
+            }
+            match layout {
+                BlockLayout::Opaque => {}
+                BlockLayout::Tree(key, value) => {
+                    for (key_block, value_block) in self.check_tree(blocks, i, data) {
+                        queue.push((key_block, key));
+                        queue.push((value_block, value));
+                    }
+                }
+                BlockLayout::Refs(refs) => {
+                    for (offset, layout) in refs.iter() {
+                        match data.get(*offset..).map(u32::read_be) {
+                            Some(Ok(child)) => queue.push((child, layout)),
+                            _ => self.push(
+                                Some(i),
+                                format!("block index at offset {} is out of block bounds", offset),
+                            ),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Check the structure of the tree stored in block `i` level by level.
+    ///
+    /// Returns key and value block indices of all data node entries.
+    fn check_tree(&mut self, blocks: &Blocks, i: u32, data: &[u8]) -> Vec<(u32, u32)> {
+        let tree = match RawTree::read_be(data) {
+            Ok(tree) => tree,
+            Err(e) => {
+                self.push(Some(i), format!("invalid tree: {}", e));
+                return Vec::new();
+            }
+        };
+        let mut nodes = HashSet::new();
+        // The top level is either the root or the list of nodes linked to the root.
+        let mut level = Vec::new();
+        let mut node_block = tree.root;
+        while node_block != 0 {
+            if !nodes.insert(node_block) {
+                self.push(Some(node_block), "loop in `next` links");
+                break;
+            }
+            let Some(node) = self.read_node(blocks, node_block) else {
+                break;
+            };
+            let next = node.next;
+            level.push((node_block, node));
+            node_block = next;
+        }
+        self.check_links(&level, true);
+        let mut entries = Vec::new();
+        while !level.is_empty() {
+            let mut next_level = Vec::new();
+            for (node_block, node) in level.into_iter() {
+                if node.is_data {
+                    if !next_level.is_empty() {
+                        self.push(
+                            Some(node_block),
+                            "data node is on the same level as meta node",
+                        );
+                    }
+                    entries.extend(node.entries);
+                    continue;
+                }
+                for (child, value) in node.entries.into_iter() {
+                    if !nodes.insert(child) {
+                        self.push(
+                            Some(node_block),
+                            format!("node {} is referenced more than once", child),
+                        );
+                        continue;
+                    }
+                    let Some(child_node) = self.read_node(blocks, child) else {
+                        continue;
+                    };
+                    if let Some(last_value) = self.last_value(blocks, &child_node) {
+                        if last_value != value {
+                            self.push(
+                                Some(node_block),
+                                format!(
+                                    "value {} of child node {} is not the last value {} of the child",
+                                    value, child, last_value
+                                ),
+                            );
+                        }
+                    }
+                    next_level.push((child, child_node));
+                }
+            }
+            self.check_links(&next_level, false);
+            level = next_level;
+        }
+        if entries.len() != tree.num_entries as usize {
+            self.push(
+                Some(i),
+                format!(
+                    "tree has {} entries, but the header says {}",
+                    entries.len(),
+                    tree.num_entries
+                ),
+            );
+        }
+        entries
+    }
+
+    /// Check that `next` and `prev` links follow the order of the nodes.
+    ///
+    /// The `next` links of the top level were already followed.
+    fn check_links(&mut self, level: &[(u32, RawTreeNode)], top_level: bool) {
+        for (k, (node_block, node)) in level.iter().enumerate() {
+            let prev = k.checked_sub(1).map(|k| level[k].0).unwrap_or(0);
+            let next = level.get(k + 1).map(|(i, _)| *i).unwrap_or(0);
+            if node.prev != prev {
+                self.push(
+                    Some(*node_block),
+                    format!("`prev` link is {}, expected {}", node.prev, prev),
+                );
+            }
+            if !top_level && node.next != next {
+                self.push(
+                    Some(*node_block),
+                    format!("`next` link is {}, expected {}", node.next, next),
+                );
+            }
+        }
+    }
+
+    fn read_node(&mut self, blocks: &Blocks, i: u32) -> Option<RawTreeNode> {
+        match blocks
+            .get_slice(i, self.file)
+            .and_then(RawTreeNode::read_be)
+        {
+            Ok(node) => Some(node),
+            Err(e) => {
+                self.push(Some(i), format!("invalid tree node: {}", e));
+                None
+            }
+        }
+    }
+
+    /// Get the value block index of the last entry in the subtree.
+    ///
+    /// Problems are reported when the nodes are checked level by level.
+    fn last_value(&self, blocks: &Blocks, node: &RawTreeNode) -> Option<u32> {
+        let (mut child, value) = *node.entries.last()?;
+        if node.is_data {
+            return Some(value);
+        }
+        for _ in 0..MAX_DEPTH {
+            let node = blocks
+                .get_slice(child, self.file)
+                .and_then(RawTreeNode::read_be)
+                .ok()?;
+            let (key, value) = *node.entries.last()?;
+            if node.is_data {
+                return Some(value);
+            }
+            child = key;
+        }
+        None
+    }
+}
+
+impl Block {
+    fn end(&self) -> u64 {
+        self.offset as u64 + self.len as u64
+    }
+}
+
+/// Header length without padding.
+const HEADER_LEN: usize = 32;
+const MAX_DEPTH: usize = 32;
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::io::Seek;
+    use std::io::SeekFrom;
+    use std::io::Write;
+
+    use arbtest::arbtest;
+
+    use super::*;
+    use crate::BlockWrite;
+    use crate::VecTree;
+
+    #[test]
+    fn valid() {
+        arbtest(|u| {
+            let entries: Vec<(u32, u64)> = u.arbitrary()?;
+            let block_len = u.int_in_range(crate::MIN_BLOCK_LEN..=512)?;
+            let tree = VecTree::<u32, u64, ()>::new(entries, block_len);
+            let mut writer = Cursor::new(Vec::new());
+            writer.set_position(Bom::LEN as u64);
+            let mut bom = Bom::new();
+            bom.write_named(c"Tree", writer.by_ref(), &tree, &mut ())
+                .unwrap();
+            bom.write(writer.by_ref()).unwrap();
+            let file = writer.into_inner();
+            let findings = Bom::check(&file, |_| {
+                BlockLayout::tree(BlockLayout::Opaque, BlockLayout::Opaque)
+            });
+            assert_eq!(Vec::<Finding>::new(), findings);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn wrong_num_non_null_blocks() {
+        let mut writer = Cursor::new(Vec::new());
+        writer.set_position(Bom::LEN as u64);
+        let mut bom = Bom::new();
+        bom.write_named(
+            c"Tree",
+            writer.by_ref(),
+            &VecTree::<u32, u32, ()>::new(vec![(1, 2)], 256),
+            &mut (),
+        )
+        .unwrap();
+        bom.write(writer.by_ref()).unwrap();
+        let mut file = writer.into_inner();
+        let actual = bom.blocks().num_non_null_blocks() as u32;
+        for expected in [actual - 1, actual + 1] {
+            file[12..16].copy_from_slice(&expected.to_be_bytes());
+            let findings = Bom::check(&file, |_| BlockLayout::Opaque);
+            let findings: Vec<_> = findings.iter().map(ToString::to_string).collect();
+            assert_eq!(
+                vec![format!(
+                    "the header says there are {} non-null blocks, but there are {}",
+                    expected, actual
+                )],
+                findings
+            );
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        // data node with three entries
+        let mut writer = Cursor::new(Vec::new());
+        writer.set_position(Bom::LEN as u64);
+        let mut blocks = Blocks::new();
+        let tree = VecTree::<u32, u32, ()>::new(vec![(1, 2), (3, 4), (5, 6)], 256);
+        let i = tree
+            .write_block(writer.by_ref(), &mut blocks, &mut ())
+            .unwrap();
+        // tree header is the last block, the node is right before it
+        let mut node =
+            RawTreeNode::read_be(blocks.get_slice(i - 1, writer.get_ref()).unwrap()).unwrap();
+        let tree_block = *blocks.get(i).unwrap();
+        // the node is linked to itself
+        node.next = i - 1;
+        let node_block = *blocks.get(i - 1).unwrap();
+        writer.set_position(node_block.offset as u64);
+        crate::BigEndianWrite::write_be(&node, writer.by_ref()).unwrap();
+        // the tree has more entries than it says
+        writer.set_position(tree_block.offset as u64 + 16);
+        writer.write_all(&2_u32.to_be_bytes()).unwrap();
+        writer.seek(SeekFrom::End(0)).unwrap();
+        let mut bom = Bom::new();
+        *bom.blocks_mut() = blocks;
+        bom.named_blocks_mut().insert(c"Tree".into(), i);
+        bom.named_blocks_mut().insert(c"Missing".into(), 1000);
+        bom.write(writer.by_ref()).unwrap();
+        let mut file = writer.into_inner();
+        // wrong version
+        file[11] = 2;
+        let findings = Bom::check(&file, |_| {
+            BlockLayout::tree(BlockLayout::Opaque, BlockLayout::Opaque)
+        });
+        let findings: Vec<_> = findings.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            vec![
+                "unsupported version 2".to_string(),
+                "named block `Missing` points to non-existent block 1000".to_string(),
+                format!("block {}: loop in `next` links", i - 1),
+                format!("block {}: tree has 3 entries, but the header says 2", i),
+            ],
+            findings
+        );
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::io::Error;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+
+use crate::BigEndianRead;
+use crate::BlockWrite;
+use crate::Blocks;
+use crate::Bom;
+use crate::RawTree;
+use crate::RawTreeNode;
+
+/// The structure of a block that is used to find the blocks that it references.
+///
+/// Used by [`Bom::compact`] to trace reachable blocks.
+#[derive(Debug, Clone)]
+pub enum BlockLayout {
+    /// The block does not reference other blocks or its format is unknown.
+    Opaque,
+    /// [Tree](crate::VecTree) with the specified layouts of keys and values.
+    Tree(Box<BlockLayout>, Box<BlockLayout>),
+    /// The block stores `u32` indices of other blocks at the specified byte offsets.
+    Refs(Vec<(usize, BlockLayout)>),
+}
+
+impl BlockLayout {
+    /// Tree with the specified layouts of keys and values.
+    pub fn tree(key: BlockLayout, value: BlockLayout) -> Self {
+        Self::Tree(Box::new(key), Box::new(value))
+    }
+
+    /// A block that stores the index of another block, e.g. [`Ptr`](crate::receipt::Ptr) or [`Option`].
+    pub fn ptr(target: BlockLayout) -> Self {
+        Self::Refs(vec![(0, target)])
+    }
+}
+
+/// The result of [`Bom::compact`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactStats {
+    /// File size before compaction.
+    pub old_len: u64,
+    /// File size after compaction.
+    pub new_len: u64,
+    /// No. of blocks before compaction excluding the null block.
+    pub old_num_blocks: u32,
+    /// No. of blocks after compaction excluding the null block.
+    pub new_num_blocks: u32,
+}
+
+impl CompactStats {
+    /// The number of bytes reclaimed.
+    pub fn reclaimed(&self) -> u64 {
+        self.old_len.saturating_sub(self.new_len)
+    }
+}
+
+impl Bom {
+    /// Write only the blocks that are reachable from the named blocks to `writer`.
+    ///
+    /// The blocks are traced starting from the named blocks in the order of their names
+    /// using the layout returned by `layout` for each name,
+    /// e.g. [`Receipt::block_layout`](crate::receipt::Receipt::block_layout).
+    /// The live blocks are written consecutively in the order they were found,
+    /// and the block indices that they store are updated accordingly.
+    /// Use [`BlockLayout::Opaque`] for unknown named blocks to keep them as is.
+    pub fn compact<W, F>(
+        &self,
+        file: &[u8],
+        mut writer: W,
+        mut layout: F,
+    ) -> Result<CompactStats, Error>
+    where
+        W: Write + Seek,
+        F: FnMut(&CStr) -> BlockLayout,
+    {
+        let mut named_blocks: Vec<_> = self
+            .named_blocks()
+            .iter()
+            .map(|(name, i)| (name, i, layout(name)))
+            .collect();
+        named_blocks.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        let mut tracer = Tracer::new(self, file);
+        for (_, i, layout) in named_blocks.iter() {
+            tracer.trace(*i, layout)?;
+        }
+        let mut bom = Bom::new();
+        writer.seek(SeekFrom::Start(Bom::LEN as u64))?;
+        for (data, _) in tracer.copy()?.into_iter() {
+            bom.blocks_mut()
+                .append(writer.by_ref(), |writer| writer.write_all(&data))?;
+        }
+        for (name, i, _) in named_blocks.into_iter() {
+            bom.named_blocks_mut()
+                .insert(name.into(), tracer.new_index(i)?);
+        }
+        bom.write(writer.by_ref())?;
+        let new_len = writer.seek(SeekFrom::End(0))?;
+        let num_blocks = |bom: &Bom| bom.blocks().next_block_index().saturating_sub(1);
+        Ok(CompactStats {
+            old_len: file.len() as u64,
+            new_len,
+            old_num_blocks: num_blocks(self),
+            new_num_blocks: num_blocks(&bom),
+        })
+    }
+}
+
+/// A block copied together with the blocks that it references.
+///
+/// The blocks are traced the same way as in [`Bom::compact`]
+/// and are written consecutively with the block indices updated accordingly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct OpaqueBlock {
+    /// The contents of the blocks with the offsets of the block indices that they store.
+    ///
+    /// The first block is the root. The indices start with one and are relative to the root.
+    blocks: Vec<PatchedBlock>,
+}
+
+impl OpaqueBlock {
+    /// Copy block `i` and the blocks that it references according to the `layout`.
+    pub(crate) fn read(
+        bom: &Bom,
+        file: &[u8],
+        i: u32,
+        layout: &BlockLayout,
+    ) -> Result<Self, Error> {
+        let mut tracer = Tracer::new(bom, file);
+        tracer.trace(i, layout)?;
+        Ok(Self {
+            blocks: tracer.copy()?,
+        })
+    }
+}
+
+impl<C> BlockWrite<C> for OpaqueBlock {
+    fn write_block<W: Write + Seek>(
+        &self,
+        mut writer: W,
+        blocks: &mut Blocks,
+        _context: &mut C,
+    ) -> Result<u32, Error> {
+        if self.blocks.is_empty() {
+            // the null block is never copied
+            return Ok(0);
+        }
+        let root = blocks.next_block_index();
+        for (data, patches) in self.blocks.iter() {
+            let mut data = data.clone();
+            for offset in patches.iter() {
+                let i = read_ref(&data, *offset)?;
+                let i = if i == 0 { 0 } else { root + i - 1 };
+                data[*offset..*offset + 4].copy_from_slice(&i.to_be_bytes());
+            }
+            blocks.append(writer.by_ref(), |writer| writer.write_all(&data))?;
+        }
+        Ok(root)
+    }
+}
+
+/// Finds reachable blocks.
+struct Tracer<'a> {
+    bom: &'a Bom,
+    file: &'a [u8],
+    /// Old block index to new block index mapping.
+    new_indices: HashMap<u32, u32>,
+    /// Live blocks in the new order with the offsets of the block indices that they store.
+    live: Vec<(u32, Vec<usize>)>,
+}
+
+impl<'a> Tracer<'a> {
+    fn new(bom: &'a Bom, file: &'a [u8]) -> Self {
+        Self {
+            bom,
+            file,
+            new_indices: Default::default(),
+            live: Default::default(),
+        }
+    }
+
+    fn trace(&mut self, i: u32, layout: &BlockLayout) -> Result<(), Error> {
+        let mut queue = vec![Item::Block(i, layout)];
+        while let Some(item) = queue.pop() {
+            let i = item.index();
+            // the null block is never moved
+            if i == 0 || self.new_indices.contains_key(&i) {
+                continue;
+            }
+            let data = self.bom.blocks().get_slice(i, self.file)?;
+            let mut patches = Vec::new();
+            let first_child = queue.len();
+            match item {
+                // empty blocks denote missing values, e.g. `None`
+                _ if data.is_empty() => {}
+                Item::Block(_, BlockLayout::Opaque) => {}
+                Item::Block(_, BlockLayout::Tree(key, value)) => {
+                    let tree = RawTree::read_be(data)?;
+                    patches.push(RawTree::ROOT_OFFSET);
+                    queue.push(Item::Node(tree.root, key, value));
+                }
+                Item::Block(_, BlockLayout::Refs(refs)) => {
+                    for (offset, layout) in refs.iter() {
+                        let child = read_ref(data, *offset)?;
+                        patches.push(*offset);
+                        queue.push(Item::Block(child, layout));
+                    }
+                }
+                Item::Node(_, key, value) => {
+                    let node = RawTreeNode::read_be(data)?;
+                    patches.push(RawTreeNode::NEXT_OFFSET);
+                    patches.push(RawTreeNode::PREV_OFFSET);
+                    for (j, (key_block, value_block)) in node.entries.iter().enumerate() {
+                        let offset = RawTreeNode::entry_offset(j);
+                        patches.push(offset);
+                        patches.push(offset + 4);
+                        if node.is_data {
+                            queue.push(Item::Block(*key_block, key));
+                        } else {
+                            queue.push(Item::Node(*key_block, key, value));
+                        }
+                        queue.push(Item::Block(*value_block, value));
+                    }
+                    queue.push(Item::Node(node.next, key, value));
+                    queue.push(Item::Node(node.prev, key, value));
+                }
+            }
+            // visit the children in the order they are referenced
+            queue[first_child..].reverse();
+            let new_index = self.live.len() as u32 + 1;
+            self.new_indices.insert(i, new_index);
+            self.live.push((i, patches));
+        }
+        Ok(())
+    }
+
+    /// Get the contents of the live blocks with the block indices updated.
+    fn copy(&self) -> Result<Vec<PatchedBlock>, Error> {
+        let mut blocks = Vec::with_capacity(self.live.len());
+        for (old, patches) in self.live.iter() {
+            let mut data = self.bom.blocks().get_slice(*old, self.file)?.to_vec();
+            for offset in patches.iter() {
+                let old_ref = read_ref(&data, *offset)?;
+                let new_ref = self.new_index(old_ref)?;
+                data[*offset..*offset + 4].copy_from_slice(&new_ref.to_be_bytes());
+            }
+            blocks.push((data, patches.clone()));
+        }
+        Ok(blocks)
+    }
+
+    fn new_index(&self, old: u32) -> Result<u32, Error> {
+        if old == 0 {
+            return Ok(0);
+        }
+        self.new_indices
+            .get(&old)
+            .copied()
+            .ok_or_else(|| Error::other("unreachable block"))
+    }
+}
+
+enum Item<'a> {
+    Block(u32, &'a BlockLayout),
+    Node(u32, &'a BlockLayout, &'a BlockLayout),
+}
+
+impl Item<'_> {
+    fn index(&self) -> u32 {
+        match self {
+            Self::Block(i, ..) | Self::Node(i, ..) => *i,
+        }
+    }
+}
+
+/// Block contents with the offsets of the block indices that it stores.
+type PatchedBlock = (Vec<u8>, Vec<usize>);
+
+fn read_ref(data: &[u8], offset: usize) -> Result<u32, Error> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .ok_or_else(|| Error::other("block index is out of block bounds"))?;
+    u32::read_be(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use arbtest::arbtest;
+
+    use super::*;
+    use crate::VecTree;
+
+    type Tree = VecTree<u32, Option<VecTree<u64, (), ()>>, ()>;
+
+    #[test]
+    fn compact() {
+        arbtest(|u| {
+            let live: Vec<(u32, Option<Vec<u64>>)> = u.arbitrary()?;
+            let live: Vec<_> = live
+                .into_iter()
+                .map(|(key, value)| {
+                    let value = value.map(|keys| {
+                        VecTree::new(keys.into_iter().map(|key| (key, ())).collect(), 128)
+                    });
+                    (key, value)
+                })
+                .collect();
+            let live = Tree::new(live, u.int_in_range(crate::MIN_BLOCK_LEN..=512)?);
+            let dead: Vec<(u32, u64)> = u.arbitrary()?;
+            let dead = VecTree::<u32, u64, ()>::new(dead, 128);
+            let opaque: u64 = u.arbitrary()?;
+            let mut writer = Cursor::new(Vec::new());
+            writer.set_position(Bom::LEN as u64);
+            let mut bom = Bom::new();
+            bom.write_named(c"Dead", writer.by_ref(), &dead, &mut ())
+                .unwrap();
+            bom.write_named(c"Live", writer.by_ref(), &live, &mut ())
+                .unwrap();
+            bom.write_named(c"Opaque", writer.by_ref(), &opaque, &mut ())
+                .unwrap();
+            bom.named_blocks_mut().remove(c"Dead");
+            bom.write(writer.by_ref()).unwrap();
+            let file = writer.into_inner();
+            let mut writer = Cursor::new(Vec::new());
+            let stats = bom
+                .compact(&file, writer.by_ref(), |name| {
+                    if name == c"Live" {
+                        BlockLayout::tree(
+                            BlockLayout::Opaque,
+                            BlockLayout::ptr(BlockLayout::tree(
+                                BlockLayout::Opaque,
+                                BlockLayout::Opaque,
+                            )),
+                        )
+                    } else {
+                        BlockLayout::Opaque
+                    }
+                })
+                .unwrap();
+            let file = writer.into_inner();
+            assert_eq!(file.len() as u64, stats.new_len);
+            assert!(stats.new_len <= stats.old_len);
+            assert!(stats.new_num_blocks < stats.old_num_blocks);
+            let mut bom = Bom::read(&file[..]).unwrap();
+            assert_eq!(2, bom.named_blocks().len());
+            assert_eq!(
+                stats.new_num_blocks,
+                bom.blocks().num_non_null_blocks() as u32
+            );
+            let actual: Tree = bom.read_named(c"Live", &file, &mut ()).unwrap();
+            assert_eq!(live, actual);
+            let actual: u64 = bom.read_named(c"Opaque", &file, &mut ()).unwrap();
+            assert_eq!(opaque, actual);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn opaque_block() {
+        arbtest(|u| {
+            let live: Vec<(u32, Option<Vec<u64>>)> = u.arbitrary()?;
+            let live: Vec<_> = live
+                .into_iter()
+                .map(|(key, value)| {
+                    let value = value.map(|keys| {
+                        VecTree::new(keys.into_iter().map(|key| (key, ())).collect(), 128)
+                    });
+                    (key, value)
+                })
+                .collect();
+            let live = Tree::new(live, u.int_in_range(crate::MIN_BLOCK_LEN..=512)?);
+            let mut writer = Cursor::new(Vec::new());
+            writer.set_position(Bom::LEN as u64);
+            let mut bom = Bom::new();
+            bom.write_named(c"Live", writer.by_ref(), &live, &mut ())
+                .unwrap();
+            bom.write(writer.by_ref()).unwrap();
+            let file = writer.into_inner();
+            let layout = BlockLayout::tree(
+                BlockLayout::Opaque,
+                BlockLayout::ptr(BlockLayout::tree(BlockLayout::Opaque, BlockLayout::Opaque)),
+            );
+            let i = bom.get_named(c"Live").unwrap();
+            let payload = OpaqueBlock::read(&bom, &file, i, &layout).unwrap();
+            // write after some other block to change the indices
+            let mut writer = Cursor::new(Vec::new());
+            writer.set_position(Bom::LEN as u64);
+            let mut bom = Bom::new();
+            bom.write_named(c"Other", writer.by_ref(), &0_u64, &mut ())
+                .unwrap();
+            bom.write_named(c"Live", writer.by_ref(), &payload, &mut ())
+                .unwrap();
+            bom.write(writer.by_ref()).unwrap();
+            let file = writer.into_inner();
+            let mut bom = Bom::read(&file[..]).unwrap();
+            let actual: Tree = bom.read_named(c"Live", &file, &mut ()).unwrap();
+            assert_eq!(live, actual);
+            Ok(())
+        });
+    }
+}
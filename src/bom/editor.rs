@@ -0,0 +1,282 @@
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::io::Error;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+
+use crate::read_vec;
+use crate::BigEndianWrite;
+use crate::Block;
+use crate::BlockWrite;
+use crate::Bom;
+use crate::BomHeader;
+use crate::Warnings;
+
+/// BOM file editor that modifies the file in-place.
+///
+/// New blocks are written to the free space listed in the block index or appended at the end of the file.
+/// The space of the replaced and removed blocks becomes free only after [`commit`](Self::commit),
+/// hence the file remains valid until the new header is written.
+/// The changes are lost unless [`commit`](Self::commit) is called.
+pub struct BomFile<F> {
+    file: F,
+    bom: Bom,
+    /// The space that is freed by the changes but is still referenced by the current header.
+    freed: Vec<Block>,
+    /// The location of the current block index and named blocks.
+    tables: [Block; 2],
+}
+
+impl<F: Read + Write + Seek> BomFile<F> {
+    /// Read BOM header from `file`.
+    pub fn new(mut file: F) -> Result<Self, Error> {
+        let (bom, header) = Bom::read_with_header(&mut file, &mut Warnings::default())?;
+        Ok(Self {
+            file,
+            bom,
+            freed: Vec::new(),
+            tables: [header.blocks, header.named_blocks],
+        })
+    }
+
+    /// Get BOM header including uncommitted changes.
+    pub fn bom(&self) -> &Bom {
+        &self.bom
+    }
+
+    /// Read the contents of the block with index `i`.
+    pub fn read_block(&mut self, i: u32) -> Result<Vec<u8>, Error> {
+        let block = self
+            .bom
+            .blocks()
+            .get(i)
+            .ok_or_else(|| crate::Error::invalid_block_index(i))?;
+        let (offset, len) = (block.offset, block.len);
+        self.file.seek(SeekFrom::Start(offset as u64))?;
+        read_vec(&mut self.file, len as usize)
+    }
+
+    /// Read the contents of the named block.
+    pub fn read_named_block(&mut self, name: &CStr) -> Result<Vec<u8>, Error> {
+        let i = self
+            .bom
+            .get_named(name)
+            .ok_or_else(|| crate::Error::named_block_not_found(name))?;
+        self.read_block(i)
+    }
+
+    /// Write `data` into a new regular block and return its index.
+    ///
+    /// The indices of the removed blocks are reused.
+    pub fn add_block(&mut self, data: &[u8]) -> Result<u32, Error> {
+        let block = self.write_data(data)?;
+        Ok(self.bom.blocks_mut().insert(block))
+    }
+
+    /// Replace the contents of the block with index `i` with `data`.
+    pub fn replace_block(&mut self, i: u32, data: &[u8]) -> Result<(), Error> {
+        let block = self.write_data(data)?;
+        let old = self.bom.blocks_mut().remove(i)?;
+        self.freed.push(old);
+        self.bom.blocks_mut().set(i, block)
+    }
+
+    /// Remove the block with index `i`.
+    ///
+    /// Named blocks that point to this block are not removed.
+    pub fn remove_block(&mut self, i: u32) -> Result<(), Error> {
+        let old = self.bom.blocks_mut().remove(i)?;
+        self.freed.push(old);
+        Ok(())
+    }
+
+    /// Replace the contents of the named block or add a new one.
+    ///
+    /// Returns the block index.
+    pub fn set_named<N: Into<CString>>(&mut self, name: N, data: &[u8]) -> Result<u32, Error> {
+        let name = name.into();
+        match self.bom.get_named(&name) {
+            Some(i) => {
+                self.replace_block(i, data)?;
+                Ok(i)
+            }
+            None => {
+                let i = self.add_block(data)?;
+                self.bom.named_blocks_mut().insert(name, i);
+                Ok(i)
+            }
+        }
+    }
+
+    /// Remove the named block and the regular block that it points to.
+    pub fn remove_named(&mut self, name: &CStr) -> Result<(), Error> {
+        let i = self
+            .bom
+            .named_blocks_mut()
+            .remove(name)
+            .ok_or_else(|| crate::Error::named_block_not_found(name))?;
+        self.remove_block(i)
+    }
+
+    /// Write `value` at the end of the file and point the named block to it.
+    ///
+    /// Only the block that the named block pointed to is freed;
+    /// the blocks that it referenced (e.g. tree nodes) remain allocated.
+    pub fn write_named<N, C, T>(&mut self, name: N, value: &T, context: &mut C) -> Result<(), Error>
+    where
+        N: Into<CString>,
+        T: BlockWrite<C>,
+    {
+        let name = name.into();
+        self.file.seek(SeekFrom::End(0))?;
+        let old = self.bom.get_named(&name);
+        self.bom.write_named(name, &mut self.file, value, context)?;
+        if let Some(i) = old {
+            self.remove_block(i)?;
+        }
+        Ok(())
+    }
+
+    /// Write the block index and the named blocks at the end of the file, then rewrite the header.
+    ///
+    /// The header is written with one call, and it is the only place that is overwritten;
+    /// call [`File::sync_all`](std::fs::File::sync_all) afterwards to make the changes durable.
+    pub fn commit(&mut self) -> Result<(), Error> {
+        let end = self.file.seek(SeekFrom::End(0))?;
+        if end < Bom::LEN as u64 {
+            self.file.seek(SeekFrom::Start(Bom::LEN as u64))?;
+        }
+        // The old tables and the freed blocks are not referenced by the new header.
+        let blocks = self.bom.blocks_mut();
+        for block in self.freed.drain(..).chain(self.tables) {
+            blocks.free(block);
+        }
+        let header = self.bom.write_tables(&mut self.file)?;
+        self.file.flush()?;
+        self.file.rewind()?;
+        header.write_be(&mut self.file)?;
+        self.file.flush()?;
+        let BomHeader {
+            blocks,
+            named_blocks,
+            ..
+        } = header;
+        self.tables = [blocks, named_blocks];
+        Ok(())
+    }
+
+    /// Transform into the underlying file.
+    ///
+    /// Uncommitted changes are lost.
+    pub fn into_inner(self) -> F {
+        self.file
+    }
+
+    fn write_data(&mut self, data: &[u8]) -> Result<Block, Error> {
+        let len: u32 = data
+            .len()
+            .try_into()
+            .map_err(|_| Error::other("the block is too large"))?;
+        match self.bom.blocks_mut().allocate(len) {
+            Some(offset) => {
+                self.file.seek(SeekFrom::Start(offset as u64))?;
+                self.file.write_all(data)?;
+                Ok(Block { offset, len })
+            }
+            None => {
+                let end = self.file.seek(SeekFrom::End(0))?.max(Bom::LEN as u64);
+                self.file.seek(SeekFrom::Start(end))?;
+                Block::from_write(&mut self.file, |file| file.write_all(data))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    use arbtest::arbtest;
+
+    use super::*;
+
+    #[test]
+    fn edit_in_place() {
+        arbtest(|u| {
+            let mut writer = Cursor::new(Vec::new());
+            Bom::new().write(&mut writer).unwrap();
+            let mut expected: HashMap<CString, Vec<u8>> = HashMap::new();
+            let mut committed = expected.clone();
+            let mut file = BomFile::new(writer).unwrap();
+            for _ in 0..u.int_in_range(0..=20)? {
+                let name = CString::new(format!("Block{}", u.int_in_range(0..=3)?)).unwrap();
+                match u.int_in_range(0..=3)? {
+                    0 => {
+                        let data: Vec<u8> = u.arbitrary()?;
+                        file.set_named(name.clone(), &data).unwrap();
+                        expected.insert(name, data);
+                    }
+                    1 => {
+                        let value: u64 = u.arbitrary()?;
+                        file.write_named(name.clone(), &value, &mut ()).unwrap();
+                        expected.insert(name, value.to_be_bytes().to_vec());
+                    }
+                    2 => {
+                        assert_eq!(
+                            expected.remove(&name).is_some(),
+                            file.remove_named(&name).is_ok()
+                        );
+                    }
+                    _ => {
+                        file.commit().unwrap();
+                        committed = expected.clone();
+                    }
+                }
+                for (name, data) in expected.iter() {
+                    assert_eq!(data, &file.read_named_block(name).unwrap());
+                }
+            }
+            // uncommitted changes are lost
+            let bytes = file.into_inner().into_inner();
+            let mut file = BomFile::new(Cursor::new(bytes.clone())).unwrap();
+            assert_eq!(committed.len(), file.bom().named_blocks().len());
+            for (name, data) in committed.iter() {
+                assert_eq!(data, &file.read_named_block(name).unwrap());
+            }
+            // blocks do not overlap
+            let bom = Bom::read(&bytes[..]).unwrap();
+            let mut blocks: Vec<_> = (0..bom.blocks().next_block_index())
+                .filter_map(|i| bom.blocks().get(i))
+                .filter(|block| block.len != 0)
+                .collect();
+            blocks.sort_unstable_by_key(|block| block.offset);
+            for pair in blocks.windows(2) {
+                assert!(pair[0].offset + pair[0].len <= pair[1].offset);
+            }
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn reuse_free_space() {
+        let mut writer = Cursor::new(Vec::new());
+        Bom::new().write(&mut writer).unwrap();
+        let mut file = BomFile::new(writer).unwrap();
+        file.set_named(c"Data", &[1_u8; 4096]).unwrap();
+        file.commit().unwrap();
+        file.remove_named(c"Data").unwrap();
+        file.commit().unwrap();
+        let len = file.file.get_ref().len();
+        file.set_named(c"Data", &[2_u8; 4000]).unwrap();
+        file.set_named(c"Other", &[3_u8; 96]).unwrap();
+        assert_eq!(len, file.file.get_ref().len());
+        file.commit().unwrap();
+        let bytes = file.into_inner().into_inner();
+        let mut file = BomFile::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(vec![2_u8; 4000], file.read_named_block(c"Data").unwrap());
+        assert_eq!(vec![3_u8; 96], file.read_named_block(c"Other").unwrap());
+    }
+}
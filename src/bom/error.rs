@@ -0,0 +1,334 @@
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::io;
+use std::io::ErrorKind;
+
+use crate::Block;
+
+/// BOM decoding error.
+///
+/// All public functions return [`std::io::Error`];
+/// this error is stored inside and can be extracted via [`From<std::io::Error>`].
+/// Each variant carries the [`Trace`] of the blocks that were being decoded when the error occurred.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The entity (e.g. BOM file or tree) has invalid signature.
+    BadMagic {
+        /// The name of the entity.
+        entity: &'static str,
+        /// The blocks being decoded.
+        trace: Trace,
+    },
+    /// The entity (e.g. BOM file or tree) has unsupported version.
+    UnsupportedVersion {
+        /// The name of the entity.
+        entity: &'static str,
+        /// The version from the file.
+        version: u32,
+        /// The blocks being decoded.
+        trace: Trace,
+    },
+    /// The block index is not present in the block index.
+    InvalidBlockIndex {
+        /// Block index.
+        index: u32,
+        /// The blocks being decoded.
+        trace: Trace,
+    },
+    /// The block is out of the file bounds.
+    BlockOutOfRange {
+        /// Block index.
+        index: u32,
+        /// Block offset.
+        offset: u32,
+        /// Block size.
+        len: u32,
+        /// The blocks being decoded.
+        trace: Trace,
+    },
+    /// The named block does not exist.
+    NamedBlockNotFound {
+        /// Block name.
+        name: CString,
+        /// The blocks being decoded.
+        trace: Trace,
+    },
+    /// The structure of the tree is broken.
+    InvalidTree {
+        /// Tree node block index.
+        block: u32,
+        /// What is wrong with the tree.
+        reason: &'static str,
+        /// The blocks being decoded.
+        trace: Trace,
+    },
+    /// The contents of the block are invalid.
+    InvalidData {
+        /// What is wrong with the data.
+        message: String,
+        /// The blocks being decoded.
+        trace: Trace,
+    },
+    /// Any other error, e.g. unexpected end of block.
+    Io {
+        /// The original error.
+        source: io::Error,
+        /// The blocks being decoded.
+        trace: Trace,
+    },
+}
+
+impl Error {
+    /// Get the blocks that were being decoded when the error occurred.
+    pub fn trace(&self) -> &Trace {
+        match self {
+            Self::BadMagic { trace, .. }
+            | Self::UnsupportedVersion { trace, .. }
+            | Self::InvalidBlockIndex { trace, .. }
+            | Self::BlockOutOfRange { trace, .. }
+            | Self::NamedBlockNotFound { trace, .. }
+            | Self::InvalidTree { trace, .. }
+            | Self::InvalidData { trace, .. }
+            | Self::Io { trace, .. } => trace,
+        }
+    }
+
+    fn trace_mut(&mut self) -> &mut Trace {
+        match self {
+            Self::BadMagic { trace, .. }
+            | Self::UnsupportedVersion { trace, .. }
+            | Self::InvalidBlockIndex { trace, .. }
+            | Self::BlockOutOfRange { trace, .. }
+            | Self::NamedBlockNotFound { trace, .. }
+            | Self::InvalidTree { trace, .. }
+            | Self::InvalidData { trace, .. }
+            | Self::Io { trace, .. } => trace,
+        }
+    }
+
+    pub(crate) fn bad_magic(entity: &'static str) -> io::Error {
+        Self::BadMagic {
+            entity,
+            trace: Default::default(),
+        }
+        .into()
+    }
+
+    pub(crate) fn unsupported_version(entity: &'static str, version: u32) -> io::Error {
+        Self::UnsupportedVersion {
+            entity,
+            version,
+            trace: Default::default(),
+        }
+        .into()
+    }
+
+    pub(crate) fn invalid_block_index(index: u32) -> io::Error {
+        Self::InvalidBlockIndex {
+            index,
+            trace: Default::default(),
+        }
+        .into()
+    }
+
+    pub(crate) fn block_out_of_range(index: u32, block: &Block) -> io::Error {
+        Self::BlockOutOfRange {
+            index,
+            offset: block.offset,
+            len: block.len,
+            trace: Default::default(),
+        }
+        .into()
+    }
+
+    pub(crate) fn named_block_not_found(name: &CStr) -> io::Error {
+        Self::NamedBlockNotFound {
+            name: name.into(),
+            trace: Default::default(),
+        }
+        .into()
+    }
+
+    pub(crate) fn invalid_tree(block: u32, reason: &'static str) -> io::Error {
+        Self::InvalidTree {
+            block,
+            reason,
+            trace: Default::default(),
+        }
+        .into()
+    }
+
+    pub(crate) fn invalid_data<S: Into<String>>(message: S) -> io::Error {
+        Self::InvalidData {
+            message: message.into(),
+            trace: Default::default(),
+        }
+        .into()
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadMagic { entity, .. } => write!(f, "invalid {} signature", entity)?,
+            Self::UnsupportedVersion {
+                entity, version, ..
+            } => write!(f, "unsupported {} version {}", entity, version)?,
+            Self::InvalidBlockIndex { index, .. } => write!(f, "invalid block index {}", index)?,
+            Self::BlockOutOfRange {
+                index, offset, len, ..
+            } => write!(
+                f,
+                "block {} (offset {}, size {}) is out of file bounds",
+                index, offset, len
+            )?,
+            Self::NamedBlockNotFound { name, .. } => {
+                write!(f, "named block `{}` not found", name.to_string_lossy())?
+            }
+            Self::InvalidTree { block, reason, .. } => {
+                write!(f, "invalid tree node {}: {}", block, reason)?
+            }
+            Self::InvalidData { message, .. } => write!(f, "{}", message)?,
+            Self::Io { source, .. } => write!(f, "{}", source)?,
+        }
+        let trace = self.trace();
+        if !trace.is_empty() {
+            write!(f, " ({})", trace)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(other: io::Error) -> Self {
+        match other.downcast::<Self>() {
+            Ok(error) => error,
+            Err(other) => Self::Io {
+                source: other,
+                trace: Default::default(),
+            },
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(other: Error) -> Self {
+        let kind = match &other {
+            Error::Io { source, .. } => source.kind(),
+            _ => ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, other)
+    }
+}
+
+/// The blocks that were being decoded when the error occurred.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    /// The named block from which the decoding started.
+    pub named_block: Option<CString>,
+    /// Block indices from the outermost to the innermost one.
+    pub blocks: Vec<u32>,
+}
+
+impl Trace {
+    /// Returns `true` if neither the named block nor block indices are known.
+    pub fn is_empty(&self) -> bool {
+        self.named_block.is_none() && self.blocks.is_empty()
+    }
+}
+
+impl Display for Trace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(name) = self.named_block.as_ref() {
+            write!(f, "named block `{}`", name.to_string_lossy())?;
+            if !self.blocks.is_empty() {
+                f.write_str(", ")?;
+            }
+        }
+        if !self.blocks.is_empty() {
+            f.write_str("blocks ")?;
+            for (i, block) in self.blocks.iter().enumerate() {
+                if i != 0 {
+                    f.write_str(" -> ")?;
+                }
+                write!(f, "{}", block)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Add decoding context to I/O errors.
+pub(crate) trait ErrorContext {
+    /// Add block index `i` to the beginning of the trace.
+    fn in_block(self, i: u32) -> Self;
+
+    /// Set the name of the block from which the decoding started.
+    fn in_named_block(self, name: &CStr) -> Self;
+}
+
+impl<T> ErrorContext for Result<T, io::Error> {
+    fn in_block(self, i: u32) -> Self {
+        self.map_err(|e| {
+            let mut error = Error::from(e);
+            let blocks = &mut error.trace_mut().blocks;
+            // the same block might be added by the caller and the callee
+            if blocks.first() != Some(&i) {
+                blocks.insert(0, i);
+            }
+            error.into()
+        })
+    }
+
+    fn in_named_block(self, name: &CStr) -> Self {
+        self.map_err(|e| {
+            let mut error = Error::from(e);
+            error
+                .trace_mut()
+                .named_block
+                .get_or_insert_with(|| name.into());
+            error.into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trace() {
+        let result: Result<(), io::Error> = Err(Error::invalid_block_index(42));
+        let error = result
+            .in_block(3)
+            .in_block(2)
+            .in_block(2)
+            .in_named_block(c"Paths")
+            .unwrap_err();
+        assert_eq!(ErrorKind::InvalidData, error.kind());
+        assert_eq!(
+            "invalid block index 42 (named block `Paths`, blocks 2 -> 3)",
+            error.to_string()
+        );
+        let error = Error::from(error);
+        assert!(matches!(error, Error::InvalidBlockIndex { index: 42, .. }));
+        assert_eq!(vec![2, 3], error.trace().blocks);
+        // foreign errors keep their kind
+        let result: Result<(), io::Error> = Err(ErrorKind::UnexpectedEof.into());
+        let error = result.in_block(1).unwrap_err();
+        assert_eq!(ErrorKind::UnexpectedEof, error.kind());
+        assert_eq!(vec![1], Error::from(error).trace().blocks);
+    }
+}
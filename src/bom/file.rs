@@ -1,5 +1,7 @@
+use std::cmp::Ordering;
 use std::ffi::CStr;
 use std::ffi::CString;
+use std::io::Cursor;
 use std::io::Error;
 use std::io::Read;
 use std::io::Seek;
@@ -12,7 +14,13 @@ use crate::Block;
 use crate::BlockRead;
 use crate::BlockWrite;
 use crate::Blocks;
+use crate::ErrorContext;
+use crate::Finding;
 use crate::NamedBlocks;
+use crate::ReadOptions;
+use crate::TreeRange;
+use crate::VecTree;
+use crate::Warnings;
 
 /// BOM file low-level representation.
 ///
@@ -33,7 +41,7 @@ impl Default for Bom {
 }
 
 impl Bom {
-    const VERSION: u32 = 1;
+    pub(crate) const VERSION: u32 = 1;
 
     /// Bom length with padding.
     pub(crate) const LEN: usize = 512;
@@ -56,6 +64,14 @@ impl Bom {
         &self.named_blocks
     }
 
+    pub(crate) fn blocks_mut(&mut self) -> &mut Blocks {
+        &mut self.blocks
+    }
+
+    pub(crate) fn named_blocks_mut(&mut self) -> &mut NamedBlocks {
+        &mut self.named_blocks
+    }
+
     /// Write `value` into a new named block.
     pub fn write_named<N, W, C, T>(
         &mut self,
@@ -89,8 +105,10 @@ impl Bom {
         let i = self
             .named_blocks
             .get(name)
-            .ok_or_else(|| Error::other("named block not found"))?;
+            .ok_or_else(|| crate::Error::named_block_not_found(name))?;
         T::read_block(i, file, &mut self.blocks, context)
+            .in_block(i)
+            .in_named_block(name)
     }
 
     /// Read a value of type `T` from a regular block.
@@ -100,43 +118,105 @@ impl Bom {
         file: &[u8],
         context: &mut C,
     ) -> Result<T, Error> {
-        T::read_block(block_index, file, &mut self.blocks, context)
+        T::read_block(block_index, file, &mut self.blocks, context).in_block(block_index)
+    }
+
+    /// Find an entry in the tree stored in regular block `block_index`.
+    ///
+    /// See [`VecTree::find`](crate::VecTree::find).
+    pub fn find_regular<C, K, V, F>(
+        &mut self,
+        block_index: u32,
+        file: &[u8],
+        context: &mut C,
+        compare: F,
+    ) -> Result<Option<(K, V)>, Error>
+    where
+        K: BlockRead<C>,
+        V: BlockRead<C>,
+        F: FnMut(&K, &V) -> Ordering,
+    {
+        VecTree::find(block_index, file, &mut self.blocks, context, compare)
+    }
+
+    /// Iterate over the entries of the tree stored in regular block `block_index`.
+    ///
+    /// See [`VecTree::range`](crate::VecTree::range).
+    pub fn range_regular<'a, C, K, V, F>(
+        &'a mut self,
+        block_index: u32,
+        file: &'a [u8],
+        context: &'a mut C,
+        compare: F,
+    ) -> Result<TreeRange<'a, K, V, C>, Error>
+    where
+        K: BlockRead<C>,
+        V: BlockRead<C>,
+        F: FnMut(&K, &V) -> Ordering,
+    {
+        VecTree::range(block_index, file, &mut self.blocks, context, compare)
     }
 
     /// Read BOM header from `file`.
     pub fn read(file: &[u8]) -> Result<Self, Error> {
-        let mut reader = file;
-        let mut magic = [0_u8; BOM_MAGIC.len()];
-        reader.read_exact(&mut magic[..])?;
-        if magic != BOM_MAGIC {
-            return Err(Error::other("not a bom store"));
+        Self::read_from(Cursor::new(file))
+    }
+
+    /// Read BOM header from `file` using the specified `options`.
+    ///
+    /// Returns the warnings found in lenient mode.
+    pub fn read_with_options(
+        file: &[u8],
+        options: ReadOptions,
+    ) -> Result<(Self, Vec<Finding>), Error> {
+        let mut warnings = Warnings::new(options);
+        let (bom, _) = Self::read_with_header(Cursor::new(file), &mut warnings)?;
+        Ok((bom, warnings.take()))
+    }
+
+    /// Read BOM header from `reader`.
+    ///
+    /// Only the header, the block index and the named blocks are read.
+    /// Use [`BomReader`](crate::BomReader) to read the blocks on demand.
+    pub fn read_from<R: Read + Seek>(reader: R) -> Result<Self, Error> {
+        Self::read_with_header(reader, &mut Warnings::default()).map(|(bom, _)| bom)
+    }
+
+    pub(crate) fn read_with_header<R: Read + Seek>(
+        mut reader: R,
+        warnings: &mut Warnings,
+    ) -> Result<(Self, BomHeader), Error> {
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        reader.rewind()?;
+        let header = BomHeader::read_be(reader.by_ref())?;
+        // Both tables should be within the file to not allocate memory for non-existent entries.
+        for block in [header.blocks, header.named_blocks] {
+            if block.offset as u64 + block.len as u64 > file_len {
+                return Err(crate::Error::invalid_data(
+                    "block index or named blocks are out of file bounds",
+                ));
+            }
         }
-        let version = u32::read_be(reader.by_ref())?;
-        if version != Self::VERSION {
-            return Err(Error::other("unsupported bom store version"));
+        reader.seek(SeekFrom::Start(header.blocks.offset as u64))?;
+        let blocks = Blocks::read_be(reader.by_ref().take(header.blocks.len as u64))?;
+        reader.seek(SeekFrom::Start(header.named_blocks.offset as u64))?;
+        let named_blocks =
+            NamedBlocks::read_be(reader.by_ref().take(header.named_blocks.len as u64))?;
+        if (header.num_non_null_blocks as usize) < blocks.num_non_null_blocks() {
+            warnings.report(
+                None,
+                format!(
+                    "the header says there are {} non-null blocks, but there are {}",
+                    header.num_non_null_blocks,
+                    blocks.num_non_null_blocks()
+                ),
+            )?;
         }
-        let num_non_null_blocks = u32::read_be(reader.by_ref())?;
-        let blocks = Block {
-            offset: u32::read_be(reader.by_ref())?,
-            len: u32::read_be(reader.by_ref())?,
-        };
-        let named_blocks = Block {
-            offset: u32::read_be(reader.by_ref())?,
-            len: u32::read_be(reader.by_ref())?,
-        };
-        let blocks = Blocks::read_be(blocks.slice(file))?;
-        let named_blocks = NamedBlocks::read_be(named_blocks.slice(file))?;
-        // TODO ???
-        debug_assert!(
-            num_non_null_blocks as usize >= blocks.num_non_null_blocks(),
-            "num_non_null_blocks = {num_non_null_blocks}, \
-            blocks.num_non_null_blocks = {}",
-            blocks.num_non_null_blocks()
-        );
-        Ok(Self {
+        let bom = Self {
             blocks,
             named_blocks,
-        })
+        };
+        Ok((bom, header))
     }
 
     /// Write BOM header at the beginning of `writer`.
@@ -150,28 +230,76 @@ impl Bom {
             // ensure that we have enough space for the header
             writer.seek(SeekFrom::Start(Bom::LEN as u64))?;
         }
-        let named_blocks =
-            Block::from_write(writer.by_ref(), |writer| self.named_blocks.write_be(writer))?;
-        let blocks = Block::from_write(writer.by_ref(), |writer| self.blocks.write_be(writer))?;
+        let header = self.write_tables(writer.by_ref())?;
         // write the header at the beginning
         writer.rewind()?;
-        writer.write_all(&BOM_MAGIC[..])?;
-        Self::VERSION.write_be(writer.by_ref())?;
-        (self.blocks.num_non_null_blocks() as u32).write_be(writer.by_ref())?;
-        blocks.offset.write_be(writer.by_ref())?;
-        blocks.len.write_be(writer.by_ref())?;
-        named_blocks.offset.write_be(writer.by_ref())?;
-        named_blocks.len.write_be(writer.by_ref())?;
-        writer.write_all(&[0_u8; HEADER_PADDING])?;
+        header.write_be(writer.by_ref())?;
         Ok(())
     }
+
+    /// Write the named blocks and the block index at the current position.
+    pub(crate) fn write_tables<W: Write + Seek>(&self, mut writer: W) -> Result<BomHeader, Error> {
+        let named_blocks =
+            Block::from_write(writer.by_ref(), |writer| self.named_blocks.write_be(writer))?;
+        let blocks = Block::from_write(writer.by_ref(), |writer| self.blocks.write_be(writer))?;
+        Ok(BomHeader {
+            num_non_null_blocks: self.blocks.num_non_null_blocks() as u32,
+            blocks,
+            named_blocks,
+        })
+    }
+}
+
+/// BOM file header.
+///
+/// Stores the locations of the block index and the named blocks.
+#[derive(Debug, Clone, Copy)]
+pub struct BomHeader {
+    /// No. of non-null blocks as recorded in the header.
+    pub num_non_null_blocks: u32,
+    /// The location of the block index.
+    pub blocks: Block,
+    /// The location of the named blocks.
+    pub named_blocks: Block,
+}
+
+impl BigEndianRead for BomHeader {
+    fn read_be<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let mut magic = [0_u8; BOM_MAGIC.len()];
+        reader.read_exact(&mut magic[..])?;
+        if magic != BOM_MAGIC {
+            return Err(crate::Error::bad_magic("BOM"));
+        }
+        let version = u32::read_be(reader.by_ref())?;
+        if version != Bom::VERSION {
+            return Err(crate::Error::unsupported_version("BOM", version));
+        }
+        let num_non_null_blocks = u32::read_be(reader.by_ref())?;
+        let blocks = Block::read_be(reader.by_ref())?;
+        let named_blocks = Block::read_be(reader.by_ref())?;
+        Ok(Self {
+            num_non_null_blocks,
+            blocks,
+            named_blocks,
+        })
+    }
 }
 
-const BOM_MAGIC: [u8; 8] = *b"BOMStore";
+impl BigEndianWrite for BomHeader {
+    fn write_be<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        // write the header with one call to not leave it half-written
+        let mut header = Vec::with_capacity(Bom::LEN);
+        header.extend_from_slice(&BOM_MAGIC[..]);
+        Bom::VERSION.write_be(&mut header)?;
+        self.num_non_null_blocks.write_be(&mut header)?;
+        self.blocks.write_be(&mut header)?;
+        self.named_blocks.write_be(&mut header)?;
+        header.resize(Bom::LEN, 0_u8);
+        writer.write_all(&header[..])
+    }
+}
 
-/// Bom length without padding.
-const REAL_HEADER_LEN: usize = 32;
-const HEADER_PADDING: usize = Bom::LEN - REAL_HEADER_LEN;
+pub(crate) const BOM_MAGIC: [u8; 8] = *b"BOMStore";
 
 #[cfg(test)]
 mod tests {
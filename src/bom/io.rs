@@ -1,4 +1,5 @@
 use std::io::Error;
+use std::io::ErrorKind;
 use std::io::Read;
 use std::io::Write;
 
@@ -85,3 +86,23 @@ impl BigEndianWrite for () {
         Ok(())
     }
 }
+
+/// Limit the no. of elements that are preallocated when the count is read from an untrusted file.
+///
+/// The elements are still read one by one, hence the total no. of elements
+/// is limited by the size of the data that contains them.
+pub(crate) fn capacity_hint(count: usize) -> usize {
+    count.min(MAX_CAPACITY_HINT)
+}
+
+/// Read exactly `len` bytes without allocating more memory than `reader` contains.
+pub(crate) fn read_vec<R: Read>(reader: R, len: usize) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::with_capacity(capacity_hint(len));
+    reader.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    Ok(buf)
+}
+
+const MAX_CAPACITY_HINT: usize = 4096;
@@ -1,13 +1,25 @@
 mod block_io;
 mod blocks;
+mod check;
+mod compact;
+mod editor;
+mod error;
 mod file;
 mod io;
 mod named_blocks;
+mod options;
+mod reader;
 mod tree;
 
 pub use self::block_io::*;
-pub(crate) use self::blocks::*;
+pub use self::blocks::*;
+pub use self::check::*;
+pub use self::compact::*;
+pub use self::editor::*;
+pub use self::error::*;
 pub use self::file::*;
 pub use self::io::*;
 pub use self::named_blocks::*;
+pub use self::options::*;
+pub use self::reader::*;
 pub use self::tree::*;
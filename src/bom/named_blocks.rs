@@ -6,6 +6,7 @@ use std::io::ErrorKind;
 use std::io::Read;
 use std::io::Write;
 
+use crate::capacity_hint;
 use crate::BigEndianRead;
 use crate::BigEndianWrite;
 
@@ -50,6 +51,11 @@ impl NamedBlocks {
         self.blocks.get(name).copied()
     }
 
+    /// Iterate over block names and indices in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&CStr, u32)> {
+        self.blocks.iter().map(|(name, i)| (name.as_c_str(), *i))
+    }
+
     /// Transform into inner representation.
     pub fn into_inner(self) -> HashMap<CString, u32> {
         self.blocks
@@ -59,7 +65,7 @@ impl NamedBlocks {
 impl BigEndianRead for NamedBlocks {
     fn read_be<R: Read>(mut reader: R) -> Result<Self, Error> {
         let num_named_blocks = u32::read_be(reader.by_ref())? as usize;
-        let mut blocks = HashMap::with_capacity(num_named_blocks);
+        let mut blocks = HashMap::with_capacity(capacity_hint(num_named_blocks));
         for _ in 0..num_named_blocks {
             let index = u32::read_be(reader.by_ref())?;
             let len = u8::read_be(reader.by_ref())? as usize;
@@ -69,7 +75,8 @@ impl BigEndianRead for NamedBlocks {
             if let Some(i) = name.iter().position(|b| *b == 0) {
                 name.truncate(i);
             };
-            let name = CString::new(name).map_err(|_| ErrorKind::InvalidData)?;
+            let name = CString::new(name)
+                .map_err(|_| crate::Error::invalid_data("invalid named block name"))?;
             blocks.insert(name, index);
         }
         Ok(Self { blocks })
@@ -80,7 +87,10 @@ impl BigEndianWrite for NamedBlocks {
     fn write_be<W: Write>(&self, mut writer: W) -> Result<(), Error> {
         let num_named_blocks = self.blocks.len() as u32;
         num_named_blocks.write_be(writer.by_ref())?;
-        for (name, index) in self.blocks.iter() {
+        // Sort by name to produce the same output for the same blocks.
+        let mut blocks: Vec<_> = self.blocks.iter().collect();
+        blocks.sort_unstable();
+        for (name, index) in blocks.into_iter() {
             let name = name.to_bytes();
             let len = name.len();
             if len > u8::MAX as usize {
@@ -0,0 +1,69 @@
+use std::io::Error;
+
+use crate::Finding;
+
+/// Options for reading BOM files.
+///
+/// By default the files are read in lenient mode:
+/// deviations from the known format (e.g. unexpected values of unknown fields)
+/// are reported as warnings, and the data is decoded as far as possible.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReadOptions {
+    strict: bool,
+}
+
+impl ReadOptions {
+    /// Create options for lenient mode.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return an error on any deviation from the known format instead of a warning.
+    pub fn strict(mut self, value: bool) -> Self {
+        self.strict = value;
+        self
+    }
+
+    /// Returns `true` if any deviation is an error.
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+}
+
+/// Deviations from the known format found while reading the file.
+#[derive(Debug, Default)]
+#[cfg_attr(test, derive(PartialEq, Eq))]
+pub(crate) struct Warnings {
+    strict: bool,
+    warnings: Vec<Finding>,
+}
+
+impl Warnings {
+    pub(crate) fn new(options: ReadOptions) -> Self {
+        Self {
+            strict: options.strict,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Returns an error in strict mode, otherwise records the warning.
+    pub(crate) fn report<S: Into<String>>(
+        &mut self,
+        block: Option<u32>,
+        message: S,
+    ) -> Result<(), Error> {
+        if self.strict {
+            return Err(crate::Error::invalid_data(message));
+        }
+        self.warnings.push(Finding::new(block, message));
+        Ok(())
+    }
+
+    pub(crate) fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    pub(crate) fn take(&mut self) -> Vec<Finding> {
+        std::mem::take(&mut self.warnings)
+    }
+}
@@ -0,0 +1,111 @@
+use std::ffi::CStr;
+use std::io::Error;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+
+use crate::read_tree_entries;
+use crate::read_vec;
+use crate::Bom;
+use crate::ErrorContext;
+
+/// BOM file reader that reads the blocks on demand.
+///
+/// Only the header, the block index and the named blocks are kept in memory.
+/// Each block is read with one seek and one read call;
+/// wrap slow readers in [`BufReader`](std::io::BufReader).
+pub struct BomReader<R> {
+    reader: R,
+    bom: Bom,
+}
+
+impl<R: Read + Seek> BomReader<R> {
+    /// Read BOM header from `reader`.
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let bom = Bom::read_from(reader.by_ref())?;
+        Ok(Self { reader, bom })
+    }
+
+    /// Get BOM header.
+    pub fn bom(&self) -> &Bom {
+        &self.bom
+    }
+
+    /// Get block index by name.
+    pub fn get_named(&self, name: &CStr) -> Option<u32> {
+        self.bom.get_named(name)
+    }
+
+    /// Read the contents of the block with index `i`.
+    pub fn read_block(&mut self, i: u32) -> Result<Vec<u8>, Error> {
+        let block = self
+            .bom
+            .blocks()
+            .get(i)
+            .ok_or_else(|| crate::Error::invalid_block_index(i))?;
+        let len = block.len as usize;
+        self.reader.seek(SeekFrom::Start(block.offset as u64))?;
+        read_vec(&mut self.reader, len)
+    }
+
+    /// Read the contents of the named block.
+    pub fn read_named_block(&mut self, name: &CStr) -> Result<Vec<u8>, Error> {
+        let i = self
+            .bom
+            .get_named(name)
+            .ok_or_else(|| crate::Error::named_block_not_found(name))?;
+        self.read_block(i)
+    }
+
+    /// Read key and value block indices of all entries of the tree stored in block `i`.
+    ///
+    /// Only the tree nodes are read, the keys and the values are not.
+    pub fn read_tree_entries(&mut self, i: u32) -> Result<Vec<(u32, u32)>, Error> {
+        let tree = self.read_block(i).in_block(i)?;
+        read_tree_entries(&tree, |i| self.read_block(i)).in_block(i)
+    }
+
+    /// Transform into the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use arbtest::arbtest;
+
+    use super::*;
+    use crate::receipt::Context;
+    use crate::VecTree;
+
+    #[test]
+    fn read_blocks() {
+        arbtest(|u| {
+            let entries: Vec<(u32, u64)> = u.arbitrary()?;
+            let block_len = u.int_in_range(crate::MIN_BLOCK_LEN..=4096)?;
+            let tree = VecTree::<u32, u64, Context>::new(entries.clone(), block_len);
+            let mut writer = Cursor::new(Vec::new());
+            writer.set_position(Bom::LEN as u64);
+            let mut bom = Bom::new();
+            bom.write_named(c"Tree", writer.by_ref(), &tree, &mut Context::new())
+                .unwrap();
+            bom.write(writer.by_ref()).unwrap();
+            let mut reader = BomReader::new(Cursor::new(writer.into_inner())).unwrap();
+            let i = reader.get_named(c"Tree").unwrap();
+            let mut actual = Vec::new();
+            for (key, value) in reader.read_tree_entries(i).unwrap() {
+                let key = u32::from_be_bytes(reader.read_block(key).unwrap().try_into().unwrap());
+                let value =
+                    u64::from_be_bytes(reader.read_block(value).unwrap().try_into().unwrap());
+                actual.push((key, value));
+            }
+            assert_eq!(entries, actual);
+            assert!(reader.read_block(u32::MAX).is_err());
+            assert!(reader.read_named_block(c"Missing").is_err());
+            Ok(())
+        });
+    }
+}
@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::io::Error;
@@ -13,6 +14,7 @@ use crate::BigEndianWrite;
 use crate::BlockRead;
 use crate::BlockWrite;
 use crate::Blocks;
+use crate::ErrorContext;
 
 /// `Vec`-based BOM tree representation.
 ///
@@ -30,7 +32,7 @@ impl<K, V, C> VecTree<K, V, C> {
     /// Create a new tree with provided `entries` and `block_len` block size.
     ///
     /// The block size is silently made at least `MIN_BLOCK_LEN`
-    /// to ensure that it can hold at least one entry.
+    /// to ensure that each node can hold at least two entries.
     pub fn new(entries: Vec<(K, V)>, block_len: usize) -> Self {
         let block_len = block_len.max(MIN_BLOCK_LEN);
         Self {
@@ -83,120 +85,121 @@ impl<C, K: BlockWrite<C>, V: BlockWrite<C>> BlockWrite<C> for VecTree<K, V, C> {
         context: &mut C,
     ) -> Result<u32, Error> {
         let n = max_enties_per_block(self.block_len);
-        let num_entries = self.entries.len();
-        let root = if num_entries <= n {
-            // One data node is enough, no need to introduce meta nodes.
-            let mut raw_entries = Vec::with_capacity(num_entries);
-            for (key, value) in self.entries.iter() {
+        let num_entries: u32 = self
+            .entries
+            .len()
+            .try_into()
+            .map_err(|_| Error::other("too many tree entries"))?;
+        // write keys and values first to place the data nodes in consecutive blocks
+        let mut data_nodes = Vec::with_capacity(self.entries.len().div_ceil(n));
+        let mut last_values = Vec::with_capacity(data_nodes.capacity());
+        for entries in self.entries.chunks(n) {
+            let mut raw_entries = Vec::with_capacity(entries.len());
+            for (key, value) in entries.iter() {
                 let key = key.write_block(writer.by_ref(), blocks, context)?;
                 let value = value.write_block(writer.by_ref(), blocks, context)?;
                 raw_entries.push((key, value));
             }
-            //let last_value_block = raw_entries.last().map(|x| x.1).unwrap_or(0);
+            #[allow(clippy::expect_used)]
+            let last_value_block = raw_entries.last().expect("We have at least one entry").1;
+            last_values.push(last_value_block);
+            data_nodes.push(raw_entries);
+        }
+        let root = if data_nodes.len() <= 1 {
+            // One data node is enough, no need to introduce meta nodes.
             let data_node = RawTreeNode {
                 next: 0,
                 prev: 0,
-                entries: raw_entries,
+                entries: data_nodes.pop().unwrap_or_default(),
                 is_data: true,
             };
             blocks.append(writer.by_ref(), |writer| data_node.write_be(writer))?
-            //let raw_entries = vec![(block, last_value_block)];
-            //let meta_node = RawTreeNode {
-            //    next: 0,
-            //    prev: 0,
-            //    entries: raw_entries,
-            //    is_data: false,
-            //};
-            //blocks.append(writer.by_ref(), |writer| meta_node.write_be(writer))?
         } else {
-            let num_data_nodes = num_entries.div_ceil(n);
-            let num_meta_nodes = num_data_nodes.div_ceil(n);
-            let max_data_nodes_per_meta_node = num_data_nodes.div_ceil(num_meta_nodes);
-            let mut iter = self.entries.iter();
-            let mut meta_nodes = Vec::with_capacity(num_meta_nodes);
-            for _ in 0..num_meta_nodes {
-                let mut data_nodes = Vec::with_capacity(max_data_nodes_per_meta_node);
-                for _ in 0..max_data_nodes_per_meta_node {
-                    let entries = collect_n(&mut iter, n);
-                    if entries.is_empty() {
-                        break;
-                    }
-                    let mut raw_entries = Vec::with_capacity(entries.len());
-                    for (key, value) in entries.iter() {
-                        let key = key.write_block(writer.by_ref(), blocks, context)?;
-                        let value = value.write_block(writer.by_ref(), blocks, context)?;
-                        raw_entries.push((key, value));
-                    }
-                    #[allow(clippy::expect_used)]
-                    let last_value_block =
-                        raw_entries.last().expect("We have at least one entry").1;
-                    let data_node = RawTreeNode {
-                        next: 0,
-                        prev: 0,
-                        entries: raw_entries,
-                        is_data: true,
-                    };
-                    data_nodes.push((data_node, last_value_block));
-                }
-                // set next/prev and generate meta node entries
-                let mut raw_entries = Vec::with_capacity(data_nodes.len());
-                let first_block = blocks.next_block_index();
-                let last_block = first_block + data_nodes.len() as u32 - 1;
-                let mut current_block = first_block;
-                for (mut data_node, last_value_block) in data_nodes.into_iter() {
-                    data_node.prev = if current_block == first_block {
-                        0
-                    } else {
-                        current_block - 1
-                    };
-                    data_node.next = if current_block == last_block {
-                        0
-                    } else {
-                        current_block + 1
-                    };
-                    let block =
-                        blocks.append(writer.by_ref(), |writer| data_node.write_be(writer))?;
-                    debug_assert!(block == current_block);
-                    current_block += 1;
-                    raw_entries.push((block, last_value_block));
-                }
-                meta_nodes.push(RawTreeNode {
-                    next: 0,
-                    prev: 0,
-                    entries: raw_entries,
-                    is_data: false,
-                });
+            // Build the tree bottom-up: each level links its nodes via next/prev,
+            // and the entries of the parent level point to these nodes and the last values of their subtrees.
+            let mut level = write_level(writer.by_ref(), blocks, data_nodes, true)?
+                .into_iter()
+                .zip(last_values)
+                .collect::<Vec<_>>();
+            while level.len() > n {
+                let meta_nodes = split_evenly(&level, n);
+                let last_values: Vec<_> = meta_nodes
+                    .iter()
+                    .filter_map(|entries| entries.last())
+                    .map(|(_, value)| *value)
+                    .collect();
+                level = write_level(writer.by_ref(), blocks, meta_nodes, false)?
+                    .into_iter()
+                    .zip(last_values)
+                    .collect();
             }
-            // set next/prev for meta nodes
-            let first_block = blocks.next_block_index();
-            let last_block = first_block + meta_nodes.len() as u32 - 1;
-            let mut current_block = first_block;
-            for mut meta_node in meta_nodes.into_iter() {
-                meta_node.prev = if current_block == first_block {
-                    0
-                } else {
-                    current_block - 1
-                };
-                meta_node.next = if current_block == last_block {
-                    0
-                } else {
-                    current_block + 1
-                };
-                let block = blocks.append(writer.by_ref(), |writer| meta_node.write_be(writer))?;
-                debug_assert!(block == current_block);
-                current_block += 1;
-            }
-            first_block
+            let root = RawTreeNode {
+                next: 0,
+                prev: 0,
+                entries: level,
+                is_data: false,
+            };
+            blocks.append(writer.by_ref(), |writer| root.write_be(writer))?
         };
         let tree = RawTree {
             root,
             block_len: self.block_len as u32,
-            num_entries: num_entries as u32,
+            num_entries,
         };
         blocks.append(writer.by_ref(), |writer| tree.write_be(writer))
     }
 }
 
+/// Split `entries` into the minimal no. of nodes with at most `n` entries each.
+///
+/// The sizes of the nodes differ by one at most, hence each node is at least half full.
+fn split_evenly(entries: &[(u32, u32)], n: usize) -> Vec<Vec<(u32, u32)>> {
+    let num_nodes = entries.len().div_ceil(n);
+    let mut nodes = Vec::with_capacity(num_nodes);
+    let mut rest = entries;
+    for i in 0..num_nodes {
+        let len = rest.len().div_ceil(num_nodes - i);
+        let (node, tail) = rest.split_at(len);
+        nodes.push(node.to_vec());
+        rest = tail;
+    }
+    nodes
+}
+
+/// Write the nodes of the same level to consecutive blocks linking them via `next` and `prev`.
+///
+/// Returns the block indices of the nodes.
+fn write_level<W: Write + Seek>(
+    mut writer: W,
+    blocks: &mut Blocks,
+    nodes: Vec<Vec<(u32, u32)>>,
+    is_data: bool,
+) -> Result<Vec<u32>, Error> {
+    let first_block = blocks.next_block_index();
+    let last_block = first_block + nodes.len() as u32 - 1;
+    let mut node_blocks = Vec::with_capacity(nodes.len());
+    for (current_block, entries) in (first_block..).zip(nodes) {
+        let node = RawTreeNode {
+            prev: if current_block == first_block {
+                0
+            } else {
+                current_block - 1
+            },
+            next: if current_block == last_block {
+                0
+            } else {
+                current_block + 1
+            },
+            entries,
+            is_data,
+        };
+        let block = blocks.append(writer.by_ref(), |writer| node.write_be(writer))?;
+        debug_assert!(block == current_block);
+        node_blocks.push(block);
+    }
+    Ok(node_blocks)
+}
+
 impl<C, K: BlockRead<C>, V: BlockRead<C>> BlockRead<C> for VecTree<K, V, C> {
     fn read_block(
         i: u32,
@@ -204,37 +207,17 @@ impl<C, K: BlockRead<C>, V: BlockRead<C>> BlockRead<C> for VecTree<K, V, C> {
         blocks: &mut Blocks,
         context: &mut C,
     ) -> Result<Self, Error> {
-        let tree = RawTree::read_be(blocks.slice(i, file)?)?;
-        let mut entries = Vec::new();
-        let mut visited = HashSet::new();
-        let mut nodes = VecDeque::new();
-        nodes.push_back(tree.root);
-        while let Some(node) = nodes.pop_front() {
-            if !visited.insert(node) {
-                // loop
-                continue;
-            }
-            let node = RawTreeNode::read_be(blocks.slice(node, file)?)?;
-            if node.is_data {
-                // data node
-                for (key, value) in node.entries.into_iter() {
-                    let key = K::read_block(key, file, blocks, context)?;
-                    let value = V::read_block(value, file, blocks, context)?;
-                    entries.push((key, value));
-                }
-            } else {
-                // meta node
-                for (key, _value) in node.entries.into_iter() {
-                    // value equals to the last entry of the data node referenced by key
-                    nodes.push_back(key);
-                }
-            }
-            if node.next != 0 {
-                nodes.push_back(node.next);
-            }
-            if node.prev != 0 {
-                nodes.push_back(node.prev);
-            }
+        let tree = RawTree::read_be(blocks.slice(i, file)?).in_block(i)?;
+        let raw_entries = read_node_entries(tree.root, |i| blocks.slice(i, file)).in_block(i)?;
+        let mut entries = Vec::with_capacity(raw_entries.len());
+        for (key, value) in raw_entries.into_iter() {
+            let key = K::read_block(key, file, blocks, context)
+                .in_block(key)
+                .in_block(i)?;
+            let value = V::read_block(value, file, blocks, context)
+                .in_block(value)
+                .in_block(i)?;
+            entries.push((key, value));
         }
         let block_len = tree.block_len as usize;
         Ok(Self {
@@ -245,15 +228,302 @@ impl<C, K: BlockRead<C>, V: BlockRead<C>> BlockRead<C> for VecTree<K, V, C> {
     }
 }
 
-#[derive(Debug)]
-struct RawTree {
-    root: u32,
-    block_len: u32,
-    num_entries: u32,
+impl<C, K: BlockRead<C>, V: BlockRead<C>> VecTree<K, V, C> {
+    /// Find the entry for which `compare` returns [`Ordering::Equal`] in the tree stored in block `i`.
+    ///
+    /// `compare` returns the ordering of the entry relative to the target
+    /// as in [`slice::binary_search_by`], and the entries should be sorted accordingly.
+    /// Meta nodes are used to find the data node that contains the entry,
+    /// hence only a few blocks are read.
+    pub fn find<F>(
+        i: u32,
+        file: &[u8],
+        blocks: &mut Blocks,
+        context: &mut C,
+        mut compare: F,
+    ) -> Result<Option<(K, V)>, Error>
+    where
+        F: FnMut(&K, &V) -> Ordering,
+    {
+        let Some((node, index)) =
+            lower_bound(i, file, blocks, context, &mut compare).in_block(i)?
+        else {
+            return Ok(None);
+        };
+        let Some((key, value)) = node.entries.get(index).copied() else {
+            return Ok(None);
+        };
+        let key = K::read_block(key, file, blocks, context)
+            .in_block(key)
+            .in_block(i)?;
+        let value = V::read_block(value, file, blocks, context)
+            .in_block(value)
+            .in_block(i)?;
+        Ok((compare(&key, &value) == Ordering::Equal).then_some((key, value)))
+    }
+
+    /// Iterate over the entries of the tree stored in block `i`
+    /// starting from the first entry for which `compare` does not return [`Ordering::Less`].
+    ///
+    /// The requirements for `compare` are the same as in [`find`](Self::find).
+    /// The iteration continues until the last entry of the tree; use
+    /// [`Iterator::take_while`] to stop earlier.
+    pub fn range<'a, F>(
+        i: u32,
+        file: &'a [u8],
+        blocks: &'a mut Blocks,
+        context: &'a mut C,
+        mut compare: F,
+    ) -> Result<TreeRange<'a, K, V, C>, Error>
+    where
+        F: FnMut(&K, &V) -> Ordering,
+    {
+        let (node, index) = match lower_bound(i, file, blocks, context, &mut compare).in_block(i)? {
+            Some((node, index)) => (Some(node), index),
+            None => (None, 0),
+        };
+        Ok(TreeRange {
+            file,
+            blocks,
+            context,
+            node,
+            index,
+            visited: Default::default(),
+            phantom: Default::default(),
+        })
+    }
+}
+
+/// Iterator over the entries of the tree that follows `next` links of the data nodes.
+///
+/// Returned by [`VecTree::range`].
+pub struct TreeRange<'a, K, V, C> {
+    file: &'a [u8],
+    blocks: &'a mut Blocks,
+    context: &'a mut C,
+    node: Option<RawTreeNode>,
+    index: usize,
+    visited: HashSet<u32>,
+    phantom: PhantomData<(K, V)>,
+}
+
+impl<C, K: BlockRead<C>, V: BlockRead<C>> Iterator for TreeRange<'_, K, V, C> {
+    type Item = Result<(K, V), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let node = self.node.as_ref()?;
+            if let Some((key, value)) = node.entries.get(self.index).copied() {
+                self.index += 1;
+                let entry = K::read_block(key, self.file, self.blocks, self.context)
+                    .in_block(key)
+                    .and_then(|key| {
+                        let value = V::read_block(value, self.file, self.blocks, self.context)
+                            .in_block(value)?;
+                        Ok((key, value))
+                    });
+                return Some(entry);
+            }
+            let next = node.next;
+            self.node = None;
+            self.index = 0;
+            if next == 0 || !self.visited.insert(next) {
+                return None;
+            }
+            let node = match self
+                .blocks
+                .slice(next, self.file)
+                .and_then(RawTreeNode::read_be)
+                .in_block(next)
+            {
+                Ok(node) => node,
+                Err(e) => return Some(Err(e)),
+            };
+            if !node.is_data {
+                return Some(Err(crate::Error::invalid_tree(next, "expected data node")));
+            }
+            self.node = Some(node);
+        }
+    }
+}
+
+/// Find the data node and the position of the first entry
+/// for which `compare` does not return [`Ordering::Less`].
+fn lower_bound<C, K, V, F>(
+    i: u32,
+    file: &[u8],
+    blocks: &mut Blocks,
+    context: &mut C,
+    compare: &mut F,
+) -> Result<Option<(RawTreeNode, usize)>, Error>
+where
+    K: BlockRead<C>,
+    V: BlockRead<C>,
+    F: FnMut(&K, &V) -> Ordering,
+{
+    let tree = RawTree::read_be(blocks.slice(i, file)?).in_block(i)?;
+    let mut is_less = |key: u32, value: u32, blocks: &mut Blocks| -> Result<bool, Error> {
+        let key = K::read_block(key, file, blocks, context).in_block(key)?;
+        let value = V::read_block(value, file, blocks, context).in_block(value)?;
+        Ok(compare(&key, &value) == Ordering::Less)
+    };
+    let mut visited = HashSet::new();
+    let mut node_block = tree.root;
+    loop {
+        if !visited.insert(node_block) {
+            return Err(crate::Error::invalid_tree(node_block, "loop in tree nodes")).in_block(i);
+        }
+        let node = RawTreeNode::read_be(blocks.slice(node_block, file)?)
+            .in_block(node_block)
+            .in_block(i)?;
+        let index = if node.next != 0 && node_is_less(&node, file, blocks, &mut is_less)? {
+            // Skip the whole node.
+            node.entries.len()
+        } else if node.is_data {
+            partition_point(&node.entries, |(key, value)| is_less(*key, *value, blocks))?
+        } else {
+            // Compare with the last entry of each child node.
+            partition_point(&node.entries, |(child, _)| {
+                match last_entry(*child, file, blocks)? {
+                    Some((key, value)) => is_less(key, value, blocks),
+                    None => Ok(true),
+                }
+            })?
+        };
+        match node.entries.get(index) {
+            Some(_) if node.is_data => return Ok(Some((node, index))),
+            Some((child, _)) => node_block = *child,
+            // All entries are less than the target, try the next node on the same level.
+            None if node.next != 0 => node_block = node.next,
+            None => return Ok(None),
+        }
+    }
+}
+
+/// Returns `true` if the last entry of the subtree is less than the target.
+fn node_is_less<F>(
+    node: &RawTreeNode,
+    file: &[u8],
+    blocks: &mut Blocks,
+    is_less: &mut F,
+) -> Result<bool, Error>
+where
+    F: FnMut(u32, u32, &mut Blocks) -> Result<bool, Error>,
+{
+    let last = match node.entries.last() {
+        Some(entry) if node.is_data => Some(*entry),
+        Some((child, _)) => last_entry(*child, file, blocks)?,
+        None => None,
+    };
+    match last {
+        Some((key, value)) => is_less(key, value, blocks),
+        None => Ok(true),
+    }
+}
+
+/// Get key and value block indices of the last entry in the subtree.
+fn last_entry(
+    mut node_block: u32,
+    file: &[u8],
+    blocks: &mut Blocks,
+) -> Result<Option<(u32, u32)>, Error> {
+    for _ in 0..MAX_DEPTH {
+        let node = RawTreeNode::read_be(blocks.slice(node_block, file)?).in_block(node_block)?;
+        match node.entries.last() {
+            Some(entry) if node.is_data => return Ok(Some(*entry)),
+            Some((child, _)) => node_block = *child,
+            None => return Ok(None),
+        }
+    }
+    Err(crate::Error::invalid_tree(node_block, "tree is too deep"))
+}
+
+/// The same as [`slice::partition_point`] but with fallible predicate.
+fn partition_point<T, F>(items: &[T], mut pred: F) -> Result<usize, Error>
+where
+    F: FnMut(&T) -> Result<bool, Error>,
+{
+    let mut low = 0;
+    let mut high = items.len();
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if pred(&items[middle])? {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    Ok(low)
+}
+
+/// Collect key and value block indices of all entries of the tree without reading the blocks.
+///
+/// `slice` returns the contents of the block with the specified index.
+/// The entries are returned in the order of the data nodes.
+pub(crate) fn read_tree_entries<F, B>(tree_block: &[u8], slice: F) -> Result<Vec<(u32, u32)>, Error>
+where
+    F: FnMut(u32) -> Result<B, Error>,
+    B: AsRef<[u8]>,
+{
+    let tree = RawTree::read_be(tree_block)?;
+    read_node_entries(tree.root, slice)
+}
+
+fn read_node_entries<F, B>(root: u32, mut slice: F) -> Result<Vec<(u32, u32)>, Error>
+where
+    F: FnMut(u32) -> Result<B, Error>,
+    B: AsRef<[u8]>,
+{
+    let mut entries = Vec::new();
+    let mut visited = HashSet::new();
+    let mut nodes = VecDeque::new();
+    nodes.push_back(root);
+    while let Some(node) = nodes.pop_front() {
+        if !visited.insert(node) {
+            // loop
+            continue;
+        }
+        let node = slice(node)
+            .and_then(|block| RawTreeNode::read_be(block.as_ref()))
+            .in_block(node)?;
+        if node.is_data {
+            // data node
+            entries.extend(node.entries);
+        } else {
+            // meta node
+            for (key, _value) in node.entries.into_iter() {
+                // value equals to the last entry of the data node referenced by key
+                nodes.push_back(key);
+            }
+        }
+        if node.next != 0 {
+            nodes.push_back(node.next);
+        }
+        if node.prev != 0 {
+            nodes.push_back(node.prev);
+        }
+    }
+    Ok(entries)
+}
+
+/// Tree header as it is stored in the file.
+///
+/// Use [`VecTree`] to read and write the whole tree.
+#[derive(Debug, Clone)]
+pub struct RawTree {
+    /// Block index of the root node.
+    pub root: u32,
+    /// Block size that is used to allocate tree nodes.
+    pub block_len: u32,
+    /// Total no. of entries.
+    pub num_entries: u32,
 }
 
 impl RawTree {
     const VERSION: u32 = 1;
+
+    /// Byte offset of the root node index.
+    pub(crate) const ROOT_OFFSET: usize = 4 + 4;
 }
 
 impl BigEndianRead for RawTree {
@@ -261,11 +531,11 @@ impl BigEndianRead for RawTree {
         let mut magic = [0_u8; 4];
         reader.read_exact(&mut magic[..])?;
         if TREE_MAGIC[..] != magic[..] {
-            return Err(Error::other("invalid tree magic"));
+            return Err(crate::Error::bad_magic("tree"));
         }
         let version = u32::read_be(reader.by_ref())?;
         if version != Self::VERSION {
-            return Err(Error::other("unsupported tree version"));
+            return Err(crate::Error::unsupported_version("tree", version));
         }
         let root = u32::read_be(reader.by_ref())?;
         let block_len = u32::read_be(reader.by_ref())?;
@@ -291,13 +561,34 @@ impl BigEndianWrite for RawTree {
     }
 }
 
-#[derive(Debug)]
-struct RawTreeNode {
-    next: u32,
-    prev: u32,
-    entries: Vec<(u32, u32)>,
+/// Tree node as it is stored in the file.
+///
+/// Data nodes store key and value block indices,
+/// meta nodes store child node block indices and the last value block indices of the children.
+#[derive(Debug, Clone)]
+pub struct RawTreeNode {
+    /// Block index of the next node on the same level or zero.
+    pub next: u32,
+    /// Block index of the previous node on the same level or zero.
+    pub prev: u32,
+    /// Key and value block indices.
+    pub entries: Vec<(u32, u32)>,
     /// Is data node or meta node?
-    is_data: bool,
+    pub is_data: bool,
+}
+
+impl RawTreeNode {
+    /// Byte offset of the next node index.
+    pub(crate) const NEXT_OFFSET: usize = 2 + 2;
+    /// Byte offset of the previous node index.
+    pub(crate) const PREV_OFFSET: usize = 2 + 2 + 4;
+
+    /// Byte offset of the key block index of entry `i`.
+    ///
+    /// The value block index follows the key.
+    pub(crate) const fn entry_offset(i: usize) -> usize {
+        NODE_HEADER_LEN + i * ENTRY_LEN
+    }
 }
 
 impl BigEndianRead for RawTreeNode {
@@ -342,36 +633,36 @@ impl BigEndianWrite for RawTreeNode {
 }
 
 const fn max_enties_per_block(block_len: usize) -> usize {
-    (block_len - NODE_HEADER_LEN) / ENTRY_LEN
-}
-
-// Collect N items (or less if unavailable) from the iterator into vector.
-fn collect_n<T, I: Iterator<Item = T>>(iter: &mut I, n: usize) -> Vec<T> {
-    let mut items = Vec::with_capacity(n);
-    loop {
-        if items.len() == n {
-            break;
-        }
-        match iter.next() {
-            Some(item) => items.push(item),
-            None => break,
-        }
+    let n = block_len.saturating_sub(NODE_HEADER_LEN) / ENTRY_LEN;
+    // The no. of entries is stored as `u16`.
+    if n > u16::MAX as usize {
+        u16::MAX as usize
+    } else if n < 2 {
+        2
+    } else {
+        n
     }
-    items
 }
 
 const TREE_MAGIC: [u8; 4] = *b"tree";
+/// Max. no. of levels of meta nodes.
+const MAX_DEPTH: usize = 32;
 const NODE_HEADER_LEN: usize = 2 + 2 + 4 + 4;
 const ENTRY_LEN: usize = 4 + 4;
 
-/// The size of the block that can hold one entry maximum.
-pub const MIN_BLOCK_LEN: usize = NODE_HEADER_LEN + ENTRY_LEN;
+/// The size of the block that can hold two entries maximum.
+///
+/// Two is the minimum number of children of a meta node that still allows the tree to grow.
+pub const MIN_BLOCK_LEN: usize = NODE_HEADER_LEN + 2 * ENTRY_LEN;
 
 #[cfg(test)]
 mod tests {
 
+    use std::io::Cursor;
+
     use arbitrary::Arbitrary;
     use arbitrary::Unstructured;
+    use arbtest::arbtest;
 
     use super::*;
     use crate::receipt::Context;
@@ -390,6 +681,10 @@ mod tests {
         test_specific_no_of_entries(MAX_LEN_FOR_SINGLE_META_NODE, BLOCK_LEN);
         test_specific_no_of_entries(MAX_LEN_FOR_SINGLE_META_NODE + 1, BLOCK_LEN);
         test_specific_no_of_entries(2 * MAX_LEN_FOR_SINGLE_META_NODE + 1, BLOCK_LEN);
+        test_specific_no_of_entries(
+            MAX_LEN_FOR_SINGLE_META_NODE * MAX_LEN_FOR_SINGLE_DATA_NODE + 1,
+            BLOCK_LEN,
+        );
     }
 
     #[test]
@@ -400,6 +695,130 @@ mod tests {
         block_io_symmetry::<VecTree<VecTree<(), (), Context>, VecTree<(), (), Context>, Context>>();
     }
 
+    #[test]
+    fn find_and_range() {
+        arbtest(|u| {
+            let mut entries: Vec<(u32, u64)> = u.arbitrary()?;
+            entries.sort_unstable_by_key(|(key, _)| *key);
+            entries.dedup_by_key(|(key, _)| *key);
+            let block_len = u.int_in_range(MIN_BLOCK_LEN..=512)?;
+            let tree = VecTree::<u32, u64, Context>::new(entries.clone(), block_len);
+            let mut blocks = Blocks::new();
+            let mut context = Context::new();
+            let mut writer = Cursor::new(Vec::new());
+            let i = tree
+                .write_block(&mut writer, &mut blocks, &mut context)
+                .unwrap();
+            let file = writer.into_inner();
+            let target: u32 = match entries.is_empty() {
+                true => u.arbitrary()?,
+                false => u.choose(&entries)?.0.wrapping_add(u.int_in_range(0..=1)?),
+            };
+            let expected = entries.iter().find(|(key, _)| *key == target).copied();
+            let actual = VecTree::<u32, u64, Context>::find(
+                i,
+                &file,
+                &mut blocks,
+                &mut context,
+                |key, _| key.cmp(&target),
+            )
+            .unwrap();
+            assert_eq!(expected, actual);
+            let expected: Vec<_> = entries
+                .iter()
+                .filter(|(key, _)| *key >= target)
+                .copied()
+                .collect();
+            let actual = VecTree::<u32, u64, Context>::range(
+                i,
+                &file,
+                &mut blocks,
+                &mut context,
+                |key, _| key.cmp(&target),
+            )
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+            assert_eq!(expected, actual);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn find_reads_few_blocks() {
+        const NUM_ENTRIES: u32 = 100_000;
+        let entries: Vec<(u32, u32)> = (0..NUM_ENTRIES).map(|i| (i, i)).collect();
+        let tree = VecTree::<u32, u32, Context>::new(entries, 128);
+        let mut blocks = Blocks::new();
+        let mut context = Context::new();
+        let mut writer = Cursor::new(Vec::new());
+        let i = tree
+            .write_block(&mut writer, &mut blocks, &mut context)
+            .unwrap();
+        let file = writer.into_inner();
+        // re-read the block index to track unread blocks
+        let mut index = Vec::new();
+        blocks.write_be(&mut index).unwrap();
+        let mut blocks = Blocks::read_be(&index[..]).unwrap();
+        let num_blocks = blocks.num_unread_blocks();
+        let actual =
+            VecTree::<u32, u32, Context>::find(i, &file, &mut blocks, &mut context, |key, _| {
+                key.cmp(&54321)
+            })
+            .unwrap();
+        assert_eq!(Some((54321, 54321)), actual);
+        let num_read_blocks = num_blocks - blocks.num_unread_blocks();
+        assert!(num_read_blocks < 100, "num_read_blocks = {num_read_blocks}");
+        // prevent printing unread blocks
+        std::mem::forget(blocks);
+    }
+
+    #[test]
+    fn single_root() {
+        arbtest(|u| {
+            let num_entries = u.int_in_range(0..=5000)?;
+            let entries: Vec<(u32, u32)> = (0..num_entries).map(|i| (i, i)).collect();
+            let block_len = u.int_in_range(MIN_BLOCK_LEN..=256)?;
+            let tree = VecTree::<u32, u32, Context>::new(entries, block_len);
+            let mut blocks = Blocks::new();
+            let mut context = Context::new();
+            let mut writer = Cursor::new(Vec::new());
+            let i = tree
+                .write_block(&mut writer, &mut blocks, &mut context)
+                .unwrap();
+            let file = writer.into_inner();
+            let raw_tree = RawTree::read_be(blocks.slice(i, &file).unwrap()).unwrap();
+            assert_eq!(num_entries, raw_tree.num_entries);
+            assert_eq!(block_len as u32, raw_tree.block_len);
+            let root = RawTreeNode::read_be(blocks.slice(raw_tree.root, &file).unwrap()).unwrap();
+            assert_eq!((0, 0), (root.next, root.prev));
+            // all data nodes are on the same level
+            let mut depths = HashSet::new();
+            let mut nodes = vec![(raw_tree.root, 0)];
+            while let Some((block, depth)) = nodes.pop() {
+                let node = RawTreeNode::read_be(blocks.slice(block, &file).unwrap()).unwrap();
+                assert!(node.entries.len() <= max_enties_per_block(block_len));
+                assert!(blocks.slice(block, &file).unwrap().len() <= block_len);
+                if node.is_data {
+                    depths.insert(depth);
+                    continue;
+                }
+                if block == raw_tree.root {
+                    assert!(node.entries.len() >= 2);
+                } else {
+                    assert!(node.entries.len() >= max_enties_per_block(block_len) / 2);
+                }
+                for (child, last_value) in node.entries.iter() {
+                    let last = last_entry(*child, &file, &mut blocks).unwrap();
+                    assert_eq!(Some(*last_value), last.map(|(_, value)| value));
+                    nodes.push((*child, depth + 1));
+                }
+            }
+            assert_eq!(1, depths.len());
+            Ok(())
+        });
+    }
+
     fn test_specific_no_of_entries(num_entries: usize, block_len: usize) {
         let entries = vec![(123_u32, 456_u32); num_entries];
         let tree = VecTree::new(entries, block_len);
@@ -1,21 +1,50 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::ffi::CStr;
+use std::ffi::CString;
+use std::ffi::OsStr;
 use std::fs::File;
 use std::io::Error;
+use std::io::ErrorKind;
 use std::io::Read;
 use std::io::Seek;
 use std::io::SeekFrom;
 use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Component;
 use std::path::Path;
 use std::path::PathBuf;
 
+use crate::receipt::to_receipt_path;
 use crate::receipt::BomInfo;
+use crate::receipt::BomInfoEntry;
 use crate::receipt::Context;
+use crate::receipt::Entry;
+use crate::receipt::EntryType;
+use crate::receipt::ExtensionRegistry;
+use crate::receipt::Extensions;
+use crate::receipt::FileDigests;
+use crate::receipt::FileList;
 use crate::receipt::FileSizes64;
 use crate::receipt::HardLinks;
 use crate::receipt::Metadata;
+use crate::receipt::MetadataRule;
+use crate::receipt::Overrides;
+use crate::receipt::PathComponent;
 use crate::receipt::PathComponentVec;
+use crate::receipt::PathFilter;
+use crate::receipt::PathIndex;
 use crate::receipt::VirtualPathTree;
+use crate::receipt::Walk;
+use crate::BlockLayout;
+use crate::BlockRead;
+use crate::BlockWrite;
 use crate::Bom;
+use crate::ErrorContext;
+use crate::Finding;
+use crate::OpaqueBlock;
+use crate::ReadOptions;
+use crate::Warnings;
 
 // TODO add `receipt` feature
 // TODO generate random executables with `random-dir`
@@ -24,12 +53,34 @@ use crate::Bom;
 /// Configuration for creating a receipt.
 pub struct ReceiptBuilder {
     paths_only: bool,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    ignore_files: Vec<PathBuf>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    umask: u16,
+    rules: Vec<(String, MetadataRule)>,
+    reproducible: bool,
+    max_mtime: Option<u32>,
+    sha256: bool,
 }
 
 impl ReceiptBuilder {
     /// Create receipt builder with the default parameters.
     pub fn new() -> Self {
-        Self { paths_only: false }
+        Self {
+            paths_only: false,
+            include: Default::default(),
+            exclude: Default::default(),
+            ignore_files: Default::default(),
+            uid: None,
+            gid: None,
+            umask: 0,
+            rules: Default::default(),
+            reproducible: false,
+            max_mtime: None,
+            sha256: false,
+        }
     }
 
     /// Do not include metadata in the receipt, include only file paths.
@@ -38,10 +89,132 @@ impl ReceiptBuilder {
         self
     }
 
+    /// Do not include the paths that match the glob pattern.
+    ///
+    /// Patterns without `/` match file names in any directory, e.g. `.DS_Store`.
+    /// Patterns with `/` match paths relative to the directory, e.g. `/build` or `src/**/*.o`.
+    /// Patterns that end with `/` match only directories, e.g. `.git/`.
+    /// Excluded directories are not scanned.
+    pub fn exclude<S: Into<String>>(mut self, pattern: S) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// Include the paths that match the glob pattern even if they are excluded.
+    ///
+    /// The syntax is the same as in [`exclude`](Self::exclude).
+    /// This does not apply to the contents of excluded directories as they are not scanned.
+    pub fn include<S: Into<String>>(mut self, pattern: S) -> Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    /// Do not include the paths that match the patterns from the `.gitignore`-style file.
+    ///
+    /// The patterns are relative to the directory that is scanned.
+    pub fn ignore_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.ignore_files.push(path.into());
+        self
+    }
+
+    /// Set the owner's user id of every path.
+    pub fn uid(mut self, value: u32) -> Self {
+        self.uid = Some(value);
+        self
+    }
+
+    /// Set the owner's group id of every path.
+    pub fn gid(mut self, value: u32) -> Self {
+        self.gid = Some(value);
+        self
+    }
+
+    /// Clear the specified permission bits of every path except symbolic links.
+    pub fn umask(mut self, value: u16) -> Self {
+        self.umask = value;
+        self
+    }
+
+    /// Set ownership and permissions of the paths that match the glob pattern.
+    ///
+    /// The syntax is the same as in [`exclude`](Self::exclude).
+    /// Rules are applied after [`uid`](Self::uid), [`gid`](Self::gid) and [`umask`](Self::umask)
+    /// in the order they were added.
+    pub fn rule<S: Into<String>>(mut self, pattern: S, rule: MetadataRule) -> Self {
+        self.rules.push((pattern.into(), rule));
+        self
+    }
+
+    /// Scan directory entries in the order of their names.
+    ///
+    /// By default the order depends on the file system and the same directory may produce receipts
+    /// with different sequential numbers and different bytes.
+    /// Use this together with [`max_mtime`](Self::max_mtime) to produce byte-for-byte identical
+    /// receipts from identical directories.
+    pub fn reproducible(mut self, value: bool) -> Self {
+        self.reproducible = value;
+        self
+    }
+
+    /// Clamp modification time of every path to the specified value.
+    ///
+    /// Usually the value comes from `SOURCE_DATE_EPOCH` environment variable.
+    pub fn max_mtime(mut self, value: u32) -> Self {
+        self.max_mtime = Some(value);
+        self
+    }
+
+    /// Compute SHA-256 digest of every file and of every architecture of universal binaries.
+    ///
+    /// The digests are stored in a separate named block that is ignored by Apple's tools.
+    /// This applies only to [`create`](Self::create).
+    pub fn sha256(mut self, value: bool) -> Self {
+        self.sha256 = value;
+        self
+    }
+
     /// Create a receipt using the provided parameters.
+    ///
+    /// Include/exclude patterns and ignore files apply only to this method.
     pub fn create<P: AsRef<Path>>(self, directory: P) -> Result<Receipt, Error> {
-        let entries = PathComponentVec::from_dir(directory, self.paths_only)?;
-        Ok(Receipt { entries })
+        let directory = directory.as_ref();
+        let filter = PathFilter::new(directory, &self.include, &self.exclude, &self.ignore_files)?;
+        let overrides = self.overrides()?;
+        let entries = PathComponentVec::scan(
+            directory,
+            self.paths_only,
+            self.reproducible,
+            self.sha256,
+            &filter,
+            &overrides,
+        )?;
+        Ok(Receipt::new(entries))
+    }
+
+    /// Create a receipt from the file list instead of scanning the directory.
+    ///
+    /// The file system is not accessed.
+    pub fn create_from_file_list(self, file_list: FileList) -> Result<Receipt, Error> {
+        let mut entries = file_list.into_inner();
+        let overrides = self.overrides()?;
+        for (path, metadata) in entries.iter_mut() {
+            let path = to_receipt_path(path);
+            let path = path.strip_prefix(".").unwrap_or(&path);
+            overrides.apply(path, metadata)?;
+        }
+        if self.paths_only {
+            for (_, metadata) in entries.iter_mut() {
+                *metadata = Metadata::Entry(Entry::new(metadata.entry_type()));
+            }
+        }
+        let entries = PathComponentVec::from_paths(entries)?;
+        Ok(Receipt::new(entries))
+    }
+}
+
+impl ReceiptBuilder {
+    fn overrides(&self) -> Result<Overrides, Error> {
+        Overrides::new(self.uid, self.gid, self.umask, self.max_mtime, &self.rules)
     }
 }
 
@@ -54,21 +227,283 @@ impl Default for ReceiptBuilder {
 /// This is what is usually called a BOM file.
 ///
 /// This file contains a list of file paths and metadata for an installed package.
+/// Unrecognized named blocks and the fields with unknown purpose are kept as read
+/// and are written back unchanged.
+/// Unrecognized named blocks are copied together with the blocks they refer to
+/// according to [`block_layout`](Self::block_layout) the same way [`Bom::compact`] does.
+///
+/// Paths can be looked up without reconstructing all of them:
+/// the lookup index is built once when the receipt is created.
 #[derive(Debug)]
-#[cfg_attr(test, derive(arbitrary::Arbitrary, PartialEq, Eq))]
+#[cfg_attr(test, derive(PartialEq, Eq))]
 pub struct Receipt {
     entries: PathComponentVec,
+    index: PathIndex,
+    /// Virtual paths as read from the file.
+    virtual_paths: VirtualPathTree,
+    /// Statistics entries with non-default unknown fields as read from the file.
+    stats: Vec<BomInfoEntry>,
+    /// Unrecognized named blocks as read from the file.
+    other_blocks: BTreeMap<CString, OpaqueBlock>,
+    /// Application-defined named blocks.
+    extensions: Extensions,
 }
 
 impl Receipt {
+    /// Create a receipt from the provided path components.
+    pub fn new(entries: PathComponentVec) -> Self {
+        let index = PathIndex::new(&entries);
+        Self {
+            entries,
+            index,
+            virtual_paths: Default::default(),
+            stats: Default::default(),
+            other_blocks: Default::default(),
+            extensions: Default::default(),
+        }
+    }
+
     /// Get paths and the corresponding metadata.
     pub fn entries(&self) -> Result<Vec<(PathBuf, Metadata)>, Error> {
         self.entries.to_paths()
     }
 
+    /// Get path component by its full path.
+    ///
+    /// The path may start with `./`, `/` or neither, i.e. `./bin/ls`, `/bin/ls` and `bin/ls`
+    /// refer to the same file.
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&PathComponent> {
+        let i = self.index.find(&self.entries, path.as_ref())?;
+        self.entries.get(i)
+    }
+
+    /// Check if the receipt contains the path.
+    pub fn contains<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.index.find(&self.entries, path.as_ref()).is_some()
+    }
+
+    /// Iterate over direct children of the directory under `path`.
+    ///
+    /// The iterator is empty if there is no such path.
+    pub fn children<P: AsRef<Path>>(&self, path: P) -> impl Iterator<Item = &PathComponent> {
+        let children = match self.get(path) {
+            Some(component) => self.index.children(component.seq_no),
+            None => &[],
+        };
+        children.iter().filter_map(|i| self.entries.get(*i))
+    }
+
+    /// Get the parent directory of the path.
+    pub fn parent<P: AsRef<Path>>(&self, path: P) -> Option<&PathComponent> {
+        let component = self.get(path)?;
+        let i = self.index.position(component.parent)?;
+        self.entries.get(i)
+    }
+
+    /// Get groups of paths that are hard links to the same file.
+    ///
+    /// Paths in each group are listed in depth-first order.
+    pub fn hard_links(&self) -> Vec<Vec<PathBuf>> {
+        let mut groups: Vec<Vec<PathBuf>> = Vec::new();
+        let mut positions: HashMap<u32, usize> = HashMap::new();
+        let hard_links = self.entries.hard_link_groups();
+        for (path, component) in self.walk() {
+            let Some(group) = hard_links.get(&component.seq_no).copied() else {
+                continue;
+            };
+            let i = *positions.entry(group).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[i].push(path);
+        }
+        groups.retain(|paths| paths.len() > 1);
+        groups
+    }
+
+    /// Traverse all paths in depth-first order.
+    ///
+    /// Directory entries are listed before their contents.
+    pub fn walk(&self) -> Walk<'_> {
+        Walk::new(&self.entries, &self.index)
+    }
+
+    /// Add new path or replace the metadata of the existing one.
+    ///
+    /// Missing parent directories are created with `0o755` permissions and root ownership.
+    /// Returns the previous metadata if the path already existed.
+    pub fn insert<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        metadata: Metadata,
+    ) -> Result<Option<Metadata>, Error> {
+        let path = to_receipt_path(path.as_ref());
+        if let Some(i) = self.index.find(&self.entries, &path) {
+            let Some(component) = self.entries.get_mut(i) else {
+                return Ok(None);
+            };
+            if metadata.entry_type() != EntryType::Directory
+                && !self.index.children(component.seq_no).is_empty()
+            {
+                return Err(Error::other(format!(
+                    "directory is not empty: {}",
+                    path.display()
+                )));
+            }
+            let old = std::mem::replace(&mut component.metadata, metadata);
+            // Hard links share the metadata, hence the path with different metadata
+            // is removed from its hard link group.
+            self.entries.normalize_hard_links();
+            return Ok(Some(old));
+        }
+        let paths_only = matches!(metadata, Metadata::Entry(..));
+        let (parent, name) = match (path.parent(), path.file_name()) {
+            (Some(dirname), Some(name)) => (self.create_dir_all(dirname, paths_only)?, name),
+            _ => (0, path.as_os_str()),
+        };
+        self.push(parent, name, metadata)?;
+        Ok(None)
+    }
+
+    /// Remove the path from the receipt.
+    ///
+    /// Directories are removed recursively.
+    /// Sequential numbers of the remaining paths are reassigned.
+    /// Returns the metadata of the removed path.
+    pub fn remove<P: AsRef<Path>>(&mut self, path: P) -> Option<Metadata> {
+        let i = self.index.find(&self.entries, path.as_ref())?;
+        let metadata = self.entries.get(i)?.metadata.clone();
+        let mut removed = vec![false; self.entries.len()];
+        for j in self.index.depth_first(&self.entries, &[i]) {
+            removed[j] = true;
+        }
+        let order: Vec<usize> = (0..self.entries.len()).filter(|j| !removed[*j]).collect();
+        self.entries.reorder(&order);
+        self.index = PathIndex::new(&self.entries);
+        Some(metadata)
+    }
+
+    /// Move the path to a new location.
+    ///
+    /// Directories are moved with all their contents.
+    /// Missing parent directories of the new location are created as in [`insert`](Self::insert).
+    /// Sequential numbers are reassigned as in [`renumber`](Self::renumber)
+    /// to keep the parents before their contents.
+    /// Returns an error if the new location already exists.
+    pub fn rename<P1: AsRef<Path>, P2: AsRef<Path>>(
+        &mut self,
+        from: P1,
+        to: P2,
+    ) -> Result<(), Error> {
+        let from = to_receipt_path(from.as_ref());
+        let to = to_receipt_path(to.as_ref());
+        let i = self.index.find(&self.entries, &from).ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("no such path: {}", from.display()),
+            )
+        })?;
+        if self.index.find(&self.entries, &to).is_some() {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("path already exists: {}", to.display()),
+            ));
+        }
+        if to.starts_with(&from) {
+            return Err(Error::other(format!(
+                "can't move {} inside itself",
+                from.display()
+            )));
+        }
+        let (Some(dirname), Some(name)) = (to.parent(), to.file_name()) else {
+            return Err(Error::other(format!("invalid path: {}", to.display())));
+        };
+        let name = CString::new(name.as_bytes()).map_err(|_| Error::other("invalid c-string"))?;
+        let paths_only = matches!(
+            self.entries.get(i).map(|component| &component.metadata),
+            Some(Metadata::Entry(..))
+        );
+        let parent = self.create_dir_all(dirname, paths_only)?;
+        if let Some(component) = self.entries.get_mut(i) {
+            component.parent = parent;
+            component.name = name;
+        }
+        self.index = PathIndex::new(&self.entries);
+        self.renumber();
+        Ok(())
+    }
+
+    /// Reassign sequential numbers in depth-first order.
+    ///
+    /// Directories are numbered before their contents.
+    pub fn renumber(&mut self) {
+        let mut order = self
+            .index
+            .depth_first(&self.entries, self.index.children(0));
+        // Keep the paths that are not reachable from the top-level paths.
+        let mut visited = vec![false; self.entries.len()];
+        for i in order.iter() {
+            visited[*i] = true;
+        }
+        order.extend((0..self.entries.len()).filter(|i| !visited[*i]));
+        self.entries.reorder(&order);
+        self.index = PathIndex::new(&self.entries);
+    }
+
+    /// Create directory `path` and all its missing parents.
+    ///
+    /// Returns the sequential number of the directory.
+    fn create_dir_all(&mut self, path: &Path, paths_only: bool) -> Result<u32, Error> {
+        let mut parent = 0;
+        for component in path.components() {
+            let name = component.as_os_str();
+            let i = match self.index.child(parent, name.as_bytes()) {
+                Some(i) => i,
+                // Top-level paths without `.` parent.
+                None if component == Component::CurDir && !self.entries.is_empty() => continue,
+                None => {
+                    let metadata = if paths_only {
+                        Metadata::entry(EntryType::Directory)
+                    } else {
+                        Metadata::directory(0o40755, 0, 0, 0)?
+                    };
+                    self.push(parent, name, metadata)?
+                }
+            };
+            let Some(directory) = self.entries.get(i) else {
+                break;
+            };
+            if directory.metadata.entry_type() != EntryType::Directory {
+                return Err(Error::other(format!("not a directory: {}", path.display())));
+            }
+            parent = directory.seq_no;
+        }
+        Ok(parent)
+    }
+
+    /// Append new path component with the next sequential number.
+    ///
+    /// Returns the position of the path component.
+    fn push(&mut self, parent: u32, name: &OsStr, metadata: Metadata) -> Result<usize, Error> {
+        let i = self.entries.len();
+        let seq_no = u32::try_from(i + 1).map_err(|_| Error::other("too many paths"))?;
+        let name = CString::new(name.as_bytes()).map_err(|_| Error::other("invalid c-string"))?;
+        let component = PathComponent {
+            seq_no,
+            parent,
+            metadata,
+            name,
+        };
+        self.index.insert(i, &component);
+        self.entries.push(component);
+        Ok(i)
+    }
+
     /// Compute and return per-architecture file statistics.
     pub fn stats(&self) -> BomInfo {
-        BomInfo::new(&self.entries)
+        let mut stats = BomInfo::new(&self.entries);
+        stats.set_unknown_fields(&self.stats);
+        stats
     }
 
     /// Write receipt to `writer` in bill-of-materials (BOM) format.
@@ -80,28 +515,46 @@ impl Receipt {
         bom.write_named(
             Self::V_INDEX,
             writer.by_ref(),
-            &VirtualPathTree::new(),
-            &mut context,
-        )?;
-        bom.write_named(
-            Self::HL_INDEX,
-            writer.by_ref(),
-            &std::mem::take(&mut context.hard_links),
+            &self.virtual_paths,
             &mut context,
         )?;
+        // Paths are written before hard links to know the indices of the shared metadata blocks.
         bom.write_named(Self::PATHS, writer.by_ref(), &self.entries, &mut context)?;
+        let mut hard_links = HardLinks::default();
+        for component in self.entries.iter() {
+            if !context.hard_link_groups.contains_key(&component.seq_no) {
+                continue;
+            }
+            let Some(metadata_index) = context.metadata_blocks.get(&component.seq_no) else {
+                continue;
+            };
+            hard_links
+                .entry(*metadata_index)
+                .or_default()
+                .push(component.name.clone());
+        }
+        bom.write_named(Self::HL_INDEX, writer.by_ref(), &hard_links, &mut context)?;
         bom.write_named(
             Self::SIZE_64,
             writer.by_ref(),
             &std::mem::take(&mut context.file_sizes),
             &mut context,
         )?;
-        bom.write_named(
-            Self::BOM_INFO,
-            writer.by_ref(),
-            &BomInfo::new(&self.entries),
-            &mut context,
-        )?;
+        let file_digests = std::mem::take(&mut context.file_digests);
+        if !file_digests.is_empty() {
+            bom.write_named(Self::SHA_256, writer.by_ref(), &file_digests, &mut context)?;
+        }
+        bom.write_named(Self::BOM_INFO, writer.by_ref(), &self.stats(), &mut context)?;
+        for (name, i) in self
+            .extensions
+            .write(writer.by_ref(), bom.blocks_mut(), &mut context)?
+        {
+            bom.named_blocks_mut().insert(name, i);
+        }
+        // Unrecognized named blocks are written as is.
+        for (name, payload) in self.other_blocks.iter() {
+            bom.write_named(name.clone(), writer.by_ref(), payload, &mut context)?;
+        }
         // write the header
         bom.write(writer.by_ref())?;
         Ok(())
@@ -114,23 +567,234 @@ impl Receipt {
     }
 
     /// Read a receipt from `reader`.
-    pub fn read<R: Read>(mut reader: R) -> Result<Self, Error> {
+    ///
+    /// The file is read in lenient mode and the warnings are discarded.
+    /// Use [`ReceiptView`](crate::receipt::ReceiptView) to read large files without copying.
+    pub fn read<R: Read>(reader: R) -> Result<Self, Error> {
+        Self::read_with_options(reader, ReadOptions::new()).map(|(receipt, _)| receipt)
+    }
+
+    /// Read a receipt from `reader` using the specified `options`.
+    ///
+    /// Returns the warnings found in lenient mode.
+    pub fn read_with_options<R: Read>(
+        reader: R,
+        options: ReadOptions,
+    ) -> Result<(Self, Vec<Finding>), Error> {
+        Self::read_with_extensions(reader, options, &ExtensionRegistry::new())
+    }
+
+    /// Read a receipt from `reader` decoding the named blocks from the `registry`.
+    ///
+    /// Decoded values are available via [`extension`](Self::extension)
+    /// and are written back by [`write`](Self::write).
+    /// Returns the warnings found in lenient mode.
+    pub fn read_with_extensions<R: Read>(
+        mut reader: R,
+        options: ReadOptions,
+        registry: &ExtensionRegistry,
+    ) -> Result<(Self, Vec<Finding>), Error> {
         let mut file = Vec::new();
         reader.read_to_end(&mut file)?;
-        let mut bom = Bom::read(&file[..])?;
+        let (mut bom, mut warnings) = Bom::read_with_options(&file[..], options)?;
         let mut context = Context::new();
-        //let _stats: BomInfo = bom.read_named(Self::BOM_INFO, &file, &mut context)?;
-        //let _vindex: VirtualPathTree = bom.read_named(Self::V_INDEX, &file, &mut context)?;
-        if let Some(i) = bom.get_named(Self::SIZE_64) {
-            let file_sizes: FileSizes64 = bom.read_regular(i, &file, &mut context)?;
+        context.warnings = Warnings::new(options);
+        // Statistics are computed on write, only their unknown fields are kept.
+        let mut stats = Vec::new();
+        if bom.get_named(Self::BOM_INFO).is_some() {
+            let result: Result<BomInfo, Error> =
+                bom.read_named(Self::BOM_INFO, &file, &mut context);
+            match result {
+                Ok(bom_info) => stats = bom_info.unknown_fields(),
+                Err(e) if context.warnings.is_strict() => return Err(e),
+                Err(e) => context.warnings.report(None, e.to_string())?,
+            }
+        }
+        let mut virtual_paths = VirtualPathTree::new();
+        if bom.get_named(Self::V_INDEX).is_some() {
+            let result: Result<VirtualPathTree, Error> =
+                bom.read_named(Self::V_INDEX, &file, &mut context);
+            match result {
+                Ok(value) => virtual_paths = value,
+                Err(e) if context.warnings.is_strict() => return Err(e),
+                Err(e) => context.warnings.report(None, e.to_string())?,
+            }
+        }
+        let mut extensions = Extensions::default();
+        let mut other_blocks = BTreeMap::new();
+        let names: Vec<(CString, u32)> = bom
+            .named_blocks()
+            .iter()
+            .map(|(name, i)| (name.into(), i))
+            .collect();
+        for (name, i) in names.into_iter() {
+            if Self::is_standard_block(&name) {
+                continue;
+            }
+            if registry.contains(&name) {
+                let result = registry.read(&name, &mut bom, &file, &mut context, &mut extensions);
+                match result {
+                    Ok(()) => continue,
+                    Err(e) if context.warnings.is_strict() => return Err(e),
+                    // malformed values are kept as is
+                    Err(e) => context.warnings.report(Some(i), e.to_string())?,
+                }
+            }
+            let result = OpaqueBlock::read(&bom, &file, i, &Self::block_layout(&name))
+                .in_block(i)
+                .in_named_block(&name);
+            match result {
+                Ok(payload) => {
+                    other_blocks.insert(name, payload);
+                }
+                Err(e) if context.warnings.is_strict() => return Err(e),
+                Err(e) => context.warnings.report(Some(i), e.to_string())?,
+            }
+        }
+        if bom.get_named(Self::SIZE_64).is_some() {
+            let file_sizes: FileSizes64 = bom.read_named(Self::SIZE_64, &file, &mut context)?;
             context.file_sizes = file_sizes;
         }
-        if let Some(i) = bom.get_named(Self::HL_INDEX) {
-            let hard_links: HardLinks = bom.read_regular(i, &file, &mut context)?;
+        if bom.get_named(Self::SHA_256).is_some() {
+            let file_digests: FileDigests = bom.read_named(Self::SHA_256, &file, &mut context)?;
+            context.file_digests = file_digests;
+        }
+        if bom.get_named(Self::HL_INDEX).is_some() {
+            let hard_links: HardLinks = bom.read_named(Self::HL_INDEX, &file, &mut context)?;
             context.hard_links = hard_links;
         }
         let entries: PathComponentVec = bom.read_named(Self::PATHS, &file, &mut context)?;
-        Ok(Self { entries })
+        warnings.extend(context.warnings.take());
+        let mut receipt = Self::new(entries);
+        receipt.virtual_paths = virtual_paths;
+        receipt.stats = stats;
+        receipt.other_blocks = other_blocks;
+        receipt.extensions = extensions;
+        Ok((receipt, warnings))
+    }
+
+    /// Read the path component with the sequential number `seq_no` from `file`.
+    ///
+    /// Only the blocks on the way to the path component are read, the other paths are not.
+    /// Paths should be sorted by sequential numbers which is the case for the files created by
+    /// `mkbom` and this library. Hard link group is not determined.
+    pub fn read_component(file: &[u8], seq_no: u32) -> Result<Option<PathComponent>, Error> {
+        let mut bom = Bom::read(file)?;
+        let mut context = Context::new();
+        if bom.get_named(Self::SIZE_64).is_some() {
+            context.file_sizes = bom.read_named(Self::SIZE_64, file, &mut context)?;
+        }
+        if bom.get_named(Self::SHA_256).is_some() {
+            context.file_digests = bom.read_named(Self::SHA_256, file, &mut context)?;
+        }
+        let i = bom
+            .get_named(Self::PATHS)
+            .ok_or_else(|| crate::Error::named_block_not_found(Self::PATHS))?;
+        PathComponentVec::find(&mut bom, i, file, &mut context, seq_no).in_named_block(Self::PATHS)
+    }
+
+    /// Store application-defined `value` in the named block `name`.
+    ///
+    /// The value is written next to the standard named blocks by [`write`](Self::write)
+    /// and can be decoded via [`read_extension`](Self::read_extension)
+    /// or [`read_with_extensions`](Self::read_with_extensions).
+    /// Replaces the unrecognized named block with the same name, if any.
+    /// Returns an error if `name` is one of the standard named blocks.
+    ///
+    /// [`Bom::compact`] with [`block_layout`](Self::block_layout) treats such blocks as opaque,
+    /// hence values that span multiple blocks do not survive the compaction.
+    /// The same applies to reading them as unrecognized named blocks via [`read`](Self::read)
+    /// and writing them back.
+    pub fn set_extension<N, T>(&mut self, name: N, value: T) -> Result<(), Error>
+    where
+        N: Into<CString>,
+        T: BlockRead<Context> + BlockWrite<Context> + 'static,
+    {
+        let name = name.into();
+        if Self::is_standard_block(&name) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("`{}` is a standard named block", name.to_string_lossy()),
+            ));
+        }
+        self.other_blocks.remove(&name);
+        self.extensions.insert(name, value);
+        Ok(())
+    }
+
+    /// Get application-defined value stored via [`set_extension`](Self::set_extension).
+    ///
+    /// Returns `None` if there is no such value or it has a different type.
+    /// The values from the file are decoded only by [`read_with_extensions`](Self::read_with_extensions),
+    /// [`read`](Self::read) keeps them as is, use [`read_extension`](Self::read_extension)
+    /// to decode them from the file on demand.
+    pub fn extension<T: 'static>(&self, name: &CStr) -> Option<&T> {
+        self.extensions.get(name)
+    }
+
+    /// Remove application-defined value or unrecognized named block `name`.
+    ///
+    /// Returns `true` if the block was removed.
+    pub fn remove_extension(&mut self, name: &CStr) -> bool {
+        let removed = self.extensions.remove(name);
+        self.other_blocks.remove(name).is_some() || removed
+    }
+
+    /// Decode application-defined value stored in the named block `name` of `file`.
+    ///
+    /// [`Receipt::read`] keeps such blocks as unrecognized named blocks without decoding them,
+    /// use [`read_with_extensions`](Self::read_with_extensions) to decode them on read.
+    pub fn read_extension<T: BlockRead<Context>>(
+        file: &[u8],
+        name: &CStr,
+    ) -> Result<Option<T>, Error> {
+        let mut bom = Bom::read(file)?;
+        if bom.get_named(name).is_none() {
+            return Ok(None);
+        }
+        let mut context = Context::new();
+        bom.read_named(name, file, &mut context).map(Some)
+    }
+
+    /// Get the layout of the named block of the receipt for [`Bom::compact`].
+    ///
+    /// Unknown named blocks are opaque.
+    pub fn block_layout(name: &CStr) -> BlockLayout {
+        use BlockLayout::*;
+        if name == Self::PATHS {
+            // key stores sequential number and metadata block index
+            BlockLayout::tree(Refs(vec![(4, Opaque)]), Opaque)
+        } else if name == Self::HL_INDEX {
+            // key points to the tree of paths, value stores metadata block index
+            BlockLayout::tree(
+                BlockLayout::ptr(BlockLayout::tree(Opaque, Opaque)),
+                BlockLayout::ptr(Opaque),
+            )
+        } else if name == Self::SIZE_64 || name == Self::SHA_256 {
+            // value stores metadata block index
+            BlockLayout::tree(Opaque, BlockLayout::ptr(Opaque))
+        } else if name == Self::V_INDEX {
+            // the second field is the tree index, tree keys are optional trees
+            Refs(vec![(
+                4,
+                BlockLayout::tree(BlockLayout::ptr(BlockLayout::tree(Opaque, Opaque)), Opaque),
+            )])
+        } else {
+            Opaque
+        }
+    }
+
+    /// Returns `true` if the named block is decoded by the receipt.
+    fn is_standard_block(name: &CStr) -> bool {
+        [
+            Self::V_INDEX,
+            Self::HL_INDEX,
+            Self::SIZE_64,
+            Self::SHA_256,
+            Self::BOM_INFO,
+            Self::PATHS,
+        ]
+        .contains(&name)
     }
 
     /// Virtual paths named block.
@@ -144,6 +808,11 @@ impl Receipt {
     /// 64-bit file sizes named block.
     pub const SIZE_64: &'static CStr = c"Size64";
 
+    /// SHA-256 digests of the files named block.
+    ///
+    /// This block is not part of the original format and is written only if there are digests.
+    pub const SHA_256: &'static CStr = c"Sha256";
+
     /// Per-architecture file statistics named block,
     pub const BOM_INFO: &'static CStr = c"BomInfo";
 
@@ -155,9 +824,21 @@ impl Receipt {
 mod tests {
     use std::io::Cursor;
 
+    use arbitrary::Arbitrary;
+    use arbitrary::Unstructured;
+    use std::os::unix::fs::PermissionsExt;
+
     use arbtest::arbtest;
+    use random_dir::DirBuilder;
+    use tempfile::TempDir;
 
     use super::*;
+    use crate::receipt::sha256;
+    use crate::receipt::ReceiptView;
+    use crate::BigEndianRead;
+    use crate::BomFile;
+    use crate::RawTree;
+    use crate::RawTreeNode;
 
     #[test]
     fn write_read() {
@@ -166,10 +847,831 @@ mod tests {
             let mut writer = Cursor::new(Vec::new());
             expected.write(&mut writer).unwrap();
             let bytes = writer.into_inner();
-            let actual = Receipt::read(&bytes[..]).unwrap();
+            let (actual, warnings) =
+                Receipt::read_with_options(&bytes[..], ReadOptions::new().strict(true)).unwrap();
+            assert_eq!(Vec::<Finding>::new(), warnings);
             assert_eq!(expected, actual);
             assert_eq!(expected.stats(), actual.stats());
             Ok(())
         });
     }
+
+    #[test]
+    fn lookup() {
+        let text = b".\t40755\t0/0\n\
+            ./bin\t40755\t0/0\n\
+            ./bin/tool\t100755\t501/20\t1234\t4294967295\n\
+            ./bin/other\t100755\t0/0\t1\t2\n\
+            ./lib\t40755\t0/0\n\
+            ./lib/libtool.dylib\t100644\t0/0\t3\t4\n";
+        let file_list = FileList::read(&text[..]).unwrap();
+        let receipt = ReceiptBuilder::new()
+            .create_from_file_list(file_list)
+            .unwrap();
+        for path in ["./bin/tool", "/bin/tool", "bin/tool", "./lib/../bin/tool"] {
+            let tool = receipt.get(path).unwrap();
+            assert_eq!(c"tool", tool.name.as_c_str(), "path = {}", path);
+            assert_eq!(1234, tool.metadata.size());
+        }
+        for path in [".", "/", ""] {
+            assert_eq!(c".", receipt.get(path).unwrap().name.as_c_str());
+        }
+        assert!(receipt.contains("./lib/libtool.dylib"));
+        assert!(!receipt.contains("./lib/tool"));
+        assert!(!receipt.contains("./bin/tool/x"));
+        let children: Vec<_> = receipt.children("bin").map(|c| c.name.as_c_str()).collect();
+        assert_eq!(vec![c"tool", c"other"], children);
+        assert_eq!(0, receipt.children("./bin/tool").count());
+        assert_eq!(0, receipt.children("./none").count());
+        assert_eq!(
+            c"lib",
+            receipt
+                .parent("./lib/libtool.dylib")
+                .unwrap()
+                .name
+                .as_c_str()
+        );
+        assert!(receipt.parent(".").is_none());
+        let paths: Vec<_> = receipt.walk().map(|(path, _)| path).collect();
+        assert_eq!(
+            vec![
+                PathBuf::from("."),
+                PathBuf::from("./bin"),
+                PathBuf::from("./bin/tool"),
+                PathBuf::from("./bin/other"),
+                PathBuf::from("./lib"),
+                PathBuf::from("./lib/libtool.dylib"),
+            ],
+            paths
+        );
+    }
+
+    #[test]
+    fn lookup_all() {
+        arbtest(|u| {
+            let receipt: Receipt = u.arbitrary()?;
+            let mut expected = receipt.entries().unwrap();
+            let mut actual: Vec<_> = receipt
+                .walk()
+                .map(|(path, component)| (path, component.metadata.clone()))
+                .collect();
+            expected.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+            actual.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+            assert_eq!(expected, actual);
+            for (path, metadata) in expected.iter() {
+                assert_eq!(Some(metadata), receipt.get(path).map(|c| &c.metadata));
+            }
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn insert_remove_rename() {
+        let mut receipt = Receipt::new(PathComponentVec::new(Vec::new()));
+        let file = Metadata::file(0o100644, 501, 20, 0, 3, 4).unwrap();
+        assert!(receipt
+            .insert("./usr/lib/libx.dylib", file.clone())
+            .unwrap()
+            .is_none());
+        assert!(receipt
+            .insert("/usr/bin/x", file.clone())
+            .unwrap()
+            .is_none());
+        assert_consistent(&receipt);
+        assert_eq!(
+            vec![
+                ".",
+                "./usr",
+                "./usr/lib",
+                "./usr/lib/libx.dylib",
+                "./usr/bin",
+                "./usr/bin/x"
+            ],
+            walk_paths(&receipt)
+        );
+        let usr = &receipt.get("usr").unwrap().metadata;
+        assert_eq!(EntryType::Directory, usr.entry_type());
+        assert_eq!((0o40755, 0, 0), (usr.mode(), usr.uid(), usr.gid()));
+        // replace
+        let other = Metadata::file(0o100755, 0, 0, 0, 1, 2).unwrap();
+        assert_eq!(
+            Some(file.clone()),
+            receipt.insert("usr/bin/x", other).unwrap()
+        );
+        assert!(receipt.insert("./usr/bin/x/y", file.clone()).is_err());
+        assert!(receipt.insert("./usr", file.clone()).is_err());
+        // rename
+        assert!(receipt.rename("./usr/lib", "./usr/lib/x").is_err());
+        assert!(receipt.rename("./usr/lib", "./usr/bin").is_err());
+        assert!(receipt.rename("./usr/none", "./usr/x").is_err());
+        receipt.rename("./usr/lib", "./opt/lib").unwrap();
+        assert_consistent(&receipt);
+        assert_eq!(
+            vec![
+                ".",
+                "./usr",
+                "./usr/bin",
+                "./usr/bin/x",
+                "./opt",
+                "./opt/lib",
+                "./opt/lib/libx.dylib"
+            ],
+            walk_paths(&receipt)
+        );
+        receipt.renumber();
+        assert_consistent(&receipt);
+        let paths: Vec<_> = receipt
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(
+            walk_paths(&receipt)
+                .into_iter()
+                .map(PathBuf::from)
+                .collect::<Vec<_>>(),
+            paths
+        );
+        // remove
+        assert!(receipt.remove("./none").is_none());
+        assert_eq!(
+            Some(EntryType::Directory),
+            receipt.remove("./usr").map(|m| m.entry_type())
+        );
+        assert_consistent(&receipt);
+        assert_eq!(
+            vec![".", "./opt", "./opt/lib", "./opt/lib/libx.dylib"],
+            walk_paths(&receipt)
+        );
+        let mut writer = Cursor::new(Vec::new());
+        receipt.write(&mut writer).unwrap();
+        let actual = Receipt::read(&writer.into_inner()[..]).unwrap();
+        assert_eq!(receipt, actual);
+    }
+
+    #[test]
+    fn create_filtered() {
+        let directory = TempDir::new().unwrap();
+        let root = directory.path();
+        for dir in [".git/objects", "src", "build"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            ".git/objects/x",
+            ".DS_Store",
+            "src/.DS_Store",
+            "src/main.c",
+            "src/main.c~",
+            "src/main.o",
+            "src/keep.o",
+            "build/out",
+        ] {
+            std::fs::write(root.join(file), file).unwrap();
+        }
+        let ignore_file = root.join("build/.ignore");
+        std::fs::write(&ignore_file, "*~\n/build\n").unwrap();
+        let receipt = ReceiptBuilder::new()
+            .exclude(".git/")
+            .exclude(".DS_Store")
+            .exclude("*.o")
+            .include("keep.o")
+            .ignore_file(&ignore_file)
+            .create(root)
+            .unwrap();
+        let mut paths: Vec<_> = receipt.walk().map(|(path, _)| path).collect();
+        paths.sort();
+        assert_eq!(
+            vec![
+                PathBuf::from("."),
+                PathBuf::from("./src"),
+                PathBuf::from("./src/keep.o"),
+                PathBuf::from("./src/main.c"),
+            ],
+            paths
+        );
+        assert!(ReceiptBuilder::new().exclude("[").create(root).is_err());
+    }
+
+    #[test]
+    fn create_with_sha256() {
+        let directory = TempDir::new().unwrap();
+        let root = directory.path();
+        std::fs::write(root.join("file"), "hello").unwrap();
+        let receipt = ReceiptBuilder::new().create(root).unwrap();
+        let mut bytes = Vec::new();
+        receipt.write(Cursor::new(&mut bytes)).unwrap();
+        let bom = Bom::read(&bytes[..]).unwrap();
+        assert!(bom.get_named(Receipt::SHA_256).is_none());
+        let receipt = ReceiptBuilder::new().sha256(true).create(root).unwrap();
+        let digest = receipt.get("file").unwrap().metadata.digest().unwrap();
+        assert_eq!(&sha256(&b"hello"[..]).unwrap(), digest.sha256());
+        assert!(digest.arches().is_empty());
+        assert!(receipt.get(".").unwrap().metadata.digest().is_none());
+        let mut bytes = Vec::new();
+        receipt.write(Cursor::new(&mut bytes)).unwrap();
+        let bom = Bom::read(&bytes[..]).unwrap();
+        assert!(bom.get_named(Receipt::SHA_256).is_some());
+        let actual = Receipt::read(&bytes[..]).unwrap();
+        assert_eq!(receipt, actual);
+        let component = Receipt::read_component(&bytes[..], 2).unwrap().unwrap();
+        assert_eq!(Some(digest), component.metadata.digest());
+    }
+
+    #[test]
+    fn create_with_overrides() {
+        let directory = TempDir::new().unwrap();
+        let root = directory.path();
+        std::fs::create_dir_all(root.join("Applications/App")).unwrap();
+        std::fs::write(root.join("Applications/App/file"), "").unwrap();
+        std::fs::write(root.join("other"), "").unwrap();
+        std::fs::set_permissions(root.join("other"), PermissionsExt::from_mode(0o666)).unwrap();
+        let receipt = ReceiptBuilder::new()
+            .uid(0)
+            .gid(0)
+            .umask(0o022)
+            .rule(
+                "./Applications/**",
+                MetadataRule::new().gid(80).dir_mode(0o775).file_mode(0o444),
+            )
+            .create(root)
+            .unwrap();
+        for (path, expected) in [
+            ("./Applications/App", (0o40775, 0, 80)),
+            ("./Applications/App/file", (0o100444, 0, 80)),
+            ("./other", (0o100644, 0, 0)),
+        ] {
+            let metadata = &receipt.get(path).unwrap().metadata;
+            assert_eq!(
+                expected,
+                (metadata.mode(), metadata.uid(), metadata.gid()),
+                "path = {}",
+                path
+            );
+        }
+        let file_list =
+            FileList::read(&b".\t40777\t0/0\n./x\t100777\t1000/1000\t0\t0\n"[..]).unwrap();
+        let receipt = ReceiptBuilder::new()
+            .uid(0)
+            .umask(0o022)
+            .create_from_file_list(file_list)
+            .unwrap();
+        let metadata = &receipt.get("./x").unwrap().metadata;
+        assert_eq!(
+            (0o100755, 0, 1000),
+            (metadata.mode(), metadata.uid(), metadata.gid())
+        );
+    }
+
+    #[test]
+    fn hard_links() {
+        let directory = TempDir::new().unwrap();
+        let root = directory.path();
+        std::fs::create_dir(root.join("bin")).unwrap();
+        std::fs::write(root.join("bin/a"), "a").unwrap();
+        std::fs::hard_link(root.join("bin/a"), root.join("bin/b")).unwrap();
+        std::fs::hard_link(root.join("bin/a"), root.join("c")).unwrap();
+        std::fs::write(root.join("d"), "d").unwrap();
+        let receipt = ReceiptBuilder::new().create(root).unwrap();
+        let mut expected = vec![
+            PathBuf::from("./bin/a"),
+            PathBuf::from("./bin/b"),
+            PathBuf::from("./c"),
+        ];
+        let mut groups = receipt.hard_links();
+        assert_eq!(1, groups.len());
+        groups[0].sort();
+        assert_eq!(expected, groups[0]);
+        let mut writer = Cursor::new(Vec::new());
+        receipt.write(&mut writer).unwrap();
+        let bytes = writer.into_inner();
+        let actual = Receipt::read(&bytes[..]).unwrap();
+        assert_eq!(receipt, actual);
+        // check that the metadata block is shared and `HLIndex` is filled
+        let mut bom = Bom::read(&bytes[..]).unwrap();
+        let mut context = Context::new();
+        let _: PathComponentVec = bom
+            .read_named(Receipt::PATHS, &bytes, &mut context)
+            .unwrap();
+        let i = bom.get_named(Receipt::HL_INDEX).unwrap();
+        let hard_links: HardLinks = bom.read_regular(i, &bytes, &mut context).unwrap();
+        let hard_links = hard_links.into_inner();
+        assert_eq!(1, hard_links.len());
+        let (metadata_index, mut names) = hard_links.into_iter().next().unwrap();
+        for path in expected.iter() {
+            let seq_no = actual.get(path).unwrap().seq_no;
+            assert_eq!(Some(&metadata_index), context.metadata_blocks.get(&seq_no));
+        }
+        names.sort();
+        assert_eq!(
+            vec![
+                CString::new("a").unwrap(),
+                CString::new("b").unwrap(),
+                CString::new("c").unwrap()
+            ],
+            names
+        );
+        // removing the first path keeps the rest of the group
+        let mut receipt = actual;
+        receipt.remove(&expected[0]).unwrap();
+        expected.remove(0);
+        let mut groups = receipt.hard_links();
+        groups[0].sort();
+        assert_eq!(vec![expected], groups);
+        receipt.remove("./c").unwrap();
+        assert!(receipt.hard_links().is_empty());
+    }
+
+    #[test]
+    fn replace_hard_link_metadata() {
+        let directory = TempDir::new().unwrap();
+        let root = directory.path();
+        std::fs::write(root.join("a"), "a").unwrap();
+        std::fs::hard_link(root.join("a"), root.join("b")).unwrap();
+        std::fs::hard_link(root.join("a"), root.join("c")).unwrap();
+        let mut receipt = ReceiptBuilder::new().uid(7).create(root).unwrap();
+        assert_eq!(1, receipt.hard_links().len());
+        let mut metadata = receipt.get("b").unwrap().metadata.clone();
+        // the same metadata keeps the group
+        receipt.insert("./b", metadata.clone()).unwrap();
+        assert_eq!(3, receipt.hard_links()[0].len());
+        metadata.set_uid(1);
+        receipt.insert("./b", metadata).unwrap();
+        let mut groups = receipt.hard_links();
+        groups[0].sort();
+        assert_eq!(
+            vec![vec![PathBuf::from("./a"), PathBuf::from("./c")]],
+            groups
+        );
+        let mut writer = Cursor::new(Vec::new());
+        receipt.write(&mut writer).unwrap();
+        let actual = Receipt::read(&writer.into_inner()[..]).unwrap();
+        assert_eq!(7, actual.get("a").unwrap().metadata.uid());
+        assert_eq!(1, actual.get("b").unwrap().metadata.uid());
+        assert_eq!(7, actual.get("c").unwrap().metadata.uid());
+        assert_eq!(receipt, actual);
+    }
+
+    #[test]
+    fn modify_hard_link_metadata() {
+        let directory = TempDir::new().unwrap();
+        let root = directory.path();
+        std::fs::write(root.join("a"), "a").unwrap();
+        std::fs::hard_link(root.join("a"), root.join("b")).unwrap();
+        std::fs::hard_link(root.join("a"), root.join("c")).unwrap();
+        let mut entries = PathComponentVec::from_dir(root, false).unwrap();
+        // modify the last path in the group
+        let component = entries
+            .iter_mut()
+            .filter(|component| component.metadata.entry_type() == EntryType::File)
+            .max_by_key(|component| component.seq_no)
+            .unwrap();
+        let name = PathBuf::from(".").join(OsStr::from_bytes(component.name.to_bytes()));
+        component.metadata.set_uid(1234);
+        let receipt = Receipt::new(entries);
+        let mut writer = Cursor::new(Vec::new());
+        receipt.write(&mut writer).unwrap();
+        let actual = Receipt::read(&writer.into_inner()[..]).unwrap();
+        assert_eq!(1234, actual.get(&name).unwrap().metadata.uid());
+        for path in ["./a", "./b", "./c"] {
+            if Path::new(path) != name {
+                assert_ne!(1234, actual.get(path).unwrap().metadata.uid());
+            }
+        }
+        let groups = actual.hard_links();
+        assert_eq!(1, groups.len());
+        assert_eq!(2, groups[0].len());
+        assert!(!groups[0].contains(&name));
+        assert_eq!(groups, receipt.hard_links());
+    }
+
+    #[test]
+    fn reproducible() {
+        arbtest(|u| {
+            use random_dir::FileType::*;
+            let directory = DirBuilder::new()
+                .file_types([Regular, Directory, Symlink, HardLink])
+                .create(u)?;
+            let max_mtime: u32 = u.int_in_range(0..=u32::MAX)?;
+            let write = || {
+                let receipt = ReceiptBuilder::new()
+                    .reproducible(true)
+                    .max_mtime(max_mtime)
+                    .create(directory.path())
+                    .unwrap();
+                let mut writer = Cursor::new(Vec::new());
+                receipt.write(&mut writer).unwrap();
+                (receipt, writer.into_inner())
+            };
+            let (receipt, expected) = write();
+            let (_, actual) = write();
+            assert!(expected == actual, "receipts differ");
+            for (_, component) in receipt.walk() {
+                assert!(component.metadata.mtime() <= max_mtime);
+            }
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn read_component() {
+        arbtest(|u| {
+            let receipt: Receipt = u.arbitrary()?;
+            let mut writer = Cursor::new(Vec::new());
+            receipt.write(&mut writer).unwrap();
+            let bytes = writer.into_inner();
+            let expected = Receipt::read(&bytes[..]).unwrap();
+            let seq_no = u.int_in_range(0..=expected.entries.len() as u32 + 1)?;
+            let expected = expected
+                .entries
+                .iter()
+                .find(|component| component.seq_no == seq_no)
+                .cloned();
+            let actual = Receipt::read_component(&bytes[..], seq_no).unwrap();
+            assert_eq!(expected, actual);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn compact() {
+        arbtest(|u| {
+            let expected: Receipt = u.arbitrary()?;
+            let mut writer = Cursor::new(Vec::new());
+            expected.write(&mut writer).unwrap();
+            // rewrite the paths leaving the old ones unreachable
+            let mut file = BomFile::new(writer).unwrap();
+            let mut context = Context::new();
+            file.write_named(Receipt::PATHS, &expected.entries, &mut context)
+                .unwrap();
+            file.set_named(c"Unknown", b"opaque\0").unwrap();
+            file.commit().unwrap();
+            let bytes = file.into_inner().into_inner();
+            let bom = Bom::read(&bytes[..]).unwrap();
+            let mut writer = Cursor::new(Vec::new());
+            let stats = bom
+                .compact(&bytes, &mut writer, Receipt::block_layout)
+                .unwrap();
+            assert!(stats.reclaimed() > 0);
+            let bytes = writer.into_inner();
+            // unknown named blocks are kept by the compaction and by the receipt
+            let (mut actual, warnings) =
+                Receipt::read_with_options(&bytes[..], ReadOptions::new().strict(true)).unwrap();
+            assert!(warnings.is_empty());
+            let mut writer = Cursor::new(Vec::new());
+            actual.write(&mut writer).unwrap();
+            let bytes = writer.into_inner();
+            let mut bom = Bom::read(&bytes[..]).unwrap();
+            let unknown: CString = bom.read_named(c"Unknown", &bytes, &mut ()).unwrap();
+            assert_eq!(c"opaque", unknown.as_c_str());
+            assert!(actual.remove_extension(c"Unknown"));
+            assert_eq!(expected, actual);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn read_corrupted() {
+        arbtest(|u| {
+            let receipt: Receipt = u.arbitrary()?;
+            let mut writer = Cursor::new(Vec::new());
+            receipt.write(&mut writer).unwrap();
+            let mut bytes = writer.into_inner();
+            for _ in 0..u.int_in_range(1..=10)? {
+                let i = u.choose_index(bytes.len())?;
+                bytes[i] = u.arbitrary()?;
+            }
+            // errors are fine, panics are not
+            let _ = Receipt::read(&bytes[..]);
+            let _ = Receipt::check(&bytes[..]);
+            if let Ok(view) = ReceiptView::new(&bytes[..]) {
+                for component in view.iter().flatten() {
+                    let _ = view.path(&component);
+                }
+            }
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn error_trace() {
+        let mut receipt = Receipt::new(PathComponentVec::new(Vec::new()));
+        receipt
+            .insert("./bin", Metadata::directory(0o40755, 0, 0, 0).unwrap())
+            .unwrap();
+        let mut writer = Cursor::new(Vec::new());
+        receipt.write(&mut writer).unwrap();
+        let mut file = BomFile::new(writer).unwrap();
+        let tree = file.bom().get_named(Receipt::PATHS).unwrap();
+        let root = RawTree::read_be(&file.read_block(tree).unwrap()[..])
+            .unwrap()
+            .root;
+        let node = RawTreeNode::read_be(&file.read_block(root).unwrap()[..]).unwrap();
+        // point the metadata of the first path to a non-existent block
+        let (key, _) = node.entries[0];
+        let mut bytes = file.read_block(key).unwrap();
+        bytes[4..8].copy_from_slice(&1000_u32.to_be_bytes());
+        file.replace_block(key, &bytes).unwrap();
+        file.commit().unwrap();
+        let bytes = file.into_inner().into_inner();
+        let error = crate::Error::from(Receipt::read(&bytes[..]).unwrap_err());
+        assert!(matches!(
+            error,
+            crate::Error::InvalidBlockIndex { index: 1000, .. }
+        ));
+        assert_eq!(Some(Receipt::PATHS), error.trace().named_block.as_deref());
+        assert_eq!(vec![tree, key, 1000], error.trace().blocks);
+        assert_eq!(
+            format!(
+                "invalid block index 1000 (named block `Paths`, blocks {} -> {} -> 1000)",
+                tree, key
+            ),
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn strict_and_lenient() {
+        let mut receipt = Receipt::new(PathComponentVec::new(Vec::new()));
+        receipt
+            .insert("./bin", Metadata::directory(0o40755, 0, 0, 0).unwrap())
+            .unwrap();
+        let mut writer = Cursor::new(Vec::new());
+        receipt.write(&mut writer).unwrap();
+        let mut file = BomFile::new(writer).unwrap();
+        let tree = file.bom().get_named(Receipt::PATHS).unwrap();
+        let root = RawTree::read_be(&file.read_block(tree).unwrap()[..])
+            .unwrap()
+            .root;
+        let node = RawTreeNode::read_be(&file.read_block(root).unwrap()[..]).unwrap();
+        let (key, _) = node.entries[0];
+        let metadata = u32::read_be(&file.read_block(key).unwrap()[4..8]).unwrap();
+        // change the unknown field that is always 1
+        let mut bytes = file.read_block(metadata).unwrap();
+        bytes[1] = 7;
+        file.replace_block(metadata, &bytes).unwrap();
+        file.commit().unwrap();
+        let bytes = file.into_inner().into_inner();
+        let error = crate::Error::from(
+            Receipt::read_with_options(&bytes[..], ReadOptions::new().strict(true)).unwrap_err(),
+        );
+        assert!(matches!(error, crate::Error::InvalidData { .. }));
+        assert_eq!(vec![tree, key, metadata], error.trace().blocks);
+        let (actual, warnings) =
+            Receipt::read_with_options(&bytes[..], ReadOptions::new()).unwrap();
+        assert_eq!(walk_paths(&receipt), walk_paths(&actual));
+        assert_eq!(
+            Some(0o40755),
+            actual
+                .get("./bin")
+                .map(|component| component.metadata.mode())
+        );
+        assert_eq!(
+            vec![Finding::new(
+                Some(metadata),
+                "unexpected value 7 in metadata"
+            )],
+            warnings
+        );
+    }
+
+    #[test]
+    fn keep_unknown() {
+        let mut receipt = Receipt::new(PathComponentVec::new(Vec::new()));
+        receipt
+            .insert("./bin", Metadata::directory(0o40755, 0, 0, 0).unwrap())
+            .unwrap();
+        let mut writer = Cursor::new(Vec::new());
+        receipt.write(&mut writer).unwrap();
+        let mut file = BomFile::new(writer).unwrap();
+        file.set_named(c"Extra", b"payload").unwrap();
+        // change the unknown field and remove the trailing zeroes
+        let metadata = first_metadata_block(&mut file);
+        let mut bytes = file.read_block(metadata).unwrap();
+        bytes[1] = 7;
+        bytes.truncate(bytes.len() - 8);
+        file.replace_block(metadata, &bytes).unwrap();
+        let expected_metadata = bytes;
+        // change the unknown field of the first statistics entry
+        let mut bytes = file.read_named_block(Receipt::BOM_INFO).unwrap();
+        bytes[16..20].copy_from_slice(&5_u32.to_be_bytes());
+        file.set_named(Receipt::BOM_INFO, &bytes).unwrap();
+        file.commit().unwrap();
+        let bytes = file.into_inner().into_inner();
+        let (receipt, expected_warnings) =
+            Receipt::read_with_options(&bytes[..], ReadOptions::new()).unwrap();
+        assert_eq!(2, expected_warnings.len());
+        let mut writer = Cursor::new(Vec::new());
+        receipt.write(&mut writer).unwrap();
+        let bytes = writer.into_inner();
+        let (actual, warnings) =
+            Receipt::read_with_options(&bytes[..], ReadOptions::new()).unwrap();
+        assert_eq!(receipt, actual);
+        assert_eq!(
+            expected_warnings
+                .into_iter()
+                .map(|w| w.message)
+                .collect::<Vec<_>>(),
+            warnings.into_iter().map(|w| w.message).collect::<Vec<_>>()
+        );
+        let mut file = BomFile::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(b"payload", &file.read_named_block(c"Extra").unwrap()[..]);
+        let metadata = first_metadata_block(&mut file);
+        assert_eq!(expected_metadata, file.read_block(metadata).unwrap());
+    }
+
+    #[test]
+    fn extensions() {
+        let mut receipt = Receipt::new(PathComponentVec::new(Vec::new()));
+        receipt
+            .insert("./bin", Metadata::directory(0o40755, 0, 0, 0).unwrap())
+            .unwrap();
+        let build_id = CString::new("1234").unwrap();
+        let provenance = || -> crate::receipt::VecTree<CString, CString> {
+            crate::VecTree::new(vec![(c"source".into(), c"git".into())], 4096)
+        };
+        receipt.set_extension(c"BuildId", build_id.clone()).unwrap();
+        // the tree spans multiple blocks
+        receipt.set_extension(c"Provenance", provenance()).unwrap();
+        receipt.set_extension(Receipt::PATHS, 0_u32).unwrap_err();
+        assert_eq!(Some(&build_id), receipt.extension::<CString>(c"BuildId"));
+        assert_eq!(None, receipt.extension::<u32>(c"BuildId"));
+        let mut writer = Cursor::new(Vec::new());
+        receipt.write(&mut writer).unwrap();
+        let bytes = writer.into_inner();
+        assert_eq!(
+            Some(build_id.clone()),
+            Receipt::read_extension::<CString>(&bytes, c"BuildId").unwrap()
+        );
+        assert_eq!(
+            Some(provenance()),
+            Receipt::read_extension(&bytes, c"Provenance").unwrap()
+        );
+        assert_eq!(
+            None,
+            Receipt::read_extension::<CString>(&bytes, c"Missing").unwrap()
+        );
+        // unregistered extensions are kept as is on read
+        let actual = Receipt::read(&bytes[..]).unwrap();
+        assert_eq!(None, actual.extension::<CString>(c"BuildId"));
+        let mut writer = Cursor::new(Vec::new());
+        actual.write(&mut writer).unwrap();
+        assert_eq!(
+            Some(build_id.clone()),
+            Receipt::read_extension::<CString>(&writer.into_inner(), c"BuildId").unwrap()
+        );
+        // registered extensions are decoded on read and survive the round trip
+        let registry = ExtensionRegistry::new()
+            .register::<_, CString>(c"BuildId")
+            .register::<_, crate::receipt::VecTree<CString, CString>>(c"Provenance");
+        let (mut actual, warnings) =
+            Receipt::read_with_extensions(&bytes[..], ReadOptions::new(), &registry).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(receipt, actual);
+        assert_eq!(Some(&build_id), actual.extension::<CString>(c"BuildId"));
+        let mut writer = Cursor::new(Vec::new());
+        actual.write(&mut writer).unwrap();
+        let bytes = writer.into_inner();
+        assert_eq!(
+            Some(build_id.clone()),
+            Receipt::read_extension::<CString>(&bytes, c"BuildId").unwrap()
+        );
+        assert_eq!(
+            Some(provenance()),
+            Receipt::read_extension(&bytes, c"Provenance").unwrap()
+        );
+        // malformed extensions are reported and are kept as is
+        let registry = ExtensionRegistry::new()
+            .register::<_, crate::receipt::VecTree<CString, CString>>(c"BuildId");
+        let (malformed, warnings) =
+            Receipt::read_with_extensions(&bytes[..], ReadOptions::new(), &registry).unwrap();
+        assert_eq!(1, warnings.len());
+        let mut writer = Cursor::new(Vec::new());
+        malformed.write(&mut writer).unwrap();
+        assert_eq!(
+            Some(build_id),
+            Receipt::read_extension::<CString>(&writer.into_inner(), c"BuildId").unwrap()
+        );
+        assert!(Receipt::read_with_extensions(
+            &bytes[..],
+            ReadOptions::new().strict(true),
+            &registry
+        )
+        .is_err());
+        assert!(actual.remove_extension(c"BuildId"));
+        assert!(!actual.remove_extension(c"BuildId"));
+    }
+
+    fn first_metadata_block(file: &mut BomFile<Cursor<Vec<u8>>>) -> u32 {
+        let tree = file.read_named_block(Receipt::PATHS).unwrap();
+        let root = RawTree::read_be(&tree[..]).unwrap().root;
+        let node = RawTreeNode::read_be(&file.read_block(root).unwrap()[..]).unwrap();
+        let (key, _) = node.entries[0];
+        u32::read_be(&file.read_block(key).unwrap()[4..8]).unwrap()
+    }
+
+    #[test]
+    fn remove_all() {
+        arbtest(|u| {
+            let mut receipt: Receipt = u.arbitrary()?;
+            let paths: Vec<_> = receipt.walk().map(|(path, _)| path).collect();
+            let path = u.choose(&paths)?;
+            receipt.remove(path).unwrap();
+            assert_consistent(&receipt);
+            assert!(!receipt.contains(path));
+            Ok(())
+        });
+    }
+
+    fn walk_paths(receipt: &Receipt) -> Vec<String> {
+        receipt
+            .walk()
+            .map(|(path, _)| path.display().to_string())
+            .collect()
+    }
+
+    fn assert_consistent(receipt: &Receipt) {
+        for (i, component) in receipt.entries.iter().enumerate() {
+            assert_eq!(i + 1, component.seq_no as usize);
+            if component.parent != 0 {
+                assert!(component.parent < component.seq_no);
+                let parent = &receipt.entries[component.parent as usize - 1];
+                assert_eq!(EntryType::Directory, parent.metadata.entry_type());
+            }
+        }
+        assert_eq!(receipt.entries.len(), receipt.walk().count());
+    }
+
+    #[test]
+    fn rename_into_new_directory() {
+        let mut receipt = Receipt::new(PathComponentVec::new(Vec::new()));
+        let file = Metadata::file(0o100644, 0, 0, 0, 0, 0).unwrap();
+        receipt.insert("./a", file.clone()).unwrap();
+        receipt.insert("./dir/b", file).unwrap();
+        receipt.rename("./a", "./new/a").unwrap();
+        receipt.rename("./dir", "./new/dir").unwrap();
+        assert_consistent(&receipt);
+        let paths: Vec<_> = receipt
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(5, paths.len());
+        for (i, path) in paths.iter().enumerate().skip(1) {
+            let parent = path.parent().unwrap();
+            assert!(paths[..i].iter().any(|other| other == parent), "{path:?}");
+        }
+        let mut writer = Cursor::new(Vec::new());
+        receipt.write(&mut writer).unwrap();
+        let actual = Receipt::read(&writer.into_inner()[..]).unwrap();
+        assert_consistent(&actual);
+        assert_eq!(
+            paths,
+            walk_paths(&actual)
+                .into_iter()
+                .map(PathBuf::from)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    impl<'a> Arbitrary<'a> for Receipt {
+        fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+            Ok(Self::new(u.arbitrary()?))
+        }
+    }
+
+    #[test]
+    fn create_from_file_list() {
+        let text = b".\t40755\t0/0\n\
+            ./bin\t40755\t0/80\n\
+            ./bin/tool\t100755\t501/20\t1234\t4294967295\n";
+        let file_list = FileList::read(&text[..]).unwrap();
+        let receipt = ReceiptBuilder::new()
+            .create_from_file_list(file_list)
+            .unwrap();
+        let mut writer = Cursor::new(Vec::new());
+        receipt.write(&mut writer).unwrap();
+        let bytes = writer.into_inner();
+        let actual = Receipt::read(&bytes[..]).unwrap();
+        let paths: Vec<_> = actual
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(
+            vec![
+                PathBuf::from("."),
+                PathBuf::from("./bin"),
+                PathBuf::from("./bin/tool")
+            ],
+            paths
+        );
+        let text = b"./bin/tool\t100755\t501/20\t1234\t4294967295\n";
+        let file_list = FileList::read(&text[..]).unwrap();
+        assert!(ReceiptBuilder::new()
+            .create_from_file_list(file_list)
+            .is_err());
+    }
 }
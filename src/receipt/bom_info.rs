@@ -2,9 +2,14 @@ use std::io::Error;
 use std::io::Read;
 use std::io::Write;
 
+use crate::receipt::Context;
 use crate::receipt::PathComponentVec;
 use crate::BigEndianRead;
 use crate::BigEndianWrite;
+use crate::BlockRead;
+use crate::Blocks;
+use crate::ErrorContext;
+use crate::Warnings;
 
 /// File paths statistics.
 ///
@@ -48,29 +53,61 @@ impl BomInfo {
                 self.entries.push(BomInfoEntry {
                     cpu_type,
                     file_size,
+                    x1: DEFAULT_X1,
+                    x2: DEFAULT_X2,
                 });
             }
         }
         self.num_paths += 1;
     }
+
+    /// Get the entries which unknown fields have non-default values.
+    pub(crate) fn unknown_fields(&self) -> Vec<BomInfoEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.x1 != DEFAULT_X1 || entry.x2 != DEFAULT_X2)
+            .cloned()
+            .collect()
+    }
+
+    /// Copy the unknown fields from `other` entries with the same CPU type.
+    pub(crate) fn set_unknown_fields(&mut self, other: &[BomInfoEntry]) {
+        for entry in self.entries.iter_mut() {
+            if let Some(other) = other.iter().find(|x| x.cpu_type == entry.cpu_type) {
+                entry.x1 = other.x1;
+                entry.x2 = other.x2;
+            }
+        }
+    }
 }
 
-impl BigEndianRead for BomInfo {
-    fn read_be<R: Read>(mut reader: R) -> Result<Self, Error> {
+impl BomInfo {
+    fn read<R: Read>(mut reader: R, block: u32, warnings: &mut Warnings) -> Result<Self, Error> {
         let version = u32::read_be(reader.by_ref())?;
         if version != Self::VERSION {
-            return Err(Error::other("unsupported bom info version"));
+            return Err(crate::Error::unsupported_version("bom info", version));
         }
         let num_paths = u32::read_be(reader.by_ref())?;
         let num_entries = u32::read_be(reader.by_ref())?;
         let mut entries = Vec::new();
         for _ in 0..num_entries {
-            entries.push(BomInfoEntry::read_be(reader.by_ref())?);
+            entries.push(BomInfoEntry::read(reader.by_ref(), block, warnings)?);
         }
         Ok(Self { num_paths, entries })
     }
 }
 
+impl BlockRead<Context> for BomInfo {
+    fn read_block(
+        i: u32,
+        file: &[u8],
+        blocks: &mut Blocks,
+        context: &mut Context,
+    ) -> Result<Self, Error> {
+        Self::read(blocks.slice(i, file)?, i, &mut context.warnings).in_block(i)
+    }
+}
+
 impl BigEndianWrite for BomInfo {
     fn write_be<W: Write>(&self, mut writer: W) -> Result<(), Error> {
         Self::VERSION.write_be(writer.by_ref())?;
@@ -83,34 +120,45 @@ impl BigEndianWrite for BomInfo {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(test, derive(arbitrary::Arbitrary, PartialEq, Eq))]
 pub(crate) struct BomInfoEntry {
     cpu_type: u32,
     file_size: u32,
+    // Unknown fields are kept as is to write the block back unchanged.
+    #[cfg_attr(test, arbitrary(default))]
+    x1: u32,
+    #[cfg_attr(test, arbitrary(default))]
+    x2: u32,
 }
 
-impl BigEndianRead for BomInfoEntry {
-    fn read_be<R: Read>(mut reader: R) -> Result<Self, Error> {
+impl BomInfoEntry {
+    fn read<R: Read>(mut reader: R, block: u32, warnings: &mut Warnings) -> Result<Self, Error> {
         let cpu_type = u32::read_be(reader.by_ref())?;
-        let _x1 = u32::read_be(reader.by_ref())?;
+        let x1 = u32::read_be(reader.by_ref())?;
         let file_size = u32::read_be(reader.by_ref())?;
-        let _x2 = u32::read_be(reader.by_ref())?;
-        let entry = BomInfoEntry {
+        let x2 = u32::read_be(reader.by_ref())?;
+        if x1 != DEFAULT_X1 || x2 != DEFAULT_X2 {
+            warnings.report(
+                Some(block),
+                format!("unexpected values {} and {} in bom info entry", x1, x2),
+            )?;
+        }
+        Ok(BomInfoEntry {
             cpu_type,
             file_size,
-        };
-        debug_assert!(_x1 == DEFAULT_X1 && _x2 == DEFAULT_X2, "entry = {entry:?}");
-        Ok(entry)
+            x1,
+            x2,
+        })
     }
 }
 
 impl BigEndianWrite for BomInfoEntry {
     fn write_be<W: Write>(&self, mut writer: W) -> Result<(), Error> {
         self.cpu_type.write_be(writer.by_ref())?;
-        DEFAULT_X1.write_be(writer.by_ref())?;
+        self.x1.write_be(writer.by_ref())?;
         self.file_size.write_be(writer.by_ref())?;
-        DEFAULT_X2.write_be(writer.by_ref())?;
+        self.x2.write_be(writer.by_ref())?;
         Ok(())
     }
 }
@@ -121,11 +169,10 @@ const DEFAULT_X2: u32 = 0;
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test::test_be_io_symmetry;
+    use crate::test::block_io_symmetry;
 
     #[test]
     fn write_read_symmetry() {
-        test_be_io_symmetry::<BomInfo>();
-        test_be_io_symmetry::<BomInfoEntry>();
+        block_io_symmetry::<BomInfo>();
     }
 }
@@ -0,0 +1,175 @@
+use std::collections::HashSet;
+
+use crate::receipt::FileType;
+use crate::receipt::Receipt;
+use crate::receipt::ReceiptView;
+use crate::Bom;
+use crate::Finding;
+
+impl Receipt {
+    /// Check the integrity of the receipt stored in `file` and return all problems found.
+    ///
+    /// In addition to the checks done by [`Bom::check`] checks that the sequential numbers have
+    /// no gaps, parents exist, and file modes match entry types.
+    pub fn check(file: &[u8]) -> Vec<Finding> {
+        let mut findings = Bom::check(file, Self::block_layout);
+        let view = match ReceiptView::new(file) {
+            Ok(view) => view,
+            Err(e) => {
+                findings.push(Finding::new(None, format!("failed to read paths: {}", e)));
+                return findings;
+            }
+        };
+        let mut components = Vec::with_capacity(view.len());
+        for component in view.iter() {
+            match component {
+                Ok(component) => components.push(component),
+                Err(e) => findings.push(Finding::new(None, format!("invalid path: {}", e))),
+            }
+        }
+        // sequential numbers start from 1 and have no gaps
+        let mut expected = 1;
+        for component in components.iter() {
+            let seq_no = component.seq_no;
+            if seq_no < expected {
+                findings.push(Finding::new(
+                    None,
+                    format!("duplicate sequential number {}", seq_no),
+                ));
+                continue;
+            }
+            if seq_no > expected {
+                findings.push(Finding::new(
+                    None,
+                    format!("sequential numbers {}..{} are missing", expected, seq_no),
+                ));
+            }
+            expected = seq_no.saturating_add(1);
+        }
+        let seq_nos: HashSet<_> = components.iter().map(|c| c.seq_no).collect();
+        for component in components.iter() {
+            let name = component.name.to_string_lossy();
+            if component.parent != 0 && !seq_nos.contains(&component.parent) {
+                findings.push(Finding::new(
+                    None,
+                    format!(
+                        "path {} `{}`: parent {} does not exist",
+                        component.seq_no, name, component.parent
+                    ),
+                ));
+            }
+            let metadata = &component.metadata;
+            if metadata.is_paths_only() {
+                continue;
+            }
+            match FileType::new(metadata.mode()) {
+                Ok(file_type) if file_type.to_entry_type() == metadata.entry_type() => {}
+                Ok(file_type) => findings.push(Finding::new(
+                    None,
+                    format!(
+                        "path {} `{}`: mode {:o} is {:?}, but entry type is {:?}",
+                        component.seq_no,
+                        name,
+                        metadata.mode(),
+                        file_type,
+                        metadata.entry_type()
+                    ),
+                )),
+                Err(e) => findings.push(Finding::new(
+                    None,
+                    format!(
+                        "path {} `{}`: invalid mode {:o}: {}",
+                        component.seq_no,
+                        name,
+                        metadata.mode(),
+                        e
+                    ),
+                )),
+            }
+        }
+        findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use arbtest::arbtest;
+
+    use super::*;
+    use crate::receipt::Metadata;
+    use crate::receipt::PathComponentVec;
+    use crate::BigEndianRead;
+    use crate::BigEndianWrite;
+    use crate::BomFile;
+    use crate::RawTree;
+    use crate::RawTreeNode;
+
+    #[test]
+    fn valid() {
+        arbtest(|u| {
+            let receipt: Receipt = u.arbitrary()?;
+            let mut writer = Cursor::new(Vec::new());
+            receipt.write(&mut writer).unwrap();
+            let file = writer.into_inner();
+            assert_eq!(Vec::<Finding>::new(), Receipt::check(&file));
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn invalid() {
+        let mut receipt = Receipt::new(PathComponentVec::new(Vec::new()));
+        receipt
+            .insert("./bin", Metadata::directory(0o40755, 0, 0, 0).unwrap())
+            .unwrap();
+        receipt
+            .insert("./bin/ls", Metadata::file(0o100755, 0, 0, 0, 0, 0).unwrap())
+            .unwrap();
+        let mut writer = Cursor::new(Vec::new());
+        receipt.write(&mut writer).unwrap();
+        let mut file = BomFile::new(writer).unwrap();
+        let tree = RawTree::read_be(&file.read_named_block(Receipt::PATHS).unwrap()[..]).unwrap();
+        let node = RawTreeNode::read_be(&file.read_block(tree.root).unwrap()[..]).unwrap();
+        let mut entries = Vec::new();
+        for (key, value) in node.entries.into_iter() {
+            let key_bytes = file.read_block(key).unwrap();
+            let seq_no = u32::read_be(&key_bytes[..4]).unwrap();
+            let metadata = u32::read_be(&key_bytes[4..8]).unwrap();
+            entries.push((seq_no, key, metadata, value));
+        }
+        entries.sort_unstable();
+        assert_eq!(3, entries.len());
+        // change the sequential number of the last path from 3 to 5
+        let (_, key, metadata, _) = entries[2];
+        let mut bytes = Vec::new();
+        5_u32.write_be(&mut bytes).unwrap();
+        metadata.write_be(&mut bytes).unwrap();
+        file.replace_block(key, &bytes).unwrap();
+        // point the parent of the last path to a non-existent path
+        let (_, _, _, value) = entries[2];
+        let mut bytes = file.read_block(value).unwrap();
+        bytes[..4].copy_from_slice(&10_u32.to_be_bytes());
+        file.replace_block(value, &bytes).unwrap();
+        // make the directory look like a regular file
+        let (_, _, metadata, _) = entries[1];
+        let mut bytes = file.read_block(metadata).unwrap();
+        bytes[4..6].copy_from_slice(&0o100755_u16.to_be_bytes());
+        file.replace_block(metadata, &bytes).unwrap();
+        file.commit().unwrap();
+        let bytes = file.into_inner().into_inner();
+        let findings: Vec<_> = Receipt::check(&bytes)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            vec![
+                "sequential numbers 3..5 are missing".to_string(),
+                "path 2 `bin`: mode 100755 is Regular, but entry type is Directory".to_string(),
+                "path 5 `ls`: parent 10 does not exist".to_string(),
+            ],
+            findings
+        );
+    }
+}
@@ -1,9 +1,13 @@
+use std::collections::HashMap;
+
+use crate::receipt::FileDigests;
 use crate::receipt::FileSizes64;
 use crate::receipt::HardLinks;
+use crate::Warnings;
 
 /// File i/o context for receipts.
 ///
-/// Holds file-wide data. Currently this includes 64-bit file sizes and hard links.
+/// Holds file-wide data. Currently this includes 64-bit file sizes, file digests and hard links.
 ///
 /// The same instances of context should be passed to
 /// [`read_block`](crate::BlockRead::read_block) and
@@ -15,14 +19,27 @@ pub struct Context {
     /// 64-bit file sizes.
     pub file_sizes: FileSizes64,
 
+    /// SHA-256 digests of the files.
+    pub file_digests: FileDigests,
+
     /// Metadata block index to path mapping.
     pub hard_links: HardLinks,
 
-    /// Current metadata block index that was written in `PathComponentKey::write_block`.
+    /// Path component's sequential number to its metadata block index mapping.
     ///
-    /// This value is subsequently used by `PathComponentValue::write_block` to generate
-    /// hard links.
-    pub(crate) current_metadata_block_index: Option<u32>,
+    /// This mapping is filled in `PathComponentKey::read_block` and
+    /// `PathComponentKey::write_block`. Hard links share the same metadata block.
+    pub(crate) metadata_blocks: HashMap<u32, u32>,
+
+    /// Path component's sequential number to hard link group mapping.
+    ///
+    /// This mapping is filled in `PathComponentVec::write_block` and is subsequently used by
+    /// `PathComponentKey::write_block` to share metadata blocks between hard links.
+    pub(crate) hard_link_groups: HashMap<u32, u32>,
+
+    /// Deviations from the known format found while reading the file.
+    #[cfg_attr(test, arbitrary(default))]
+    pub(crate) warnings: Warnings,
 }
 
 impl Context {
@@ -30,8 +47,11 @@ impl Context {
     pub fn new() -> Self {
         Self {
             file_sizes: Default::default(),
+            file_digests: Default::default(),
             hard_links: Default::default(),
-            current_metadata_block_index: Default::default(),
+            metadata_blocks: Default::default(),
+            hard_link_groups: Default::default(),
+            warnings: Default::default(),
         }
     }
 }
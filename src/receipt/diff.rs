@@ -0,0 +1,706 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::path::PathBuf;
+
+use crate::receipt::EntryType;
+use crate::receipt::ExecutableArch;
+use crate::receipt::FileDigest;
+use crate::receipt::Hex;
+use crate::receipt::Metadata;
+use crate::receipt::Receipt;
+
+/// Compare two receipts.
+///
+/// A file that was removed from one location and added to another one is considered moved if no
+/// other removed or added file has the same type, permissions, owner, size and checksum.
+/// Directories are never considered moved.
+pub fn diff(old: &Receipt, new: &Receipt) -> ReceiptDiff {
+    let mut diff = ReceiptDiff::default();
+    let mut removed = Vec::new();
+    for (path, old_component) in old.walk() {
+        let old_metadata = &old_component.metadata;
+        match new.get(&path) {
+            Some(new_component) => {
+                let new_metadata = &new_component.metadata;
+                if old_metadata.entry_type() != new_metadata.entry_type() {
+                    diff.type_changed.push(TypeChange {
+                        path,
+                        old: old_metadata.entry_type(),
+                        new: new_metadata.entry_type(),
+                    });
+                    continue;
+                }
+                let changes = changes(old_metadata, new_metadata);
+                if !changes.is_empty() {
+                    diff.changed.push(PathChange { path, changes });
+                }
+            }
+            None => removed.push((path, old_metadata)),
+        }
+    }
+    let mut added = Vec::new();
+    for (path, new_component) in new.walk() {
+        if !old.contains(&path) {
+            added.push((path, &new_component.metadata));
+        }
+    }
+    // Match removed and added files with unique keys.
+    let removed_keys = unique_keys(&removed);
+    let added_keys = unique_keys(&added);
+    let mut moved_to: HashMap<usize, usize> = HashMap::new();
+    for (key, i) in removed_keys.iter() {
+        if let Some(j) = added_keys.get(key) {
+            moved_to.insert(*i, *j);
+        }
+    }
+    let mut moved_from: HashMap<usize, usize> = HashMap::new();
+    for (i, (path, old_metadata)) in removed.iter().enumerate() {
+        match moved_to.get(&i) {
+            Some(j) => {
+                moved_from.insert(*j, i);
+            }
+            None => diff.removed.push(DiffEntry {
+                path: path.clone(),
+                metadata: (*old_metadata).clone(),
+            }),
+        }
+    }
+    for (j, (path, new_metadata)) in added.into_iter().enumerate() {
+        match moved_from.get(&j).and_then(|i| removed.get(*i)) {
+            Some((old_path, old_metadata)) => diff.moved.push(Move {
+                old_path: old_path.clone(),
+                new_path: path,
+                changes: changes(old_metadata, new_metadata),
+            }),
+            None => diff.added.push(DiffEntry {
+                path,
+                metadata: new_metadata.clone(),
+            }),
+        }
+    }
+    diff
+}
+
+/// The result of comparing two receipts.
+///
+/// Returned by [`diff`].
+#[derive(Debug, Default)]
+#[cfg_attr(test, derive(PartialEq, Eq))]
+pub struct ReceiptDiff {
+    added: Vec<DiffEntry>,
+    removed: Vec<DiffEntry>,
+    moved: Vec<Move>,
+    type_changed: Vec<TypeChange>,
+    changed: Vec<PathChange>,
+}
+
+impl ReceiptDiff {
+    /// Paths that are present only in the new receipt.
+    pub fn added(&self) -> &[DiffEntry] {
+        &self.added[..]
+    }
+
+    /// Paths that are present only in the old receipt.
+    pub fn removed(&self) -> &[DiffEntry] {
+        &self.removed[..]
+    }
+
+    /// Files that were moved to a different location.
+    pub fn moved(&self) -> &[Move] {
+        &self.moved[..]
+    }
+
+    /// Paths that changed their type, e.g. a file that was replaced with a directory.
+    pub fn type_changed(&self) -> &[TypeChange] {
+        &self.type_changed[..]
+    }
+
+    /// Paths which metadata changed.
+    pub fn changed(&self) -> &[PathChange] {
+        &self.changed[..]
+    }
+
+    /// Returns `true` if the receipts are equal.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.moved.is_empty()
+            && self.type_changed.is_empty()
+            && self.changed.is_empty()
+    }
+}
+
+/// Added or removed path.
+#[derive(Debug)]
+#[cfg_attr(test, derive(PartialEq, Eq))]
+pub struct DiffEntry {
+    /// Full path.
+    pub path: PathBuf,
+    /// File metadata.
+    pub metadata: Metadata,
+}
+
+/// Moved file.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Move {
+    /// The path in the old receipt.
+    pub old_path: PathBuf,
+    /// The path in the new receipt.
+    pub new_path: PathBuf,
+    /// Metadata changes that are not taken into account when detecting moved files,
+    /// i.e. modification time.
+    pub changes: Vec<Change>,
+}
+
+/// A path that changed its type.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TypeChange {
+    /// Full path.
+    pub path: PathBuf,
+    /// The type in the old receipt.
+    pub old: EntryType,
+    /// The type in the new receipt.
+    pub new: EntryType,
+}
+
+/// A path which metadata changed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PathChange {
+    /// Full path.
+    pub path: PathBuf,
+    /// Changed fields.
+    pub changes: Vec<Change>,
+}
+
+/// A changed metadata field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// File mode.
+    Mode {
+        /// Old value.
+        old: u16,
+        /// New value.
+        new: u16,
+    },
+    /// Owner's user id.
+    Uid {
+        /// Old value.
+        old: u32,
+        /// New value.
+        new: u32,
+    },
+    /// Owner's group id.
+    Gid {
+        /// Old value.
+        old: u32,
+        /// New value.
+        new: u32,
+    },
+    /// File size.
+    Size {
+        /// Old value.
+        old: u64,
+        /// New value.
+        new: u64,
+    },
+    /// File checksum.
+    Checksum {
+        /// Old value.
+        old: u32,
+        /// New value.
+        new: u32,
+    },
+    /// Modification time.
+    Mtime {
+        /// Old value.
+        old: u32,
+        /// New value.
+        new: u32,
+    },
+    /// Symbolic link target.
+    Target {
+        /// Old value.
+        old: PathBuf,
+        /// New value.
+        new: PathBuf,
+    },
+    /// Device number.
+    Rdev {
+        /// Old value.
+        old: i32,
+        /// New value.
+        new: i32,
+    },
+    /// Size of the executable's architecture.
+    ArchSize {
+        /// CPU type.
+        cpu_type: u32,
+        /// CPU sub-type.
+        cpu_sub_type: u32,
+        /// Old value.
+        old: u32,
+        /// New value.
+        new: u32,
+    },
+    /// Checksum of the executable's architecture.
+    ArchChecksum {
+        /// CPU type.
+        cpu_type: u32,
+        /// CPU sub-type.
+        cpu_sub_type: u32,
+        /// Old value.
+        old: u32,
+        /// New value.
+        new: u32,
+    },
+    /// SHA-256 digest of the file.
+    Sha256 {
+        /// Old value.
+        old: [u8; 32],
+        /// New value.
+        new: [u8; 32],
+    },
+    /// SHA-256 digest of the executable's architecture.
+    ArchSha256 {
+        /// CPU type.
+        cpu_type: u32,
+        /// CPU sub-type.
+        cpu_sub_type: u32,
+        /// Old value.
+        old: [u8; 32],
+        /// New value.
+        new: [u8; 32],
+    },
+    /// New architecture was added to the executable.
+    ArchAdded {
+        /// CPU type.
+        cpu_type: u32,
+        /// CPU sub-type.
+        cpu_sub_type: u32,
+    },
+    /// The architecture was removed from the executable.
+    ArchRemoved {
+        /// CPU type.
+        cpu_type: u32,
+        /// CPU sub-type.
+        cpu_sub_type: u32,
+    },
+}
+
+impl Change {
+    /// The name of the field.
+    pub fn name(&self) -> &'static str {
+        use Change::*;
+        match self {
+            Mode { .. } => "mode",
+            Uid { .. } => "uid",
+            Gid { .. } => "gid",
+            Size { .. } => "size",
+            Checksum { .. } => "checksum",
+            Mtime { .. } => "mtime",
+            Target { .. } => "target",
+            Rdev { .. } => "rdev",
+            ArchSize { .. } => "arch-size",
+            ArchChecksum { .. } => "arch-checksum",
+            Sha256 { .. } => "sha256",
+            ArchSha256 { .. } => "arch-sha256",
+            ArchAdded { .. } => "arch-added",
+            ArchRemoved { .. } => "arch-removed",
+        }
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use Change::*;
+        match self {
+            Mode { old, new } => write!(f, "mode {:o} -> {:o}", old, new),
+            Uid { old, new } => write!(f, "uid {} -> {}", old, new),
+            Gid { old, new } => write!(f, "gid {} -> {}", old, new),
+            Size { old, new } => write!(f, "size {} -> {}", old, new),
+            Checksum { old, new } => write!(f, "checksum {} -> {}", old, new),
+            Mtime { old, new } => write!(f, "mtime {} -> {}", old, new),
+            Target { old, new } => write!(f, "target {} -> {}", old.display(), new.display()),
+            Rdev { old, new } => write!(f, "rdev {} -> {}", old, new),
+            ArchSize {
+                cpu_type,
+                cpu_sub_type,
+                old,
+                new,
+            } => write!(
+                f,
+                "arch {}/{} size {} -> {}",
+                cpu_type, cpu_sub_type, old, new
+            ),
+            ArchChecksum {
+                cpu_type,
+                cpu_sub_type,
+                old,
+                new,
+            } => write!(
+                f,
+                "arch {}/{} checksum {} -> {}",
+                cpu_type, cpu_sub_type, old, new
+            ),
+            Sha256 { old, new } => write!(f, "sha256 {} -> {}", Hex(old), Hex(new)),
+            ArchSha256 {
+                cpu_type,
+                cpu_sub_type,
+                old,
+                new,
+            } => write!(
+                f,
+                "arch {}/{} sha256 {} -> {}",
+                cpu_type,
+                cpu_sub_type,
+                Hex(old),
+                Hex(new)
+            ),
+            ArchAdded {
+                cpu_type,
+                cpu_sub_type,
+            } => write!(f, "arch {}/{} added", cpu_type, cpu_sub_type),
+            ArchRemoved {
+                cpu_type,
+                cpu_sub_type,
+            } => write!(f, "arch {}/{} removed", cpu_type, cpu_sub_type),
+        }
+    }
+}
+
+/// Compare metadata of the same type field by field.
+pub(crate) fn changes(old: &Metadata, new: &Metadata) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let has_common = |m: &Metadata| !matches!(m, Metadata::Entry(..));
+    if has_common(old) && has_common(new) {
+        if old.mode() != new.mode() {
+            changes.push(Change::Mode {
+                old: old.mode(),
+                new: new.mode(),
+            });
+        }
+        if old.uid() != new.uid() {
+            changes.push(Change::Uid {
+                old: old.uid(),
+                new: new.uid(),
+            });
+        }
+        if old.gid() != new.gid() {
+            changes.push(Change::Gid {
+                old: old.gid(),
+                new: new.gid(),
+            });
+        }
+        if old.size() != new.size() {
+            changes.push(Change::Size {
+                old: old.size(),
+                new: new.size(),
+            });
+        }
+        if old.checksum() != new.checksum() {
+            changes.push(Change::Checksum {
+                old: old.checksum(),
+                new: new.checksum(),
+            });
+        }
+        if old.mtime() != new.mtime() {
+            changes.push(Change::Mtime {
+                old: old.mtime(),
+                new: new.mtime(),
+            });
+        }
+    }
+    match (old, new) {
+        (Metadata::Link(old), Metadata::Link(new)) if old.target() != new.target() => {
+            changes.push(Change::Target {
+                old: old.target().to_path_buf(),
+                new: new.target().to_path_buf(),
+            });
+        }
+        (Metadata::Device(old), Metadata::Device(new)) if old.rdev() != new.rdev() => {
+            changes.push(Change::Rdev {
+                old: old.rdev(),
+                new: new.rdev(),
+            });
+        }
+        _ => {}
+    }
+    // Digests are compared only if both receipts have them.
+    let digests = old.digest().zip(new.digest());
+    if let Some((old, new)) = digests {
+        if old.sha256() != new.sha256() {
+            changes.push(Change::Sha256 {
+                old: *old.sha256(),
+                new: *new.sha256(),
+            });
+        }
+    }
+    arch_changes(arches(old), arches(new), digests, &mut changes);
+    changes
+}
+
+fn arches(metadata: &Metadata) -> &[ExecutableArch] {
+    match metadata {
+        Metadata::Executable(exe) => exe.arches(),
+        _ => &[],
+    }
+}
+
+fn arch_changes(
+    old: &[ExecutableArch],
+    new: &[ExecutableArch],
+    digests: Option<(&FileDigest, &FileDigest)>,
+    changes: &mut Vec<Change>,
+) {
+    let find = |arches: &[ExecutableArch], arch: &ExecutableArch| {
+        arches.iter().position(|other| {
+            other.cpu_type() == arch.cpu_type() && other.cpu_sub_type() == arch.cpu_sub_type()
+        })
+    };
+    for (i, old_arch) in old.iter().enumerate() {
+        let cpu_type = old_arch.cpu_type();
+        let cpu_sub_type = old_arch.cpu_sub_type();
+        let Some((j, new_arch)) = find(new, old_arch).and_then(|j| Some((j, new.get(j)?))) else {
+            changes.push(Change::ArchRemoved {
+                cpu_type,
+                cpu_sub_type,
+            });
+            continue;
+        };
+        if old_arch.size() != new_arch.size() {
+            changes.push(Change::ArchSize {
+                cpu_type,
+                cpu_sub_type,
+                old: old_arch.size(),
+                new: new_arch.size(),
+            });
+        }
+        if old_arch.checksum() != new_arch.checksum() {
+            changes.push(Change::ArchChecksum {
+                cpu_type,
+                cpu_sub_type,
+                old: old_arch.checksum(),
+                new: new_arch.checksum(),
+            });
+        }
+        // Architecture digests are in the same order as the architectures.
+        let arch_digests =
+            digests.and_then(|(old, new)| Some((old.arches().get(i)?, new.arches().get(j)?)));
+        if let Some((old, new)) = arch_digests {
+            if old != new {
+                changes.push(Change::ArchSha256 {
+                    cpu_type,
+                    cpu_sub_type,
+                    old: *old,
+                    new: *new,
+                });
+            }
+        }
+    }
+    for new_arch in new.iter() {
+        if find(old, new_arch).is_none() {
+            changes.push(Change::ArchAdded {
+                cpu_type: new_arch.cpu_type(),
+                cpu_sub_type: new_arch.cpu_sub_type(),
+            });
+        }
+    }
+}
+
+/// The fields that have to match to consider a file moved.
+#[derive(PartialEq, Eq, Hash)]
+struct MoveKey {
+    entry_type: EntryType,
+    mode: u16,
+    uid: u32,
+    gid: u32,
+    size: u64,
+    checksum: u32,
+}
+
+impl MoveKey {
+    fn new(metadata: &Metadata) -> Option<Self> {
+        match metadata {
+            Metadata::Directory(..) | Metadata::Entry(..) => None,
+            _ => Some(Self {
+                entry_type: metadata.entry_type(),
+                mode: metadata.mode(),
+                uid: metadata.uid(),
+                gid: metadata.gid(),
+                size: metadata.size(),
+                checksum: metadata.checksum(),
+            }),
+        }
+    }
+}
+
+/// Map keys that occur only once to the positions of the corresponding entries.
+fn unique_keys(entries: &[(PathBuf, &Metadata)]) -> HashMap<MoveKey, usize> {
+    let mut keys: HashMap<MoveKey, Option<usize>> = HashMap::new();
+    for (i, (_, metadata)) in entries.iter().enumerate() {
+        let Some(key) = MoveKey::new(metadata) else {
+            continue;
+        };
+        keys.entry(key)
+            .and_modify(|position| *position = None)
+            .or_insert(Some(i));
+    }
+    keys.into_iter()
+        .filter_map(|(key, i)| Some((key, i?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::receipt::Executable;
+    use crate::receipt::ExecutableType;
+    use crate::receipt::PathComponentVec;
+
+    #[test]
+    fn diff_receipts() {
+        let mut old = Receipt::new(PathComponentVec::new(Vec::new()));
+        let file = Metadata::file(0o100644, 0, 0, 1, 10, 20).unwrap();
+        old.insert("./bin/a", file.clone()).unwrap();
+        old.insert("./bin/b", file.clone()).unwrap();
+        old.insert(
+            "./lib/moved",
+            Metadata::file(0o100644, 0, 0, 1, 3, 4).unwrap(),
+        )
+        .unwrap();
+        old.insert("./lib/dir", Metadata::directory(0o40755, 0, 0, 0).unwrap())
+            .unwrap();
+        old.insert(
+            "./lib/link",
+            Metadata::symlink("a", 0o120755, 0, 0, 0).unwrap(),
+        )
+        .unwrap();
+        let exe = Executable::new(
+            vec![
+                ExecutableArch::new(7, 3, 100, 1),
+                ExecutableArch::new(0x100000c, 0, 200, 2),
+            ],
+            ExecutableType::Fat,
+        )
+        .unwrap();
+        old.insert("./bin/exe", exe.into()).unwrap();
+        let mut new = Receipt::new(PathComponentVec::new(Vec::new()));
+        new.insert(
+            "./bin/a",
+            Metadata::file(0o100755, 501, 0, 1, 10, 20).unwrap(),
+        )
+        .unwrap();
+        new.insert("./bin/c", file.clone()).unwrap();
+        new.insert(
+            "./share/moved",
+            Metadata::file(0o100644, 0, 0, 2, 3, 4).unwrap(),
+        )
+        .unwrap();
+        new.insert(
+            "./lib/dir",
+            Metadata::file(0o100644, 0, 0, 0, 0, 0).unwrap(),
+        )
+        .unwrap();
+        new.insert(
+            "./lib/link",
+            Metadata::symlink("b", 0o120755, 0, 0, 0).unwrap(),
+        )
+        .unwrap();
+        let exe = Executable::new(
+            vec![
+                ExecutableArch::new(7, 3, 101, 1),
+                ExecutableArch::new(0x1000007, 3, 300, 3),
+            ],
+            ExecutableType::Fat,
+        )
+        .unwrap();
+        new.insert("./bin/exe", exe.into()).unwrap();
+        let diff = diff(&old, &new);
+        assert!(!diff.is_empty());
+        let paths = |entries: &[DiffEntry]| -> Vec<PathBuf> {
+            entries.iter().map(|e| e.path.clone()).collect()
+        };
+        // `./bin/b` and `./bin/c` have the same key as `./bin/a` in the old receipt,
+        // but `./bin/a` is not removed, hence the move is detected.
+        assert_eq!(
+            Move {
+                old_path: "./bin/b".into(),
+                new_path: "./bin/c".into(),
+                changes: vec![],
+            },
+            diff.moved()[0]
+        );
+        assert_eq!(
+            Move {
+                old_path: "./lib/moved".into(),
+                new_path: "./share/moved".into(),
+                changes: vec![Change::Mtime { old: 1, new: 2 }],
+            },
+            diff.moved()[1]
+        );
+        assert_eq!(vec![PathBuf::from("./share")], paths(diff.added()));
+        assert!(diff.removed().is_empty());
+        assert_eq!(
+            vec![TypeChange {
+                path: "./lib/dir".into(),
+                old: EntryType::Directory,
+                new: EntryType::File,
+            }],
+            diff.type_changed()
+        );
+        let changed: Vec<_> = diff
+            .changed()
+            .iter()
+            .map(|c| (c.path.clone(), c.changes.clone()))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    PathBuf::from("./bin/a"),
+                    vec![
+                        Change::Mode {
+                            old: 0o100644,
+                            new: 0o100755
+                        },
+                        Change::Uid { old: 0, new: 501 },
+                    ]
+                ),
+                (
+                    PathBuf::from("./bin/exe"),
+                    vec![
+                        Change::ArchSize {
+                            cpu_type: 7,
+                            cpu_sub_type: 3,
+                            old: 100,
+                            new: 101
+                        },
+                        Change::ArchRemoved {
+                            cpu_type: 0x100000c,
+                            cpu_sub_type: 0
+                        },
+                        Change::ArchAdded {
+                            cpu_type: 0x1000007,
+                            cpu_sub_type: 3
+                        },
+                    ]
+                ),
+                (
+                    PathBuf::from("./lib/link"),
+                    vec![
+                        Change::Checksum {
+                            old: old.get("./lib/link").unwrap().metadata.checksum(),
+                            new: new.get("./lib/link").unwrap().metadata.checksum(),
+                        },
+                        Change::Target {
+                            old: "a".into(),
+                            new: "b".into()
+                        },
+                    ]
+                ),
+            ],
+            changed
+        );
+        assert!(super::diff(&old, &old).is_empty());
+    }
+}
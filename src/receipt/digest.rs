@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::io::Error;
+use std::io::Read;
+use std::io::Seek;
+use std::io::Write;
+use std::ops::Deref;
+use std::ops::DerefMut;
+
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::capacity_hint;
+use crate::receipt::Context;
+use crate::receipt::VecTree;
+use crate::BigEndianRead;
+use crate::BigEndianWrite;
+use crate::BlockRead;
+use crate::BlockWrite;
+use crate::Blocks;
+
+/// SHA-256 digests of the file contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(test, derive(arbitrary::Arbitrary))]
+pub struct FileDigest {
+    sha256: [u8; SHA256_LEN],
+    arches: Vec<[u8; SHA256_LEN]>,
+}
+
+impl FileDigest {
+    /// Create new digest from the digest of the whole file and the digests of each architecture.
+    ///
+    /// Architecture digests should be in the same order as
+    /// [`Executable::arches`](crate::receipt::Executable::arches).
+    pub fn new(sha256: [u8; SHA256_LEN], arches: Vec<[u8; SHA256_LEN]>) -> Self {
+        Self { sha256, arches }
+    }
+
+    /// Get SHA-256 digest of the whole file.
+    pub fn sha256(&self) -> &[u8; SHA256_LEN] {
+        &self.sha256
+    }
+
+    /// Get SHA-256 digests of each architecture of the universal binary.
+    ///
+    /// Empty for the other files.
+    pub fn arches(&self) -> &[[u8; SHA256_LEN]] {
+        &self.arches
+    }
+}
+
+impl BigEndianRead for FileDigest {
+    fn read_be<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let mut sha256 = [0_u8; SHA256_LEN];
+        reader.read_exact(&mut sha256[..])?;
+        let num_arches = u32::read_be(reader.by_ref())?;
+        let mut arches = Vec::with_capacity(capacity_hint(num_arches as usize));
+        for _ in 0..num_arches {
+            let mut sha256 = [0_u8; SHA256_LEN];
+            reader.read_exact(&mut sha256[..])?;
+            arches.push(sha256);
+        }
+        Ok(Self { sha256, arches })
+    }
+}
+
+impl BigEndianWrite for FileDigest {
+    fn write_be<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        writer.write_all(&self.sha256[..])?;
+        (self.arches.len() as u32).write_be(writer.by_ref())?;
+        for sha256 in self.arches.iter() {
+            writer.write_all(&sha256[..])?;
+        }
+        Ok(())
+    }
+}
+
+/// Metadata block index to file digest mapping.
+#[derive(Debug, Default)]
+#[cfg_attr(test, derive(arbitrary::Arbitrary, PartialEq, Eq))]
+pub struct FileDigests(HashMap<u32, FileDigest>);
+
+impl FileDigests {
+    const BLOCK_LEN: usize = 128;
+
+    /// Transform into inner representation.
+    pub fn into_inner(self) -> HashMap<u32, FileDigest> {
+        self.0
+    }
+}
+
+impl Deref for FileDigests {
+    type Target = HashMap<u32, FileDigest>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for FileDigests {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl BlockWrite<Context> for FileDigests {
+    fn write_block<W: Write + Seek>(
+        &self,
+        mut writer: W,
+        blocks: &mut Blocks,
+        context: &mut Context,
+    ) -> Result<u32, Error> {
+        let mut entries: Vec<_> = self.0.iter().map(|(k, v)| (v.clone(), *k)).collect();
+        // Sort by metadata block index to produce the same output for the same digests.
+        entries.sort_unstable_by_key(|(_, block)| *block);
+        let tree = FileDigestTree::new(entries, Self::BLOCK_LEN);
+        let i = tree.write_block(writer.by_ref(), blocks, context)?;
+        Ok(i)
+    }
+}
+
+impl BlockRead<Context> for FileDigests {
+    fn read_block(
+        i: u32,
+        file: &[u8],
+        blocks: &mut Blocks,
+        context: &mut Context,
+    ) -> Result<Self, Error> {
+        let tree = FileDigestTree::read_block(i, file, blocks, context)?;
+        Ok(Self(
+            tree.into_inner().into_iter().map(|(k, v)| (v, k)).collect(),
+        ))
+    }
+}
+
+/// Key is file digest, value is metadata block index.
+type FileDigestTree = VecTree<FileDigest, u32>;
+
+/// Compute SHA-256 digest of the contents of `reader`.
+pub(crate) fn sha256<R: Read>(mut reader: R) -> Result<[u8; SHA256_LEN], Error> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut reader, &mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
+/// Hexadecimal representation of the digest.
+pub(crate) struct Hex<'a>(pub(crate) &'a [u8]);
+
+impl Display for Hex<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for byte in self.0.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+const SHA256_LEN: usize = 32;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::block_io_symmetry;
+    use crate::test::test_be_io_symmetry;
+
+    #[test]
+    fn write_read_symmetry() {
+        test_be_io_symmetry::<FileDigest>();
+        block_io_symmetry::<FileDigests>();
+    }
+
+    #[test]
+    fn sha256_empty() {
+        assert_eq!(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            Hex(&sha256(&b""[..]).unwrap()).to_string()
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::io::Error;
+use std::io::Seek;
+use std::io::Write;
+
+use crate::receipt::Context;
+use crate::BlockRead;
+use crate::BlockWrite;
+use crate::Blocks;
+use crate::Bom;
+
+/// Application-defined named blocks of the receipt.
+#[derive(Default)]
+pub(crate) struct Extensions {
+    values: BTreeMap<CString, Box<dyn AnyExtension>>,
+}
+
+impl Extensions {
+    pub(crate) fn insert<T: BlockWrite<Context> + 'static>(&mut self, name: CString, value: T) {
+        self.values.insert(name, Box::new(value));
+    }
+
+    fn insert_boxed(&mut self, name: CString, value: Box<dyn AnyExtension>) {
+        self.values.insert(name, value);
+    }
+
+    pub(crate) fn get<T: 'static>(&self, name: &CStr) -> Option<&T> {
+        self.values.get(name)?.as_any().downcast_ref()
+    }
+
+    pub(crate) fn remove(&mut self, name: &CStr) -> bool {
+        self.values.remove(name).is_some()
+    }
+
+    /// Write each extension and return the index of its block.
+    pub(crate) fn write<W: Write + Seek>(
+        &self,
+        mut writer: W,
+        blocks: &mut Blocks,
+        context: &mut Context,
+    ) -> Result<Vec<(CString, u32)>, Error> {
+        let mut indices = Vec::with_capacity(self.values.len());
+        for (name, value) in self.values.iter() {
+            let i = value.write_extension(&mut writer, blocks, context)?;
+            indices.push((name.clone(), i));
+        }
+        Ok(indices)
+    }
+}
+
+impl Debug for Extensions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.values.keys()).finish()
+    }
+}
+
+// Extensions are equal if they have the same names and are encoded the same way.
+#[cfg(test)]
+impl PartialEq for Extensions {
+    fn eq(&self, other: &Self) -> bool {
+        use std::io::Cursor;
+        let encode = |extensions: &Self| -> Result<Vec<u8>, Error> {
+            let mut writer = Cursor::new(Vec::new());
+            extensions.write(&mut writer, &mut Blocks::new(), &mut Context::new())?;
+            Ok(writer.into_inner())
+        };
+        self.values.keys().eq(other.values.keys())
+            && matches!((encode(self), encode(other)), (Ok(a), Ok(b)) if a == b)
+    }
+}
+
+#[cfg(test)]
+impl Eq for Extensions {}
+
+/// Application-defined named blocks that are decoded on read.
+///
+/// Used by [`Receipt::read_with_extensions`](crate::receipt::Receipt::read_with_extensions).
+#[derive(Default)]
+pub struct ExtensionRegistry {
+    readers: BTreeMap<CString, ReadExtension>,
+}
+
+impl ExtensionRegistry {
+    /// Create empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode the named block `name` as a value of type `T`.
+    pub fn register<N, T>(mut self, name: N) -> Self
+    where
+        N: Into<CString>,
+        T: BlockRead<Context> + BlockWrite<Context> + 'static,
+    {
+        self.readers.insert(name.into(), read_extension::<T>);
+        self
+    }
+
+    /// Returns `true` if the named block `name` is registered.
+    pub fn contains(&self, name: &CStr) -> bool {
+        self.readers.contains_key(name)
+    }
+
+    /// Decode the named block `name` and add it to `extensions`.
+    pub(crate) fn read(
+        &self,
+        name: &CStr,
+        bom: &mut Bom,
+        file: &[u8],
+        context: &mut Context,
+        extensions: &mut Extensions,
+    ) -> Result<(), Error> {
+        if let Some(read) = self.readers.get(name) {
+            let value = read(bom, name, file, context)?;
+            extensions.insert_boxed(name.into(), value);
+        }
+        Ok(())
+    }
+}
+
+impl Debug for ExtensionRegistry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.readers.keys()).finish()
+    }
+}
+
+type ReadExtension =
+    fn(&mut Bom, &CStr, &[u8], &mut Context) -> Result<Box<dyn AnyExtension>, Error>;
+
+fn read_extension<T: BlockRead<Context> + BlockWrite<Context> + 'static>(
+    bom: &mut Bom,
+    name: &CStr,
+    file: &[u8],
+    context: &mut Context,
+) -> Result<Box<dyn AnyExtension>, Error> {
+    let value: T = bom.read_named(name, file, context)?;
+    Ok(Box::new(value))
+}
+
+trait AnyExtension {
+    fn write_extension(
+        &self,
+        writer: &mut dyn WriteSeek,
+        blocks: &mut Blocks,
+        context: &mut Context,
+    ) -> Result<u32, Error>;
+
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: BlockWrite<Context> + 'static> AnyExtension for T {
+    fn write_extension(
+        &self,
+        writer: &mut dyn WriteSeek,
+        blocks: &mut Blocks,
+        context: &mut Context,
+    ) -> Result<u32, Error> {
+        self.write_block(writer, blocks, context)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+trait WriteSeek: Write + Seek {}
+
+impl<W: Write + Seek> WriteSeek for W {}
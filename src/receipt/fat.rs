@@ -4,6 +4,7 @@ use std::io::Read;
 use std::io::Seek;
 use std::io::SeekFrom;
 
+use crate::receipt::sha256;
 use crate::receipt::CrcReader;
 use crate::receipt::ExecutableArch;
 use crate::BigEndianRead;
@@ -23,6 +24,15 @@ impl FatBinary {
         }
         Ok(arches)
     }
+
+    pub fn to_arch_digests<R: Read + Seek>(&self, mut file: R) -> Result<Vec<[u8; 32]>, Error> {
+        let mut digests = Vec::with_capacity(self.arches.len());
+        for arch in self.arches.iter() {
+            file.seek(SeekFrom::Start(arch.offset))?;
+            digests.push(sha256(file.by_ref().take(arch.size))?);
+        }
+        Ok(digests)
+    }
 }
 
 impl BigEndianRead for FatBinary {
@@ -0,0 +1,196 @@
+use std::ffi::OsStr;
+use std::io::BufRead;
+use std::io::Error;
+use std::io::ErrorKind;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::receipt::Common;
+use crate::receipt::FileType;
+use crate::receipt::Metadata;
+
+/// A list of files in `lsbom` text format.
+///
+/// Each line contains tab-separated fields: file path, octal file mode, `uid/gid` pair and
+/// type-specific fields.
+/// - Regular files: file size and checksum.
+/// - Symbolic links: file size, checksum and link target.
+/// - Block and character devices: device number.
+/// - Directories: no additional fields.
+///
+/// This is the format that `lsbom` produces by default and that `mkbom -i` consumes.
+/// Empty lines are skipped. Any trailing fields are ignored.
+///
+/// Modification time is always set to zero: `lsbom` prints it only with `-m`
+/// and in the local time zone that is not recorded in the output.
+/// Executables can't be detected without reading the files and are stored as regular files.
+#[derive(Debug, Default)]
+pub struct FileList {
+    entries: Vec<(PathBuf, Metadata)>,
+}
+
+impl FileList {
+    /// Create an empty file list.
+    pub fn new() -> Self {
+        Self {
+            entries: Default::default(),
+        }
+    }
+
+    /// Parse file list from `reader`.
+    ///
+    /// Modification time of each entry is zero, see [`FileList`].
+    pub fn read<R: BufRead>(reader: R) -> Result<Self, Error> {
+        let mut entries = Vec::new();
+        for (i, line) in reader.split(b'\n').enumerate() {
+            let line = line?;
+            let line = line.strip_suffix(b"\r").unwrap_or(&line[..]);
+            if line.is_empty() {
+                continue;
+            }
+            let entry = parse_line(line).map_err(|e| {
+                Error::new(ErrorKind::InvalidData, format!("line {}: {}", i + 1, e))
+            })?;
+            entries.push(entry);
+        }
+        Ok(Self { entries })
+    }
+
+    /// Add new entry to the list.
+    pub fn push(&mut self, path: PathBuf, metadata: Metadata) {
+        self.entries.push((path, metadata));
+    }
+
+    /// Get all entries.
+    pub fn entries(&self) -> &[(PathBuf, Metadata)] {
+        &self.entries[..]
+    }
+
+    /// Transform into inner representation.
+    pub fn into_inner(self) -> Vec<(PathBuf, Metadata)> {
+        self.entries
+    }
+}
+
+fn parse_line(line: &[u8]) -> Result<(PathBuf, Metadata), Error> {
+    let mut fields = line.split(|b| *b == b'\t');
+    let path = fields.next().unwrap_or_default();
+    if path.is_empty() {
+        return Err(Error::other("empty file path"));
+    }
+    let path: PathBuf = OsStr::from_bytes(path).into();
+    let mode = fields
+        .next()
+        .ok_or_else(|| Error::other("missing file mode"))?;
+    let mode = std::str::from_utf8(mode)
+        .ok()
+        .and_then(|s| u16::from_str_radix(s, 8).ok())
+        .ok_or_else(|| Error::other("invalid file mode"))?;
+    let owner = fields
+        .next()
+        .ok_or_else(|| Error::other("missing uid/gid"))?;
+    let (uid, gid) = std::str::from_utf8(owner)
+        .ok()
+        .and_then(|s| s.split_once('/'))
+        .ok_or_else(|| Error::other("invalid uid/gid"))?;
+    let uid: u32 = parse_number(uid.as_bytes(), "uid")?;
+    let gid: u32 = parse_number(gid.as_bytes(), "gid")?;
+    let mut size = 0;
+    let mut checksum = 0;
+    let mut target = PathBuf::new();
+    let mut dev = 0;
+    match FileType::new(mode).map_err(|_| Error::other("unsupported file type"))? {
+        FileType::Regular => {
+            size = parse_next(&mut fields, "file size")?;
+            checksum = parse_next(&mut fields, "checksum")?;
+        }
+        FileType::Symlink => {
+            size = parse_next(&mut fields, "file size")?;
+            checksum = parse_next(&mut fields, "checksum")?;
+            let link = fields
+                .next()
+                .ok_or_else(|| Error::other("missing link target"))?;
+            target = OsStr::from_bytes(link).into();
+        }
+        FileType::CharDevice | FileType::BlockDevice => {
+            dev = parse_next(&mut fields, "device number")?;
+        }
+        FileType::Directory => {}
+    }
+    let common = Common::new(mode, uid, gid, 0, size);
+    let metadata = Metadata::from_common(common, checksum, target, dev)?;
+    Ok((path, metadata))
+}
+
+fn parse_next<'a, T: FromStr, I: Iterator<Item = &'a [u8]>>(
+    fields: &mut I,
+    name: &str,
+) -> Result<T, Error> {
+    let field = fields
+        .next()
+        .ok_or_else(|| Error::other(format!("missing {}", name)))?;
+    parse_number(field, name)
+}
+
+fn parse_number<T: FromStr>(field: &[u8], name: &str) -> Result<T, Error> {
+    std::str::from_utf8(field)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| Error::other(format!("invalid {}", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::receipt::EntryType;
+
+    #[test]
+    fn read() {
+        let text = b".\t40755\t0/0\n\
+            ./bin\t40755\t0/80\n\
+            ./bin/tool\t100755\t501/20\t1234\t4294967295\n\
+            ./bin/link\t120755\t0/0\t4\t12345\ttool\n\
+            ./dev\t20644\t0/0\t1234\n";
+        let list = FileList::read(&text[..]).unwrap();
+        let entries = list.into_inner();
+        assert_eq!(5, entries.len());
+        assert_eq!(Path::new("./bin/tool"), entries[2].0);
+        let tool = &entries[2].1;
+        assert_eq!(EntryType::File, tool.entry_type());
+        assert_eq!(0o100755, tool.mode());
+        assert_eq!((501, 20), (tool.uid(), tool.gid()));
+        assert_eq!(1234, tool.size());
+        assert_eq!(u32::MAX, tool.checksum());
+        assert!(matches!(
+            &entries[3].1,
+            Metadata::Link(link) if link.target() == Path::new("tool")
+        ));
+        assert!(matches!(&entries[4].1, Metadata::Device(dev) if dev.rdev() == 1234));
+    }
+
+    #[test]
+    fn read_ignores_mtime() {
+        let text = b"./file\t100644\t0/0\t1\t2\tFri Oct 16 18:42:39 2026\n";
+        let entries = FileList::read(&text[..]).unwrap().into_inner();
+        assert_eq!(1, entries.len());
+        assert_eq!(0, entries[0].1.mtime());
+        assert_eq!((1, 2), (entries[0].1.size(), entries[0].1.checksum()));
+    }
+
+    #[test]
+    fn read_invalid() {
+        for text in [
+            &b"./file\n"[..],
+            b"./file\t100644\n",
+            b"./file\t100644\t0\t1\t2\n",
+            b"./file\t100644\t0/0\t1\n",
+            b"./file\t999\t0/0\n",
+            b"./link\t120755\t0/0\t1\t2\n",
+        ] {
+            assert!(FileList::read(text).is_err(), "text = {:?}", text);
+        }
+    }
+}
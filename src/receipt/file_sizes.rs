@@ -46,10 +46,10 @@ impl BlockWrite<Context> for FileSizes64 {
         blocks: &mut Blocks,
         context: &mut Context,
     ) -> Result<u32, Error> {
-        let file_size_tree = FileSizeTree::new(
-            self.0.iter().map(|(k, v)| (*v, *k)).collect(),
-            Self::BLOCK_LEN,
-        );
+        let mut entries: Vec<_> = self.0.iter().map(|(k, v)| (*v, *k)).collect();
+        // Sort by metadata block index to produce the same output for the same sizes.
+        entries.sort_unstable_by_key(|(_, block)| *block);
+        let file_size_tree = FileSizeTree::new(entries, Self::BLOCK_LEN);
         let i = file_size_tree.write_block(writer.by_ref(), blocks, context)?;
         Ok(i)
     }
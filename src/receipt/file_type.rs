@@ -41,7 +41,10 @@ impl FileType {
             BLOCK => Ok(BlockDevice),
             DIRECTORY => Ok(Directory),
             CHAR => Ok(CharDevice),
-            _ => Err(ErrorKind::InvalidData.into()),
+            _ => Err(crate::Error::invalid_data(format!(
+                "invalid file mode {:o}",
+                mode
+            ))),
         }
     }
 
@@ -123,7 +126,10 @@ impl TryFrom<u8> for EntryType {
             DIRECTORY => Ok(Directory),
             LINK => Ok(Link),
             DEVICE => Ok(Device),
-            _ => Err(ErrorKind::InvalidData.into()),
+            _ => Err(crate::Error::invalid_data(format!(
+                "invalid entry type {}",
+                other
+            ))),
         }
     }
 }
@@ -0,0 +1,189 @@
+use std::io::Error;
+use std::path::Path;
+use std::path::PathBuf;
+
+use globset::GlobBuilder;
+use globset::GlobMatcher;
+use ignore::gitignore::Gitignore;
+use ignore::gitignore::GitignoreBuilder;
+
+/// Decides which paths are included in the receipt.
+///
+/// A path is excluded if it matches any exclude pattern or any ignore file,
+/// and does not match any include pattern.
+#[derive(Default)]
+pub(crate) struct PathFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    ignore: Option<Gitignore>,
+}
+
+impl PathFilter {
+    /// Compile the patterns and read the ignore files.
+    ///
+    /// Patterns in the ignore files are relative to `root`.
+    pub(crate) fn new(
+        root: &Path,
+        include: &[String],
+        exclude: &[String],
+        ignore_files: &[PathBuf],
+    ) -> Result<Self, Error> {
+        let include = include
+            .iter()
+            .map(|s| Pattern::new(s))
+            .collect::<Result<Vec<_>, _>>()?;
+        let exclude = exclude
+            .iter()
+            .map(|s| Pattern::new(s))
+            .collect::<Result<Vec<_>, _>>()?;
+        let ignore = if ignore_files.is_empty() {
+            None
+        } else {
+            let mut builder = GitignoreBuilder::new(root);
+            for path in ignore_files.iter() {
+                if let Some(e) = builder.add(path) {
+                    return Err(Error::other(format!(
+                        "failed to read {}: {}",
+                        path.display(),
+                        e
+                    )));
+                }
+            }
+            Some(builder.build().map_err(Error::other)?)
+        };
+        Ok(Self {
+            include,
+            exclude,
+            ignore,
+        })
+    }
+
+    /// Returns `true` if the path should not be included in the receipt.
+    ///
+    /// The path is relative to the directory that is being scanned and should not start with `./`.
+    pub(crate) fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        if path == Path::new("") {
+            // Never exclude the root directory.
+            return false;
+        }
+        let excluded = self
+            .exclude
+            .iter()
+            .any(|pattern| pattern.is_match(path, is_dir))
+            || self
+                .ignore
+                .as_ref()
+                .is_some_and(|ignore| ignore.matched(path, is_dir).is_ignore());
+        excluded
+            && !self
+                .include
+                .iter()
+                .any(|pattern| pattern.is_match(path, is_dir))
+    }
+}
+
+/// Glob pattern.
+///
+/// Patterns without `/` match file names, e.g. `.DS_Store` excludes such files in every directory.
+/// Patterns with `/` match paths relative to the scanned directory, e.g. `/build` or `src/*.o`.
+/// Leading `/` or `./` is optional.
+/// `*` does not match `/`, use `**` to match any number of directories.
+/// Patterns that end with `/` match directories only.
+pub(crate) struct Pattern {
+    matcher: GlobMatcher,
+    match_path: bool,
+    dir_only: bool,
+}
+
+impl Pattern {
+    pub(crate) fn new(pattern: &str) -> Result<Self, Error> {
+        let (pattern, dir_only) = match pattern.strip_suffix('/') {
+            Some(pattern) => (pattern, true),
+            None => (pattern, false),
+        };
+        let match_path = pattern.contains('/');
+        let pattern = pattern.strip_prefix("./").unwrap_or(pattern);
+        let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+        let matcher = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| Error::other(format!("invalid pattern `{}`: {}", pattern, e)))?
+            .compile_matcher();
+        Ok(Self {
+            matcher,
+            match_path,
+            dir_only,
+        })
+    }
+
+    pub(crate) fn is_match(&self, path: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.match_path {
+            self.matcher.is_match(path)
+        } else {
+            path.file_name()
+                .is_some_and(|name| self.matcher.is_match(name))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn patterns() {
+        let filter = PathFilter::new(
+            Path::new("/"),
+            &["keep.o".into()],
+            &[
+                ".DS_Store".into(),
+                "*.o".into(),
+                "/build/".into(),
+                "./src/**/*.bak".into(),
+            ],
+            &[],
+        )
+        .unwrap();
+        for (expected, path, is_dir) in [
+            (true, ".DS_Store", false),
+            (true, "a/b/.DS_Store", false),
+            (true, "a/b/main.o", false),
+            (false, "a/b/keep.o", false),
+            (true, "build", true),
+            (false, "build", false),
+            (false, "a/build", true),
+            (true, "src/x/y/file.bak", false),
+            (true, "src/file.bak", false),
+            (false, "file.bak", false),
+            (false, "main.c", false),
+            (false, "", true),
+        ] {
+            assert_eq!(
+                expected,
+                filter.is_excluded(Path::new(path), is_dir),
+                "path = {:?}",
+                path
+            );
+        }
+        assert!(PathFilter::new(Path::new("/"), &[], &["a[".into()], &[]).is_err());
+    }
+
+    #[test]
+    fn ignore_file() {
+        let directory = TempDir::new().unwrap();
+        let ignore_file = directory.path().join(".mkbomignore");
+        std::fs::write(&ignore_file, "*~\n.git/\n!important~\n").unwrap();
+        let filter = PathFilter::new(directory.path(), &[], &[], &[ignore_file]).unwrap();
+        assert!(filter.is_excluded(Path::new("a/file~"), false));
+        assert!(filter.is_excluded(Path::new(".git"), true));
+        assert!(!filter.is_excluded(Path::new("important~"), false));
+        assert!(!filter.is_excluded(Path::new("file"), false));
+        let missing = directory.path().join("missing");
+        assert!(PathFilter::new(directory.path(), &[], &[], &[missing]).is_err());
+    }
+}
@@ -49,8 +49,11 @@ impl BlockWrite<Context> for HardLinks {
         blocks: &mut Blocks,
         context: &mut Context,
     ) -> Result<u32, Error> {
-        let mut hard_links = Vec::with_capacity(self.0.len());
-        for (block, paths) in self.0.iter().filter(|(_, paths)| paths.len() > 1) {
+        let mut groups: Vec<_> = self.0.iter().filter(|(_, paths)| paths.len() > 1).collect();
+        // Sort by metadata block index to produce the same output for the same hard links.
+        groups.sort_unstable_by_key(|(block, _)| **block);
+        let mut hard_links = Vec::with_capacity(groups.len());
+        for (block, paths) in groups.into_iter() {
             let paths_tree = PathsTree::new(
                 paths.iter().map(|path| ((), path.clone())).collect(),
                 Self::INNER_BLOCK_LEN,
@@ -1,7 +1,6 @@
 use std::ffi::CString;
 use std::ffi::OsStr;
 use std::fs::read_link;
-use std::io::Cursor;
 use std::io::Error;
 use std::io::ErrorKind;
 use std::io::Read;
@@ -13,11 +12,15 @@ use std::path::PathBuf;
 use std::time::Duration;
 use std::time::SystemTime;
 
+use crate::capacity_hint;
+use crate::read_vec;
+use crate::receipt::sha256;
 use crate::receipt::BomInfo;
 use crate::receipt::Context;
 use crate::receipt::CrcReader;
 use crate::receipt::EntryType;
 use crate::receipt::FatBinary;
+use crate::receipt::FileDigest;
 use crate::receipt::FileType;
 use crate::receipt::MachObject;
 use crate::BigEndianRead;
@@ -25,10 +28,11 @@ use crate::BigEndianWrite;
 use crate::BlockRead;
 use crate::BlockWrite;
 use crate::Blocks;
+use crate::Warnings;
 
 /// File metadata.
-#[derive(Debug, Clone)]
-#[cfg_attr(test, derive(arbitrary::Arbitrary, PartialEq, Eq))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(test, derive(arbitrary::Arbitrary))]
 pub enum Metadata {
     /// Regular file.
     File(File),
@@ -54,6 +58,53 @@ pub enum Metadata {
 }
 
 impl Metadata {
+    /// Create regular file metadata.
+    ///
+    /// Returns an error if `mode` does not have regular file type bits set.
+    pub fn file(
+        mode: u16,
+        uid: u32,
+        gid: u32,
+        mtime: u32,
+        size: u64,
+        checksum: u32,
+    ) -> Result<Self, Error> {
+        File::new(mode, uid, gid, mtime, size, checksum).map(Into::into)
+    }
+
+    /// Create directory metadata.
+    ///
+    /// Returns an error if `mode` does not have directory file type bits set.
+    pub fn directory(mode: u16, uid: u32, gid: u32, mtime: u32) -> Result<Self, Error> {
+        Directory::new(mode, uid, gid, mtime).map(Into::into)
+    }
+
+    /// Create symbolic link metadata.
+    ///
+    /// File size and checksum are computed from the `target`.
+    /// Returns an error if `mode` does not have symbolic link file type bits set.
+    pub fn symlink<P: Into<PathBuf>>(
+        target: P,
+        mode: u16,
+        uid: u32,
+        gid: u32,
+        mtime: u32,
+    ) -> Result<Self, Error> {
+        Link::new(target, mode, uid, gid, mtime).map(Into::into)
+    }
+
+    /// Create character or block device metadata.
+    ///
+    /// Returns an error if `mode` does not have block or character device file type bits set.
+    pub fn device(mode: u16, uid: u32, gid: u32, mtime: u32, rdev: i32) -> Result<Self, Error> {
+        Device::new(mode, uid, gid, mtime, rdev).map(Into::into)
+    }
+
+    /// Create path-only metadata.
+    pub fn entry(entry_type: EntryType) -> Self {
+        Entry::new(entry_type).into()
+    }
+
     /// Get the file type from file mode.
     ///
     /// Unsupported file types are coerced to [`FileType::Regular].
@@ -69,7 +120,7 @@ impl Metadata {
             Link { .. } => EntryType::Link,
             Directory(..) => EntryType::Directory,
             Device(..) => EntryType::Device,
-            Entry(self::Entry { entry_type }) => *entry_type,
+            Entry(self::Entry { entry_type, .. }) => *entry_type,
         }
     }
 
@@ -98,7 +149,46 @@ impl Metadata {
         get_common_field!(self, size, 0)
     }
 
-    fn set_size(&mut self, value: u64) {
+    /// Set file mode.
+    ///
+    /// Returns an error if the file type bits in `mode` do not match the variant.
+    /// Does nothing for `Entry` variant.
+    pub fn set_mode(&mut self, mode: u16) -> Result<(), Error> {
+        match self {
+            Metadata::File(x) => x.set_mode(mode),
+            Metadata::Executable(x) => x.set_mode(mode),
+            Metadata::Directory(x) => x.set_mode(mode),
+            Metadata::Link(x) => x.set_mode(mode),
+            Metadata::Device(x) => x.set_mode(mode),
+            Metadata::Entry(..) => Ok(()),
+        }
+    }
+
+    /// Set file owner's user id.
+    ///
+    /// Does nothing for `Entry` variant.
+    pub fn set_uid(&mut self, value: u32) {
+        set_common_field!(self, uid, value);
+    }
+
+    /// Set file owner's group id.
+    ///
+    /// Does nothing for `Entry` variant.
+    pub fn set_gid(&mut self, value: u32) {
+        set_common_field!(self, gid, value);
+    }
+
+    /// Set file's last modification time.
+    ///
+    /// Does nothing for `Entry` variant.
+    pub fn set_mtime(&mut self, value: u32) {
+        set_common_field!(self, mtime, value);
+    }
+
+    /// Set file size.
+    ///
+    /// Does nothing for `Entry` variant.
+    pub fn set_size(&mut self, value: u64) {
         set_common_field!(self, size, value);
     }
 
@@ -124,13 +214,84 @@ impl Metadata {
         }
     }
 
+    /// Get SHA-256 digest.
+    ///
+    /// Returns `None` for all variants except `File` and `Executable`
+    /// and for the receipts created without [`sha256`](crate::receipt::ReceiptBuilder::sha256).
+    pub fn digest(&self) -> Option<&FileDigest> {
+        match self {
+            Metadata::File(x) => x.digest(),
+            Metadata::Executable(x) => x.digest(),
+            _ => None,
+        }
+    }
+
+    /// Set SHA-256 digest.
+    ///
+    /// Does nothing for all variants except `File` and `Executable`.
+    pub fn set_digest(&mut self, value: Option<FileDigest>) {
+        match self {
+            Metadata::File(x) => x.set_digest(value),
+            Metadata::Executable(x) => x.set_digest(value),
+            _ => {}
+        }
+    }
+
+    /// Compute SHA-256 digest of the file under `path` for `File` and `Executable` variants.
+    ///
+    /// Universal binaries also get the digest of each architecture.
+    pub(crate) fn compute_digest(&mut self, path: &Path) -> Result<(), Error> {
+        let is_fat = match self {
+            Metadata::File(..) => false,
+            Metadata::Executable(x) => x.kind() == ExecutableType::Fat,
+            _ => return Ok(()),
+        };
+        let mut file = std::fs::File::open(path)?;
+        let sha256 = sha256(&mut file)?;
+        let arches = if is_fat {
+            file.rewind()?;
+            let fat = FatBinary::read_be(&mut file)?;
+            fat.to_arch_digests(file)?
+        } else {
+            Vec::new()
+        };
+        self.set_digest(Some(FileDigest::new(sha256, arches)));
+        Ok(())
+    }
+
+    /// Create metadata from the common fields and type-specific fields.
+    ///
+    /// The variant is chosen based on the file type bits in `common.mode`.
+    pub(crate) fn from_common(
+        common: Common,
+        checksum: u32,
+        target: PathBuf,
+        dev: i32,
+    ) -> Result<Self, Error> {
+        let metadata = match FileType::new(common.mode)? {
+            FileType::Regular => Metadata::File(File {
+                common,
+                checksum,
+                digest: None,
+            }),
+            FileType::Directory => Metadata::Directory(Directory { common }),
+            FileType::Symlink => Metadata::Link(Link {
+                common,
+                checksum,
+                target,
+            }),
+            FileType::CharDevice | FileType::BlockDevice => {
+                Metadata::Device(Device { common, dev })
+            }
+        };
+        Ok(metadata)
+    }
+
     /// Create metadata from the provided file path.
     pub fn new(path: &Path, path_only: bool) -> Result<Self, Error> {
         let metadata = std::fs::symlink_metadata(path)?;
         if path_only {
-            return Ok(Self::Entry(Entry {
-                entry_type: metadata.file_type().try_into()?,
-            }));
+            return Ok(Self::Entry(Entry::new(metadata.file_type().try_into()?)));
         }
         let mut metadata: Metadata = metadata.try_into()?;
         match metadata {
@@ -186,6 +347,7 @@ impl Metadata {
                     checksum: file.checksum,
                     arches,
                     kind,
+                    digest: file.digest,
                 });
             }
         }
@@ -224,84 +386,105 @@ impl Metadata {
         }
     }
 
-    fn read<R: Read>(mut reader: R) -> Result<Self, Error> {
+    pub(crate) fn read<R: Read>(
+        mut reader: R,
+        block: Option<u32>,
+        warnings: &mut Warnings,
+    ) -> Result<Self, Error> {
         let entry_type = EntryType::read_be(reader.by_ref())?;
-        let _x0 = u8::read_be(reader.by_ref())?;
-        debug_assert!(_x0 == 1, "x0 {:?}", _x0);
+        let x0 = u8::read_be(reader.by_ref())?;
+        if x0 != 1 {
+            warnings.report(block, format!("unexpected value {} in metadata", x0))?;
+        }
         let flags = u16::read_be(reader.by_ref())?;
+        let unknown = UnknownFields {
+            x0,
+            flags: flags & UNKNOWN_FLAGS,
+            ..Default::default()
+        };
         if is_path_only(flags) {
             // This BOM stores paths only.
-            let metadata = Self::Entry(Entry { entry_type });
+            let metadata = Self::Entry(Entry {
+                entry_type,
+                unknown,
+            });
             return Ok(metadata);
         }
-        let executable_type = get_executable_type(flags);
-        let common = Common::read_be(reader.by_ref())?;
+        let mut executable_type = get_executable_type(flags);
+        let mut common = Common::read(reader.by_ref(), unknown, block, warnings)?;
+        // In lenient mode the file type takes precedence over the entry type.
         let file_type = FileType::new(common.mode)?;
-        debug_assert!(
-            file_type.to_entry_type() == entry_type,
-            "entry_type = {:?}, file_type = {:?}",
-            entry_type,
-            file_type
-        );
-        let metadata = match (file_type, executable_type) {
+        if file_type.to_entry_type() != entry_type {
+            warnings.report(
+                block,
+                format!(
+                    "file mode {:o} does not match entry type {:?}",
+                    common.mode, entry_type
+                ),
+            )?;
+        }
+        if executable_type.is_some() && file_type != FileType::Regular {
+            warnings.report(block, format!("{:?} can not be an executable", file_type))?;
+            executable_type = None;
+        }
+        let mut metadata = match (file_type, executable_type) {
             (FileType::Regular, Some(executable_type)) => {
                 let checksum = u32::read_be(reader.by_ref())?;
                 let flag = u8::read_be(reader.by_ref())?;
-                debug_assert!(flag == 1, "flag = {flag}");
+                if flag != 1 {
+                    warnings.report(block, format!("unexpected executable flag {}", flag))?;
+                }
+                common.unknown.executable_flag = flag;
                 let num_arch_again = u32::read_be(reader.by_ref())?;
-                let mut arches = Vec::with_capacity(num_arch_again as usize);
+                let mut arches = Vec::with_capacity(capacity_hint(num_arch_again as usize));
                 for _ in 0..num_arch_again {
                     arches.push(ExecutableArch::read_be(reader.by_ref())?);
                 }
-                debug_assert!(
-                    (executable_type == ExecutableType::Fat && !arches.is_empty())
-                        || (executable_type == ExecutableType::Mach && arches.len() == 1),
-                    "unexpected executable type and arches combination: type = {:?}, arches = {:?}",
-                    executable_type,
-                    arches
-                );
-                Metadata::Executable(Executable {
+                if check_arches(&arches, executable_type).is_err() {
+                    warnings.report(
+                        block,
+                        format!(
+                            "{:?} executable can not have {} architectures",
+                            executable_type,
+                            arches.len()
+                        ),
+                    )?;
+                }
+                let kind = match arches.len() {
+                    0 => None,
+                    1 => Some(executable_type),
+                    _ => Some(ExecutableType::Fat),
+                };
+                match kind {
+                    Some(kind) => Metadata::Executable(Executable {
+                        common,
+                        checksum,
+                        arches,
+                        kind,
+                        digest: None,
+                    }),
+                    None => Metadata::File(File {
+                        common,
+                        checksum,
+                        digest: None,
+                    }),
+                }
+            }
+            (FileType::Regular, None) => {
+                let checksum = u32::read_be(reader.by_ref())?;
+                Metadata::File(File {
                     common,
                     checksum,
-                    arches,
-                    kind: executable_type,
+                    digest: None,
                 })
             }
-            (FileType::Regular, executable_type) => {
-                debug_assert!(
-                    executable_type.is_none(),
-                    "unexpected executable type {:?}",
-                    executable_type
-                );
-                let checksum = u32::read_be(reader.by_ref())?;
-                Metadata::File(File { common, checksum })
-            }
-            (FileType::Directory, executable_type) => {
-                debug_assert!(
-                    executable_type.is_none(),
-                    "unexpected executable type {:?}",
-                    executable_type
-                );
-                Metadata::Directory(Directory { common })
-            }
-            (FileType::Symlink, executable_type) => {
-                debug_assert!(
-                    executable_type.is_none(),
-                    "unexpected executable type {:?}",
-                    executable_type
-                );
+            (FileType::Directory, _) => Metadata::Directory(Directory { common }),
+            (FileType::Symlink, _) => {
                 let checksum = u32::read_be(reader.by_ref())?;
                 let target_len = u32::read_be(reader.by_ref())?;
-                debug_assert!(
-                    target_len == 0 || file_type == FileType::Symlink,
-                    "file_type = {:?}, target_len = {}",
-                    file_type,
-                    target_len
-                );
-                let mut target = vec![0_u8; target_len as usize];
-                reader.read_exact(&mut target[..])?;
+                let target = read_vec(reader.by_ref(), target_len as usize)?;
                 let target = CString::from_vec_with_nul(target)
-                    .map_err(|_| Error::other("invalid c-string"))?;
+                    .map_err(|_| crate::Error::invalid_data("invalid c-string"))?;
                 let target = OsStr::from_bytes(target.to_bytes());
                 Metadata::Link(Link {
                     common,
@@ -309,12 +492,7 @@ impl Metadata {
                     target: target.into(),
                 })
             }
-            (FileType::CharDevice | FileType::BlockDevice, executable_type) => {
-                debug_assert!(
-                    executable_type.is_none(),
-                    "unexpected executable type {:?}",
-                    executable_type
-                );
+            (FileType::CharDevice | FileType::BlockDevice, _) => {
                 let dev = u32::read_be(reader.by_ref())?;
                 Metadata::Device(Device {
                     common,
@@ -322,17 +500,29 @@ impl Metadata {
                 })
             }
         };
-        // We ignore 8 zero bytes here. Bomutils' `mkbom` doesn't write them but the original `mkbom` does.
+        // Bomutils' `mkbom` doesn't write 8 zero bytes at the end but the original `mkbom` does.
+        let mut trailer = Vec::new();
+        reader.read_to_end(&mut trailer)?;
+        if !(trailer.is_empty() || trailer == [0_u8; 8]) {
+            warnings.report(
+                block,
+                format!("unexpected {} trailing bytes in metadata", trailer.len()),
+            )?;
+        }
+        metadata.unknown_fields_mut().trailer = trailer;
         Ok(metadata)
     }
 
     fn write<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let unknown = self.unknown_fields();
         self.entry_type().write_be(writer.by_ref())?;
-        1_u8.write_be(writer.by_ref())?;
-        let flags = self.flags();
+        unknown.x0.write_be(writer.by_ref())?;
+        let flags = self.flags() | unknown.flags;
         flags.write_be(writer.by_ref())?;
         match self {
-            Metadata::File(File { common, checksum }) => {
+            Metadata::File(File {
+                common, checksum, ..
+            }) => {
                 common.write_be(writer.by_ref())?;
                 checksum.write_be(writer.by_ref())?;
             }
@@ -340,11 +530,11 @@ impl Metadata {
                 common,
                 checksum,
                 arches,
-                kind: _kind,
+                ..
             }) => {
                 common.write_be(writer.by_ref())?;
                 checksum.write_be(writer.by_ref())?;
-                1_u8.write_be(writer.by_ref())?;
+                unknown.executable_flag.write_be(writer.by_ref())?;
                 let num_arches = arches.len() as u32;
                 num_arches.write_be(writer.by_ref())?;
                 for arch in arches.iter() {
@@ -374,11 +564,33 @@ impl Metadata {
             Metadata::Entry(..) => {}
         }
         if !matches!(self, Metadata::Entry(..)) {
-            // Block always ends with 8 zeroes.
-            writer.write_all(&[0_u8; 8])?;
+            // Block usually ends with 8 zeroes.
+            writer.write_all(&unknown.trailer)?;
         }
         Ok(())
     }
+
+    fn unknown_fields(&self) -> &UnknownFields {
+        match self {
+            Metadata::File(x) => &x.common.unknown,
+            Metadata::Executable(x) => &x.common.unknown,
+            Metadata::Directory(x) => &x.common.unknown,
+            Metadata::Link(x) => &x.common.unknown,
+            Metadata::Device(x) => &x.common.unknown,
+            Metadata::Entry(x) => &x.unknown,
+        }
+    }
+
+    fn unknown_fields_mut(&mut self) -> &mut UnknownFields {
+        match self {
+            Metadata::File(x) => &mut x.common.unknown,
+            Metadata::Executable(x) => &mut x.common.unknown,
+            Metadata::Directory(x) => &mut x.common.unknown,
+            Metadata::Link(x) => &mut x.common.unknown,
+            Metadata::Device(x) => &mut x.common.unknown,
+            Metadata::Entry(x) => &mut x.unknown,
+        }
+    }
 }
 
 impl BlockRead<Context> for Metadata {
@@ -389,14 +601,13 @@ impl BlockRead<Context> for Metadata {
         context: &mut Context,
     ) -> Result<Self, Error> {
         let reader = blocks.slice(i, file)?;
-        let block_len = reader.len();
-        let mut cursor = Cursor::new(reader);
-        let mut metadata = Self::read(cursor.by_ref())?;
+        let mut metadata = Self::read(reader, Some(i), &mut context.warnings)?;
         if let Some(size) = context.file_sizes.get(&i) {
             metadata.set_size(*size);
         }
-        let unread_bytes = block_len - reader.len();
-        debug_assert!(unread_bytes == 0, "unread_bytes = {unread_bytes}");
+        if let Some(digest) = context.file_digests.get(&i) {
+            metadata.set_digest(Some(digest.clone()));
+        }
         Ok(metadata)
     }
 }
@@ -413,6 +624,9 @@ impl BlockWrite<Context> for Metadata {
         if file_size > u32::MAX as u64 {
             context.file_sizes.insert(i, file_size);
         }
+        if let Some(digest) = self.digest() {
+            context.file_digests.insert(i, digest.clone());
+        }
         Ok(i)
     }
 }
@@ -428,11 +642,13 @@ impl TryFrom<std::fs::Metadata> for Metadata {
             gid: other.gid(),
             mtime: other.mtime().try_into().unwrap_or(0),
             size: other.size(),
+            unknown: Default::default(),
         };
         let metadata = match kind {
             FileType::Regular => Metadata::File(File {
                 common,
                 checksum: 0,
+                digest: None,
             }),
             FileType::Directory => Metadata::Directory(Directory { common }),
             FileType::Symlink => Metadata::Link(Link {
@@ -450,38 +666,118 @@ impl TryFrom<std::fs::Metadata> for Metadata {
 }
 
 /// File entry.
-#[derive(Debug, Clone)]
-#[cfg_attr(test, derive(PartialEq, Eq))]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct File {
     common: Common,
     checksum: u32,
+    digest: Option<FileDigest>,
 }
 
 impl File {
+    /// Create new regular file entry.
+    ///
+    /// Returns an error if `mode` does not have regular file type bits set.
+    pub fn new(
+        mode: u16,
+        uid: u32,
+        gid: u32,
+        mtime: u32,
+        size: u64,
+        checksum: u32,
+    ) -> Result<Self, Error> {
+        check_mode(mode, Self::FILE_TYPES)?;
+        Ok(Self {
+            common: Common::new(mode, uid, gid, mtime, size),
+            checksum,
+            digest: None,
+        })
+    }
+
     /// Get checksum.
     pub fn checksum(&self) -> u32 {
         self.checksum
     }
+
+    /// Set checksum.
+    pub fn set_checksum(&mut self, value: u32) {
+        self.checksum = value;
+    }
+
+    /// Get SHA-256 digest.
+    pub fn digest(&self) -> Option<&FileDigest> {
+        self.digest.as_ref()
+    }
+
+    /// Set SHA-256 digest.
+    pub fn set_digest(&mut self, value: Option<FileDigest>) {
+        self.digest = value;
+    }
 }
 
-impl_common!(File);
+impl_common!(File, &[FileType::Regular]);
 
 /// Executable entry.
-#[derive(Debug, Clone)]
-#[cfg_attr(test, derive(PartialEq, Eq))]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Executable {
     common: Common,
     checksum: u32,
     arches: Vec<ExecutableArch>,
     kind: ExecutableType,
+    digest: Option<FileDigest>,
 }
 
 impl Executable {
+    /// Create new executable file entry.
+    ///
+    /// Mach-O objects should have exactly one architecture and
+    /// universal binaries should have at least one architecture.
+    /// The entry has `0o100755` mode, zero uid, gid, modification time, size and checksum.
+    /// Use setters to change them.
+    pub fn new(arches: Vec<ExecutableArch>, kind: ExecutableType) -> Result<Self, Error> {
+        check_arches(&arches, kind)?;
+        Ok(Self {
+            common: Common::new(Self::DEFAULT_MODE, 0, 0, 0, 0),
+            checksum: 0,
+            arches,
+            kind,
+            digest: None,
+        })
+    }
+
     /// Get checksum.
     pub fn checksum(&self) -> u32 {
         self.checksum
     }
 
+    /// Set checksum.
+    pub fn set_checksum(&mut self, value: u32) {
+        self.checksum = value;
+    }
+
+    /// Get SHA-256 digests of the whole file and of each architecture.
+    pub fn digest(&self) -> Option<&FileDigest> {
+        self.digest.as_ref()
+    }
+
+    /// Set SHA-256 digests of the whole file and of each architecture.
+    pub fn set_digest(&mut self, value: Option<FileDigest>) {
+        self.digest = value;
+    }
+
+    /// Replace architectures and executable type.
+    ///
+    /// Fails under the same conditions as [`new`](Self::new).
+    pub fn set_arches(
+        &mut self,
+        arches: Vec<ExecutableArch>,
+        kind: ExecutableType,
+    ) -> Result<(), Error> {
+        check_arches(&arches, kind)?;
+        self.arches = arches;
+        self.kind = kind;
+        Ok(())
+    }
+
     /// Get executable type.
     pub fn kind(&self) -> ExecutableType {
         self.kind
@@ -501,20 +797,34 @@ impl Executable {
     }
 }
 
-impl_common!(Executable);
+impl Executable {
+    const DEFAULT_MODE: u16 = 0o100755;
+}
+
+impl_common!(Executable, &[FileType::Regular]);
 
 /// Directory entry.
-#[derive(Debug, Clone)]
-#[cfg_attr(test, derive(PartialEq, Eq))]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Directory {
     common: Common,
 }
 
-impl_common!(Directory);
+impl Directory {
+    /// Create new directory entry.
+    ///
+    /// Returns an error if `mode` does not have directory file type bits set.
+    pub fn new(mode: u16, uid: u32, gid: u32, mtime: u32) -> Result<Self, Error> {
+        check_mode(mode, Self::FILE_TYPES)?;
+        Ok(Self {
+            common: Common::new(mode, uid, gid, mtime, 0),
+        })
+    }
+}
+
+impl_common!(Directory, &[FileType::Directory]);
 
 /// Symbolic link entry.
-#[derive(Debug, Clone)]
-#[cfg_attr(test, derive(PartialEq, Eq))]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Link {
     common: Common,
     checksum: u32,
@@ -522,6 +832,37 @@ pub struct Link {
 }
 
 impl Link {
+    /// Create new symbolic link entry.
+    ///
+    /// File size and checksum are computed from the `target`.
+    /// Returns an error if `mode` does not have symbolic link file type bits set.
+    pub fn new<P: Into<PathBuf>>(
+        target: P,
+        mode: u16,
+        uid: u32,
+        gid: u32,
+        mtime: u32,
+    ) -> Result<Self, Error> {
+        check_mode(mode, Self::FILE_TYPES)?;
+        let mut link = Self {
+            common: Common::new(mode, uid, gid, mtime, 0),
+            checksum: 0,
+            target: Default::default(),
+        };
+        link.set_target(target)?;
+        Ok(link)
+    }
+
+    /// Set target path and update file size and checksum accordingly.
+    pub fn set_target<P: Into<PathBuf>>(&mut self, target: P) -> Result<(), Error> {
+        let target = target.into();
+        let bytes = target.as_os_str().as_bytes();
+        self.checksum = CrcReader::new(bytes).digest()?;
+        self.common.size = bytes.len() as u64;
+        self.target = target;
+        Ok(())
+    }
+
     /// Get checksum of the target file path.
     pub fn checksum(&self) -> u32 {
         self.checksum
@@ -538,44 +879,75 @@ impl Link {
     }
 }
 
-impl_common!(Link);
+impl_common!(Link, &[FileType::Symlink]);
 
 /// Device entry.
-#[derive(Debug, Clone)]
-#[cfg_attr(test, derive(PartialEq, Eq))]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Device {
     common: Common,
     dev: i32,
 }
 
 impl Device {
+    /// Create new device entry.
+    ///
+    /// Returns an error if `mode` does not have block or character device file type bits set.
+    pub fn new(mode: u16, uid: u32, gid: u32, mtime: u32, rdev: i32) -> Result<Self, Error> {
+        check_mode(mode, Self::FILE_TYPES)?;
+        Ok(Self {
+            common: Common::new(mode, uid, gid, mtime, 0),
+            dev: rdev,
+        })
+    }
+
     /// Get platform-specific device number.
     pub fn rdev(&self) -> i32 {
         self.dev
     }
+
+    /// Set platform-specific device number.
+    pub fn set_rdev(&mut self, value: i32) {
+        self.dev = value;
+    }
 }
 
-impl_common!(Device);
+impl_common!(Device, &[FileType::CharDevice, FileType::BlockDevice]);
 
 /// Any entry.
 ///
 /// Stores only the entry type.
-#[derive(Debug, Clone)]
-#[cfg_attr(test, derive(arbitrary::Arbitrary, PartialEq, Eq))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(test, derive(arbitrary::Arbitrary))]
 pub struct Entry {
     entry_type: EntryType,
+    #[cfg_attr(test, arbitrary(default))]
+    unknown: UnknownFields,
 }
 
 impl Entry {
+    /// Create new path-only entry.
+    pub fn new(entry_type: EntryType) -> Self {
+        Self {
+            entry_type,
+            unknown: Default::default(),
+        }
+    }
+
     /// Get entry type.
     pub fn kind(&self) -> EntryType {
         self.entry_type
     }
 }
 
+impl From<Entry> for Metadata {
+    fn from(other: Entry) -> Self {
+        Metadata::Entry(other)
+    }
+}
+
 /// Binary architecture information.
-#[derive(Debug, Clone)]
-#[cfg_attr(test, derive(arbitrary::Arbitrary, PartialEq, Eq))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(test, derive(arbitrary::Arbitrary))]
 pub struct ExecutableArch {
     pub(crate) cpu_type: u32,
     pub(crate) cpu_sub_type: u32,
@@ -585,6 +957,26 @@ pub struct ExecutableArch {
 }
 
 impl ExecutableArch {
+    /// Create new architecture.
+    pub fn new(cpu_type: u32, cpu_sub_type: u32, size: u32, checksum: u32) -> Self {
+        Self {
+            cpu_type,
+            cpu_sub_type,
+            size,
+            checksum,
+        }
+    }
+
+    /// Set file size.
+    pub fn set_size(&mut self, value: u32) {
+        self.size = value;
+    }
+
+    /// Set checksum.
+    pub fn set_checksum(&mut self, value: u32) {
+        self.checksum = value;
+    }
+
     /// Get CPU type as defined in [`mach/machine.h`](https://github.com/opensource-apple/cctools/blob/master/include/mach/machine.h).
     pub fn cpu_type(&self) -> u32 {
         self.cpu_type
@@ -652,31 +1044,55 @@ fn get_executable_type(flags: u16) -> Option<ExecutableType> {
     }
 }
 
-#[derive(Debug, Clone)]
-#[cfg_attr(test, derive(arbitrary::Arbitrary, PartialEq, Eq))]
-struct Common {
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(test, derive(arbitrary::Arbitrary))]
+pub(crate) struct Common {
     mode: u16,
     uid: u32,
     gid: u32,
     mtime: u32,
     size: u64,
+    #[cfg_attr(test, arbitrary(default))]
+    unknown: UnknownFields,
 }
 
-impl BigEndianRead for Common {
-    fn read_be<R: Read>(mut reader: R) -> Result<Self, Error> {
+impl Common {
+    pub(crate) fn new(mode: u16, uid: u32, gid: u32, mtime: u32, size: u64) -> Self {
+        Self {
+            mode,
+            uid,
+            gid,
+            mtime,
+            size,
+            unknown: Default::default(),
+        }
+    }
+}
+
+impl Common {
+    fn read<R: Read>(
+        mut reader: R,
+        mut unknown: UnknownFields,
+        block: Option<u32>,
+        warnings: &mut Warnings,
+    ) -> Result<Self, Error> {
         let mode = u16::read_be(reader.by_ref())?;
         let uid = u32::read_be(reader.by_ref())?;
         let gid = u32::read_be(reader.by_ref())?;
         let mtime = u32::read_be(reader.by_ref())?;
         let size = u32::read_be(reader.by_ref())?;
-        let _x1 = u8::read_be(reader.by_ref())?;
-        debug_assert!(_x1 == 1, "x1 {:?}", _x1);
+        let x1 = u8::read_be(reader.by_ref())?;
+        if x1 != 1 {
+            warnings.report(block, format!("unexpected value {} in metadata", x1))?;
+        }
+        unknown.x1 = x1;
         Ok(Self {
             mode,
             uid,
             gid,
             mtime,
             size: size as u64,
+            unknown,
         })
     }
 }
@@ -688,11 +1104,67 @@ impl BigEndianWrite for Common {
         self.gid.write_be(writer.by_ref())?;
         self.mtime.write_be(writer.by_ref())?;
         (self.size as u32).write_be(writer.by_ref())?; // truncate the size
-        1_u8.write_be(writer.by_ref())?;
+        self.unknown.x1.write_be(writer.by_ref())?;
         Ok(())
     }
 }
 
+/// Metadata fields with unknown purpose.
+///
+/// They are kept as is to write the block back unchanged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct UnknownFields {
+    /// Always 1.
+    x0: u8,
+    /// Flags other than executable type and path-only ones.
+    flags: u16,
+    /// Always 1.
+    x1: u8,
+    /// Always 1 for executables.
+    executable_flag: u8,
+    /// 8 zeroes in the files created by `mkbom`, absent in the files created by Bomutils.
+    trailer: Vec<u8>,
+}
+
+impl Default for UnknownFields {
+    fn default() -> Self {
+        Self {
+            x0: 1,
+            flags: 0,
+            x1: 1,
+            executable_flag: 1,
+            trailer: vec![0_u8; 8],
+        }
+    }
+}
+
+fn check_mode(mode: u16, file_types: &[FileType]) -> Result<(), Error> {
+    let file_type = FileType::new(mode)?;
+    if !file_types.contains(&file_type) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("unexpected file type {:?} in mode {:o}", file_type, mode),
+        ));
+    }
+    Ok(())
+}
+
+fn check_arches(arches: &[ExecutableArch], kind: ExecutableType) -> Result<(), Error> {
+    match kind {
+        ExecutableType::Mach if arches.len() != 1 => Err(Error::new(
+            ErrorKind::InvalidInput,
+            "mach object should have exactly one architecture",
+        )),
+        ExecutableType::Fat if arches.is_empty() => Err(Error::new(
+            ErrorKind::InvalidInput,
+            "universal binary should have at least one architecture",
+        )),
+        _ => Ok(()),
+    }
+}
+
+const UNKNOWN_FLAGS: u16 = 0x0ff0;
+
 const fn is_path_only(flags: u16) -> bool {
     (flags & 0xf) == 0
 }
@@ -730,8 +1202,11 @@ macro_rules! set_common_field {
 use set_common_field;
 
 macro_rules! impl_common {
-    ($type:ty) => {
+    ($type:ident, $file_types:expr) => {
         impl $type {
+            /// Allowed file types.
+            const FILE_TYPES: &'static [FileType] = $file_types;
+
             /// Get file mode.
             pub fn mode(&self) -> u16 {
                 self.common.mode
@@ -756,6 +1231,41 @@ macro_rules! impl_common {
             pub fn size(&self) -> u64 {
                 self.common.size
             }
+
+            /// Set file mode.
+            ///
+            /// Returns an error if the file type bits in `mode` do not match the entry type.
+            pub fn set_mode(&mut self, mode: u16) -> Result<(), Error> {
+                check_mode(mode, Self::FILE_TYPES)?;
+                self.common.mode = mode;
+                Ok(())
+            }
+
+            /// Set file owner's user id.
+            pub fn set_uid(&mut self, value: u32) {
+                self.common.uid = value;
+            }
+
+            /// Set file owner's group id.
+            pub fn set_gid(&mut self, value: u32) {
+                self.common.gid = value;
+            }
+
+            /// Set file's last modification time.
+            pub fn set_mtime(&mut self, value: u32) {
+                self.common.mtime = value;
+            }
+
+            /// Set file size.
+            pub fn set_size(&mut self, value: u64) {
+                self.common.size = value;
+            }
+        }
+
+        impl From<$type> for Metadata {
+            fn from(other: $type) -> Self {
+                Metadata::$type(other)
+            }
         }
     };
 }
@@ -777,6 +1287,61 @@ mod tests {
         test_be_io_symmetry::<ExecutableArch>();
     }
 
+    #[test]
+    fn constructors() {
+        assert!(Metadata::file(0o100644, 1, 2, 3, 4, 5).is_ok());
+        assert!(Metadata::file(0o40755, 1, 2, 3, 4, 5).is_err());
+        assert!(Metadata::directory(0o40755, 1, 2, 3).is_ok());
+        assert!(Metadata::directory(0o100644, 1, 2, 3).is_err());
+        assert!(Metadata::device(0o20644, 1, 2, 3, 4).is_ok());
+        assert!(Metadata::device(0o60644, 1, 2, 3, 4).is_ok());
+        assert!(Metadata::device(0o120644, 1, 2, 3, 4).is_err());
+        assert!(Metadata::symlink("target", 0o100644, 1, 2, 3).is_err());
+        let link = Metadata::symlink("target", 0o120755, 1, 2, 3).unwrap();
+        assert_eq!(6, link.size());
+        assert_eq!(
+            CrcReader::new(&b"target"[..]).digest().unwrap(),
+            link.checksum()
+        );
+        let arch = ExecutableArch::new(7, 3, 100, 200);
+        assert!(Executable::new(vec![], ExecutableType::Mach).is_err());
+        assert!(Executable::new(vec![], ExecutableType::Fat).is_err());
+        assert!(Executable::new(vec![arch.clone(), arch.clone()], ExecutableType::Mach).is_err());
+        let mut exe = Executable::new(vec![arch.clone(), arch], ExecutableType::Fat).unwrap();
+        assert!(exe.set_mode(0o40755).is_err());
+        exe.set_mode(0o100700).unwrap();
+        exe.set_size(123);
+        let metadata: Metadata = exe.into();
+        assert_eq!(0o100700, metadata.mode());
+        assert_eq!(123, metadata.size());
+        assert_eq!(Some(ExecutableType::Fat), metadata.executable_type());
+    }
+
+    #[test]
+    fn setters() {
+        let mut metadata = Metadata::file(0o100644, 1, 2, 3, 4, 5).unwrap();
+        assert!(metadata.set_mode(0o40755).is_err());
+        metadata.set_mode(0o100600).unwrap();
+        metadata.set_uid(10);
+        metadata.set_gid(20);
+        metadata.set_mtime(30);
+        metadata.set_size(40);
+        assert_eq!(
+            (0o100600, 10, 20, 30, 40),
+            (
+                metadata.mode(),
+                metadata.uid(),
+                metadata.gid(),
+                metadata.mtime(),
+                metadata.size()
+            )
+        );
+        let mut entry = Metadata::entry(EntryType::File);
+        entry.set_mode(0o40755).unwrap();
+        entry.set_uid(10);
+        assert_eq!((0, 0), (entry.mode(), entry.uid()));
+    }
+
     impl<'a> Arbitrary<'a> for File {
         fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
             let mut common: Common = u.arbitrary()?;
@@ -784,6 +1349,7 @@ mod tests {
             Ok(File {
                 common,
                 checksum: u.arbitrary()?,
+                digest: u.arbitrary()?,
             })
         }
     }
@@ -806,6 +1372,7 @@ mod tests {
                 checksum: u.arbitrary()?,
                 arches,
                 kind,
+                digest: u.arbitrary()?,
             })
         }
     }
@@ -2,28 +2,50 @@
 
 mod bom;
 mod bom_info;
+mod check;
 mod context;
 mod crc;
+mod diff;
+mod digest;
+mod extension;
 mod fat;
+mod file_list;
 mod file_sizes;
 mod file_type;
+mod filter;
 mod hard_links;
 mod mach;
 mod metadata;
+mod overrides;
 mod path_component;
+mod path_index;
 mod ptr;
+mod reader;
+mod verify;
+mod view;
 mod virtual_paths;
 
 pub use self::bom::*;
 pub use self::bom_info::*;
 pub use self::context::*;
 pub(crate) use self::crc::*;
+pub use self::diff::*;
+pub use self::digest::*;
+pub use self::extension::*;
 pub(crate) use self::fat::*;
+pub use self::file_list::*;
 pub use self::file_sizes::*;
 pub use self::file_type::*;
+pub(crate) use self::filter::*;
 pub use self::hard_links::*;
 pub(crate) use self::mach::*;
 pub use self::metadata::*;
+pub use self::overrides::*;
 pub use self::path_component::*;
+pub(crate) use self::path_index::PathIndex;
+pub use self::path_index::Walk;
 pub use self::ptr::*;
+pub use self::reader::*;
+pub use self::verify::*;
+pub use self::view::*;
 pub use self::virtual_paths::*;
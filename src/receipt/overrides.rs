@@ -0,0 +1,198 @@
+use std::io::Error;
+use std::path::Path;
+
+use crate::receipt::EntryType;
+use crate::receipt::Metadata;
+use crate::receipt::Pattern;
+
+/// Ownership and permissions to set for the paths that match a glob pattern.
+///
+/// Added via [`ReceiptBuilder::rule`](crate::receipt::ReceiptBuilder::rule).
+#[derive(Debug, Clone, Default)]
+pub struct MetadataRule {
+    uid: Option<u32>,
+    gid: Option<u32>,
+    dir_mode: Option<u16>,
+    file_mode: Option<u16>,
+}
+
+impl MetadataRule {
+    /// Create a rule that does not change anything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set owner's user id.
+    pub fn uid(mut self, value: u32) -> Self {
+        self.uid = Some(value);
+        self
+    }
+
+    /// Set owner's group id.
+    pub fn gid(mut self, value: u32) -> Self {
+        self.gid = Some(value);
+        self
+    }
+
+    /// Set permission bits of directories.
+    ///
+    /// File type bits are ignored.
+    pub fn dir_mode(mut self, value: u16) -> Self {
+        self.dir_mode = Some(value);
+        self
+    }
+
+    /// Set permission bits of regular files, including executables.
+    ///
+    /// File type bits are ignored.
+    pub fn file_mode(mut self, value: u16) -> Self {
+        self.file_mode = Some(value);
+        self
+    }
+}
+
+/// Ownership and permissions overrides that are applied when a receipt is created.
+///
+/// The umask is applied first, then the fixed owner, then the rules in the order they were added.
+/// Modification times are clamped independently.
+#[derive(Default)]
+pub(crate) struct Overrides {
+    uid: Option<u32>,
+    gid: Option<u32>,
+    umask: u16,
+    max_mtime: Option<u32>,
+    rules: Vec<(Pattern, MetadataRule)>,
+}
+
+impl Overrides {
+    pub(crate) fn new(
+        uid: Option<u32>,
+        gid: Option<u32>,
+        umask: u16,
+        max_mtime: Option<u32>,
+        rules: &[(String, MetadataRule)],
+    ) -> Result<Self, Error> {
+        let rules = rules
+            .iter()
+            .map(|(pattern, rule)| Ok((Pattern::new(pattern)?, rule.clone())))
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(Self {
+            uid,
+            gid,
+            umask,
+            max_mtime,
+            rules,
+        })
+    }
+
+    /// Modify the metadata of the path.
+    ///
+    /// The path is relative to the directory that is being scanned and should not start with `./`.
+    pub(crate) fn apply(&self, path: &Path, metadata: &mut Metadata) -> Result<(), Error> {
+        let entry_type = metadata.entry_type();
+        // Symbolic links' permissions are not affected by umask.
+        if self.umask != 0 && entry_type != EntryType::Link {
+            metadata.set_mode(metadata.mode() & !(self.umask & PERMISSION_BITS))?;
+        }
+        if let Some(uid) = self.uid {
+            metadata.set_uid(uid);
+        }
+        if let Some(gid) = self.gid {
+            metadata.set_gid(gid);
+        }
+        if let Some(max_mtime) = self.max_mtime {
+            if metadata.mtime() > max_mtime {
+                metadata.set_mtime(max_mtime);
+            }
+        }
+        let is_dir = entry_type == EntryType::Directory;
+        for (pattern, rule) in self.rules.iter() {
+            if !pattern.is_match(path, is_dir) {
+                continue;
+            }
+            if let Some(uid) = rule.uid {
+                metadata.set_uid(uid);
+            }
+            if let Some(gid) = rule.gid {
+                metadata.set_gid(gid);
+            }
+            let mode = match entry_type {
+                EntryType::Directory => rule.dir_mode,
+                EntryType::File => rule.file_mode,
+                _ => None,
+            };
+            if let Some(mode) = mode {
+                metadata
+                    .set_mode((metadata.mode() & !PERMISSION_BITS) | (mode & PERMISSION_BITS))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Permission bits including setuid, setgid and sticky bits.
+const PERMISSION_BITS: u16 = 0o7777;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply() {
+        let overrides = Overrides::new(
+            Some(0),
+            None,
+            0o022,
+            Some(1000),
+            &[
+                (
+                    "./Applications/**".into(),
+                    MetadataRule::new().gid(80).dir_mode(0o755).file_mode(0o644),
+                ),
+                ("*.sh".into(), MetadataRule::new().file_mode(0o4755)),
+            ],
+        )
+        .unwrap();
+        for (path, metadata, expected) in [
+            (
+                "Applications/x",
+                Metadata::file(0o100777, 1000, 1000, 2000, 0, 0).unwrap(),
+                (0o100644, 0, 80, 1000),
+            ),
+            (
+                "Applications/x/y.sh",
+                Metadata::file(0o100777, 1000, 1000, 0, 0, 0).unwrap(),
+                (0o104755, 0, 80, 0),
+            ),
+            (
+                "Applications/x/y",
+                Metadata::directory(0o40700, 1000, 1000, 0).unwrap(),
+                (0o40755, 0, 80, 0),
+            ),
+            (
+                "Applications",
+                Metadata::directory(0o40777, 1000, 1000, 999).unwrap(),
+                (0o40755, 0, 1000, 999),
+            ),
+            (
+                "bin/link",
+                Metadata::symlink("x", 0o120777, 1000, 1000, 1001).unwrap(),
+                (0o120777, 0, 1000, 1000),
+            ),
+        ] {
+            let mut metadata = metadata;
+            overrides.apply(Path::new(path), &mut metadata).unwrap();
+            assert_eq!(
+                expected,
+                (
+                    metadata.mode(),
+                    metadata.uid(),
+                    metadata.gid(),
+                    metadata.mtime()
+                ),
+                "path = {}",
+                path
+            );
+        }
+    }
+}
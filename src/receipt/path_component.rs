@@ -10,6 +10,8 @@ use std::io::Write;
 use std::ops::Deref;
 use std::ops::DerefMut;
 use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::Component;
 use std::path::Path;
 use std::path::PathBuf;
 
@@ -19,12 +21,16 @@ use walkdir::WalkDir;
 use crate::receipt::BomInfo;
 use crate::receipt::Context;
 use crate::receipt::Metadata;
+use crate::receipt::Overrides;
+use crate::receipt::PathFilter;
 use crate::receipt::VecTree;
 use crate::BigEndianRead;
 use crate::BigEndianWrite;
 use crate::BlockRead;
 use crate::BlockWrite;
 use crate::Blocks;
+use crate::Bom;
+use crate::ErrorContext;
 
 #[derive(Debug, Clone)]
 #[cfg_attr(test, derive(arbitrary::Arbitrary, PartialEq, Eq))]
@@ -40,15 +46,30 @@ impl BlockWrite<Context> for PathComponentKey {
         blocks: &mut Blocks,
         context: &mut Context,
     ) -> Result<u32, Error> {
-        let metadata_index = self
-            .metadata
-            .write_block(writer.by_ref(), blocks, context)?;
+        // Hard links share the metadata block of the first path that was written.
+        let shared_metadata_index = context
+            .hard_link_groups
+            .get(&self.seq_no)
+            .and_then(|group| context.metadata_blocks.get(group))
+            .copied();
+        let metadata_index = match shared_metadata_index {
+            Some(i) => i,
+            None => self
+                .metadata
+                .write_block(writer.by_ref(), blocks, context)?,
+        };
+        if let Some(group) = context.hard_link_groups.get(&self.seq_no).copied() {
+            context
+                .metadata_blocks
+                .entry(group)
+                .or_insert(metadata_index);
+        }
+        context.metadata_blocks.insert(self.seq_no, metadata_index);
         let i = blocks.append(writer.by_ref(), |writer| {
             self.seq_no.write_be(writer.by_ref())?;
             metadata_index.write_be(writer.by_ref())?;
             Ok(())
         })?;
-        context.current_metadata_block_index = Some(i);
         Ok(i)
     }
 }
@@ -60,10 +81,11 @@ impl BlockRead<Context> for PathComponentKey {
         blocks: &mut Blocks,
         context: &mut Context,
     ) -> Result<Self, Error> {
-        let mut reader = blocks.slice(i, file)?;
-        let seq_no = u32::read_be(reader.by_ref())?;
-        let i = u32::read_be(reader.by_ref())?;
-        let metadata = Metadata::read_block(i, file, blocks, context)?;
+        let (seq_no, j) = read_component_key(blocks.slice(i, file)?).in_block(i)?;
+        let metadata = Metadata::read_block(j, file, blocks, context)
+            .in_block(j)
+            .in_block(i)?;
+        context.metadata_blocks.insert(seq_no, j);
         Ok(Self { seq_no, metadata })
     }
 }
@@ -80,20 +102,13 @@ impl BlockWrite<Context> for PathComponentValue {
         &self,
         mut writer: W,
         blocks: &mut Blocks,
-        context: &mut Context,
+        _context: &mut Context,
     ) -> Result<u32, Error> {
         let i = blocks.append(writer.by_ref(), |writer| {
             self.parent.write_be(writer.by_ref())?;
             writer.write_all(self.name.to_bytes_with_nul())?;
             Ok(())
         })?;
-        if let Some(j) = context.current_metadata_block_index.take() {
-            context
-                .hard_links
-                .entry(j)
-                .or_default()
-                .push(self.name.clone());
-        }
         Ok(i)
     }
 }
@@ -105,10 +120,7 @@ impl BlockRead<Context> for PathComponentValue {
         blocks: &mut Blocks,
         _context: &mut Context,
     ) -> Result<Self, Error> {
-        let mut reader = blocks.slice(i, file)?;
-        let parent = u32::read_be(reader.by_ref())?;
-        let name =
-            CStr::from_bytes_with_nul(reader).map_err(|_| Error::other("invalid c-string"))?;
+        let (parent, name) = read_component_value(blocks.slice(i, file)?).in_block(i)?;
         Ok(Self {
             parent,
             name: name.into(),
@@ -116,6 +128,44 @@ impl BlockRead<Context> for PathComponentValue {
     }
 }
 
+/// Path component's block indices.
+///
+/// Used to decode path components on demand.
+pub(crate) struct RawPathComponent {
+    pub(crate) seq_no: u32,
+    /// Metadata block index.
+    pub(crate) metadata: u32,
+    /// Parent and name block index.
+    pub(crate) value: u32,
+}
+
+impl RawPathComponent {
+    /// Create from the contents of the key block and the index of the value block.
+    pub(crate) fn new(key: &[u8], value: u32) -> Result<Self, Error> {
+        let (seq_no, metadata) = read_component_key(key)?;
+        Ok(Self {
+            seq_no,
+            metadata,
+            value,
+        })
+    }
+}
+
+/// Read sequential number and metadata block index from the key block.
+fn read_component_key(mut block: &[u8]) -> Result<(u32, u32), Error> {
+    let seq_no = u32::read_be(block.by_ref())?;
+    let metadata = u32::read_be(block.by_ref())?;
+    Ok((seq_no, metadata))
+}
+
+/// Read parent's sequential number and file name from the value block.
+pub(crate) fn read_component_value(mut block: &[u8]) -> Result<(u32, &CStr), Error> {
+    let parent = u32::read_be(block.by_ref())?;
+    let name = CStr::from_bytes_with_nul(block)
+        .map_err(|_| crate::Error::invalid_data("invalid c-string"))?;
+    Ok((parent, name))
+}
+
 /// Path component.
 ///
 /// This can be a file name or a name of any parent directory.
@@ -159,6 +209,11 @@ impl PathComponent {
 #[cfg_attr(test, derive(PartialEq, Eq))]
 pub struct PathComponentVec {
     components: Vec<PathComponent>,
+    /// Sequential number to hard link group mapping.
+    ///
+    /// The group is identified by the sequential number of the first path in the group.
+    /// Paths that are not hard links are not included.
+    hard_links: HashMap<u32, u32>,
 }
 
 impl PathComponentVec {
@@ -166,7 +221,10 @@ impl PathComponentVec {
 
     /// Create a new vector from the provided path components.
     pub fn new(components: Vec<PathComponent>) -> Self {
-        Self { components }
+        Self {
+            components,
+            hard_links: Default::default(),
+        }
     }
 
     fn path(&self, mut seq_no: u32) -> Result<PathBuf, Error> {
@@ -192,6 +250,76 @@ impl PathComponentVec {
         Ok(path)
     }
 
+    /// Keep only the path components at the specified positions and in the specified order.
+    ///
+    /// Sequential numbers are reassigned starting from one, parents are updated accordingly.
+    /// Path components which parent was dropped become top-level path components.
+    pub(crate) fn reorder(&mut self, order: &[usize]) {
+        let mut old: Vec<Option<PathComponent>> = std::mem::take(&mut self.components)
+            .into_iter()
+            .map(Some)
+            .collect();
+        let mut components: Vec<PathComponent> = order
+            .iter()
+            .filter_map(|i| old.get_mut(*i).and_then(Option::take))
+            .collect();
+        // Id starts with 1.
+        let seq_nos: HashMap<u32, u32> = components
+            .iter()
+            .zip(1_u32..)
+            .map(|(component, seq_no)| (component.seq_no, seq_no))
+            .collect();
+        for component in components.iter_mut() {
+            component.seq_no = seq_nos.get(&component.seq_no).copied().unwrap_or(0);
+            component.parent = seq_nos.get(&component.parent).copied().unwrap_or(0);
+        }
+        self.components = components;
+        self.hard_links = std::mem::take(&mut self.hard_links)
+            .into_iter()
+            .filter_map(|(seq_no, group)| Some((*seq_nos.get(&seq_no)?, *seq_nos.get(&group)?)))
+            .collect();
+        self.normalize_hard_links();
+    }
+
+    /// Make the sequential number of the first path in each hard link group the group identifier.
+    ///
+    /// Hard links share the metadata, hence the paths with different metadata
+    /// are split into separate groups. Groups with only one path are removed.
+    pub(crate) fn normalize_hard_links(&mut self) {
+        self.hard_links = self.hard_link_groups();
+    }
+
+    /// Get normalized sequential number to hard link group mapping.
+    ///
+    /// See [`normalize_hard_links`](Self::normalize_hard_links).
+    pub(crate) fn hard_link_groups(&self) -> HashMap<u32, u32> {
+        // old group to (new group, metadata) mapping
+        let mut groups: HashMap<u32, Vec<(u32, &Metadata)>> = HashMap::new();
+        // new group to the number of paths mapping
+        let mut lens: HashMap<u32, usize> = HashMap::new();
+        let mut hard_links = HashMap::new();
+        for component in self.components.iter() {
+            let Some(group) = self.hard_links.get(&component.seq_no) else {
+                continue;
+            };
+            let subgroups = groups.entry(*group).or_default();
+            let new_group = match subgroups
+                .iter()
+                .find(|(_, metadata)| **metadata == component.metadata)
+            {
+                Some((new_group, _)) => *new_group,
+                None => {
+                    subgroups.push((component.seq_no, &component.metadata));
+                    component.seq_no
+                }
+            };
+            *lens.entry(new_group).or_default() += 1;
+            hard_links.insert(component.seq_no, new_group);
+        }
+        hard_links.retain(|_, group| lens.get(group).is_some_and(|len| *len > 1));
+        hard_links
+    }
+
     /// Transform into a vector of _(full-path, metadata)_ pairs.
     pub fn to_paths(&self) -> Result<Vec<(PathBuf, Metadata)>, Error> {
         let mut paths = Vec::new();
@@ -202,27 +330,115 @@ impl PathComponentVec {
         Ok(paths)
     }
 
+    /// Create a vector from _(path, metadata)_ pairs.
+    ///
+    /// Paths are normalized to start with `./`.
+    /// The parent directory of each path except `.` has to be present in `entries`.
+    /// Sequential numbers are assigned in the order of `entries`.
+    pub fn from_paths(entries: Vec<(PathBuf, Metadata)>) -> Result<Self, Error> {
+        let mut seq_nos: HashMap<PathBuf, u32> = HashMap::with_capacity(entries.len());
+        let entries: Vec<_> = entries
+            .into_iter()
+            .map(|(path, metadata)| (to_receipt_path(&path), metadata))
+            .collect();
+        // Id starts with 1.
+        for (seq_no, (path, _)) in (1_u32..).zip(entries.iter()) {
+            if seq_nos.insert(path.clone(), seq_no).is_some() {
+                return Err(Error::other(format!("duplicate path: {}", path.display())));
+            }
+        }
+        let mut components = Vec::with_capacity(entries.len());
+        for (seq_no, (path, metadata)) in (1_u32..).zip(entries) {
+            let parent = match path.parent() {
+                Some(dirname) if path != Path::new(".") => {
+                    *seq_nos.get(dirname).ok_or_else(|| {
+                        Error::other(format!("no parent directory for {}", path.display()))
+                    })?
+                }
+                _ => 0,
+            };
+            let name = match path.file_name() {
+                Some(s) => s.as_bytes(),
+                None => path.as_os_str().as_bytes(),
+            };
+            let name = CString::new(name).map_err(|_| Error::other("invalid c-string"))?;
+            components.push(PathComponent {
+                seq_no,
+                parent,
+                name,
+                metadata,
+            });
+        }
+        Ok(Self::new(components))
+    }
+
     /// Create a vector by recursively scanning the provided directory.
     pub fn from_dir<P: AsRef<Path>>(directory: P, paths_only: bool) -> Result<Self, Error> {
-        let directory = directory.as_ref();
+        Self::scan(
+            directory.as_ref(),
+            paths_only,
+            false,
+            false,
+            &PathFilter::default(),
+            &Overrides::default(),
+        )
+    }
+
+    /// Create a vector by recursively scanning the provided directory.
+    ///
+    /// Skips the paths excluded by the `filter`. Excluded directories are not scanned.
+    /// Directory entries are scanned in the order of their names if `sorted` is true.
+    /// Computes SHA-256 digests of the files if `sha256` is true.
+    /// Modifies the metadata of each path according to the `overrides`.
+    pub(crate) fn scan(
+        directory: &Path,
+        paths_only: bool,
+        sorted: bool,
+        sha256: bool,
+        filter: &PathFilter,
+        overrides: &Overrides,
+    ) -> Result<Self, Error> {
         let mut components: HashMap<PathBuf, PathComponent> = HashMap::new();
+        let mut hard_links: HashMap<u32, u32> = HashMap::new();
+        // (device, inode) to (hard link group, metadata) mapping
+        let mut inodes: HashMap<(u64, u64), (u32, Metadata)> = HashMap::new();
+        let mut walker = WalkDir::new(directory);
+        if sorted {
+            walker = walker.sort_by_file_name();
+        }
+        let walker = walker.into_iter().filter_entry(|entry| {
+            let path = entry.path().strip_prefix(directory).unwrap_or(entry.path());
+            !filter.is_excluded(path, entry.file_type().is_dir())
+        });
         // Id starts with 1.
-        let mut seq_no: u32 = 1;
-        for entry in WalkDir::new(directory).into_iter() {
+        for (seq_no, entry) in (1_u32..).zip(walker) {
             let entry = entry?;
-            let entry_path = entry
-                .path()
-                .strip_prefix(directory)
-                .map_err(Error::other)?
-                .normalize();
-            let relative_path = if entry_path == Path::new("") {
-                Path::new(".").to_path_buf()
-            } else {
-                Path::new(".").join(entry_path)
-            };
+            let entry_path = entry.path().strip_prefix(directory).map_err(Error::other)?;
+            let relative_path = to_receipt_path(entry_path);
             let dirname = relative_path.parent();
             let basename = relative_path.file_name();
-            let metadata = Metadata::new(entry.path(), paths_only)?;
+            let file_metadata = entry.metadata()?;
+            let new_metadata = || -> Result<Metadata, Error> {
+                let mut metadata = Metadata::new(entry.path(), paths_only)?;
+                if sha256 {
+                    metadata.compute_digest(entry.path())?;
+                }
+                overrides.apply(entry_path, &mut metadata)?;
+                Ok(metadata)
+            };
+            let (metadata, hard_link) = if !file_metadata.is_dir() && file_metadata.nlink() > 1 {
+                let inode = (file_metadata.dev(), file_metadata.ino());
+                match inodes.get(&inode) {
+                    Some((group, metadata)) => (metadata.clone(), Some(*group)),
+                    None => {
+                        let metadata = new_metadata()?;
+                        inodes.insert(inode, (seq_no, metadata.clone()));
+                        (metadata, Some(seq_no))
+                    }
+                }
+            } else {
+                (new_metadata()?, None)
+            };
             let parent = match dirname {
                 Some(d) => components.get(d).map(|node| node.seq_no).unwrap_or(0),
                 None => 0,
@@ -232,6 +448,9 @@ impl PathComponentVec {
                 None => relative_path.as_os_str().as_bytes(),
             };
             let name = CString::new(name).map_err(|_| Error::other("invalid c-string"))?;
+            if let Some(group) = hard_link {
+                hard_links.insert(seq_no, group);
+            }
             let node = PathComponent {
                 seq_no,
                 parent,
@@ -239,11 +458,16 @@ impl PathComponentVec {
                 metadata,
             };
             components.insert(relative_path, node);
-            seq_no += 1;
         }
         let mut components: Vec<_> = components.into_values().collect();
-        components.sort_unstable_by(|a, b| a.seq_no.cmp(&b.seq_no));
-        Ok(Self { components })
+        components.sort_unstable_by_key(|a| a.seq_no);
+        let mut components = Self {
+            components,
+            hard_links,
+        };
+        // Some hard links might be outside of the directory.
+        components.normalize_hard_links();
+        Ok(components)
     }
 }
 
@@ -254,6 +478,8 @@ impl BlockWrite<Context> for PathComponentVec {
         blocks: &mut Blocks,
         context: &mut Context,
     ) -> Result<u32, Error> {
+        // Paths might have been modified since the groups were last normalized.
+        context.hard_link_groups = self.hard_link_groups();
         let paths = PathComponentTree::new(
             self.iter()
                 .cloned()
@@ -283,17 +509,31 @@ impl BlockRead<Context> for PathComponentVec {
                 name: v.name,
             })
             .collect();
-        components.sort_unstable_by(|a, b| a.seq_no.cmp(&b.seq_no));
-        #[cfg(debug_assertions)]
+        components.sort_unstable_by_key(|a| a.seq_no);
+        // Paths are looked up by their sequential numbers.
         for (i, comp) in components.iter().enumerate() {
-            debug_assert!(
-                comp.seq_no as usize == i + 1,
-                "expected seq. no. = {}, actual seq. no. = {}",
-                i + 1,
-                comp.seq_no
-            );
+            if comp.seq_no as usize != i + 1 {
+                return Err(crate::Error::invalid_data(format!(
+                    "expected sequential number {}, found {}",
+                    i + 1,
+                    comp.seq_no
+                )));
+            }
         }
-        Ok(PathComponentVec::new(components))
+        // Paths that share the same metadata block are hard links.
+        let hard_links = components
+            .iter()
+            .filter_map(|component| {
+                let block = context.metadata_blocks.get(&component.seq_no)?;
+                Some((component.seq_no, *block))
+            })
+            .collect();
+        let mut components = PathComponentVec {
+            components,
+            hard_links,
+        };
+        components.normalize_hard_links();
+        Ok(components)
     }
 }
 
@@ -311,6 +551,42 @@ impl DerefMut for PathComponentVec {
     }
 }
 
+impl PathComponentVec {
+    /// Find path component with the sequential number `seq_no` in the paths tree stored in block `i`.
+    pub(crate) fn find(
+        bom: &mut Bom,
+        i: u32,
+        file: &[u8],
+        context: &mut Context,
+        seq_no: u32,
+    ) -> Result<Option<PathComponent>, Error> {
+        let entry = bom.find_regular(
+            i,
+            file,
+            context,
+            |key: &PathComponentKey, _: &PathComponentValue| key.seq_no.cmp(&seq_no),
+        )?;
+        Ok(entry.map(|(key, value)| PathComponent {
+            seq_no: key.seq_no,
+            parent: value.parent,
+            metadata: key.metadata,
+            name: value.name,
+        }))
+    }
+}
+
+/// Convert the path to the form that is used in receipts, i.e. `.` or `./path/to/file`.
+///
+/// Only normal components are kept: the root is the path without such components.
+pub(crate) fn to_receipt_path(path: &Path) -> PathBuf {
+    let mut receipt_path = PathBuf::from(".");
+    receipt_path.extend(path.normalize().components().filter_map(|c| match c {
+        Component::Normal(name) => Some(name),
+        _ => None,
+    }));
+    receipt_path
+}
+
 type PathComponentTree = VecTree<PathComponentKey, PathComponentValue>;
 
 #[cfg(test)]
@@ -330,6 +606,26 @@ mod tests {
         block_io_symmetry::<PathComponentVec>();
     }
 
+    #[test]
+    fn receipt_path() {
+        for path in ["", ".", "./", "/", "/.", "././"] {
+            assert_eq!(
+                Path::new("."),
+                to_receipt_path(Path::new(path)),
+                "{:?}",
+                path
+            );
+        }
+        for path in ["a/b", "./a/b", "/a/b", "./a/./b/", "a/c/../b"] {
+            assert_eq!(
+                Path::new("./a/b"),
+                to_receipt_path(Path::new(path)),
+                "{:?}",
+                path
+            );
+        }
+    }
+
     impl<'a> Arbitrary<'a> for PathComponentVec {
         fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
             use random_dir::FileType::*;
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::receipt::PathComponent;
+
+/// Path lookup index.
+///
+/// Built once from the `seq_no`/`parent` links of the path components.
+/// Addresses path components by their position in the vector.
+#[derive(Debug, Default)]
+#[cfg_attr(test, derive(PartialEq, Eq))]
+pub(crate) struct PathIndex {
+    /// Sequential number to position mapping.
+    positions: HashMap<u32, usize>,
+    /// Parent's sequential number to children's positions mapping.
+    children: HashMap<u32, Vec<usize>>,
+    /// Parent's sequential number to child name to child position mapping.
+    names: HashMap<u32, HashMap<Box<[u8]>, usize>>,
+}
+
+impl PathIndex {
+    pub(crate) fn new(components: &[PathComponent]) -> Self {
+        let mut index = Self {
+            positions: HashMap::with_capacity(components.len()),
+            children: Default::default(),
+            names: Default::default(),
+        };
+        for (i, component) in components.iter().enumerate() {
+            index.insert(i, component);
+        }
+        index
+    }
+
+    /// Add path component at position `i` to the index.
+    pub(crate) fn insert(&mut self, i: usize, component: &PathComponent) {
+        self.positions.entry(component.seq_no).or_insert(i);
+        self.children.entry(component.parent).or_default().push(i);
+        self.names
+            .entry(component.parent)
+            .or_default()
+            .entry(component.name.to_bytes().into())
+            .or_insert(i);
+    }
+
+    /// Get the position of the path component with sequential number `seq_no`.
+    pub(crate) fn position(&self, seq_no: u32) -> Option<usize> {
+        self.positions.get(&seq_no).copied()
+    }
+
+    /// Get the positions of the children of the path component with sequential number `seq_no`.
+    ///
+    /// Zero `seq_no` means top-level path components.
+    pub(crate) fn children(&self, seq_no: u32) -> &[usize] {
+        self.children
+            .get(&seq_no)
+            .map(|children| &children[..])
+            .unwrap_or_default()
+    }
+
+    /// Get the position of the child with the specified name.
+    pub(crate) fn child(&self, seq_no: u32, name: &[u8]) -> Option<usize> {
+        self.names
+            .get(&seq_no)
+            .and_then(|names| names.get(name))
+            .copied()
+    }
+
+    /// Get the positions of the path components under `roots` in depth-first order.
+    ///
+    /// Each root is listed before its descendants.
+    pub(crate) fn depth_first(&self, components: &[PathComponent], roots: &[usize]) -> Vec<usize> {
+        let mut positions = Vec::new();
+        let mut visited = vec![false; components.len()];
+        let mut stack: Vec<usize> = roots.iter().rev().copied().collect();
+        while let Some(i) = stack.pop() {
+            let Some(component) = components.get(i) else {
+                continue;
+            };
+            if std::mem::replace(&mut visited[i], true) {
+                // loop
+                continue;
+            }
+            positions.push(i);
+            stack.extend(self.children(component.seq_no).iter().rev());
+        }
+        positions
+    }
+
+    /// Find the position of the path component by its full path.
+    ///
+    /// Accepts paths with or without `./` or `/` prefix.
+    pub(crate) fn find(&self, components: &[PathComponent], path: &Path) -> Option<usize> {
+        // Paths are relative to the top-level `.` directory if it exists.
+        let mut current = self.child(0, b".");
+        for component in path.components() {
+            let seq_no = match current {
+                Some(i) => components.get(i)?.seq_no,
+                None => 0,
+            };
+            current = match component {
+                Component::Prefix(..) | Component::RootDir | Component::CurDir => continue,
+                Component::ParentDir => {
+                    let parent = components.get(current?)?.parent;
+                    Some(self.position(parent)?)
+                }
+                Component::Normal(name) => Some(self.child(seq_no, name.as_bytes())?),
+            };
+        }
+        current
+    }
+}
+
+/// Depth-first traversal of path components.
+///
+/// Created by [`Receipt::walk`](crate::receipt::Receipt::walk).
+pub struct Walk<'a> {
+    components: &'a [PathComponent],
+    index: &'a PathIndex,
+    /// Positions and depths of the path components that are to be visited.
+    stack: Vec<(usize, usize)>,
+    /// Names of the parent directories of the current path component.
+    names: Vec<&'a OsStr>,
+    visited: Vec<bool>,
+}
+
+impl<'a> Walk<'a> {
+    pub(crate) fn new(components: &'a [PathComponent], index: &'a PathIndex) -> Self {
+        let stack = index.children(0).iter().rev().map(|i| (*i, 0)).collect();
+        Self {
+            components,
+            index,
+            stack,
+            names: Vec::new(),
+            visited: vec![false; components.len()],
+        }
+    }
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = (PathBuf, &'a PathComponent);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (i, depth) = self.stack.pop()?;
+            let Some(component) = self.components.get(i) else {
+                continue;
+            };
+            if std::mem::replace(&mut self.visited[i], true) {
+                // loop
+                continue;
+            }
+            self.names.truncate(depth);
+            self.names
+                .push(OsStr::from_bytes(component.name.to_bytes()));
+            self.stack.extend(
+                self.index
+                    .children(component.seq_no)
+                    .iter()
+                    .rev()
+                    .map(|j| (*j, depth + 1)),
+            );
+            let path: PathBuf = self.names.iter().collect();
+            return Some((path, component));
+        }
+    }
+}
@@ -8,6 +8,7 @@ use crate::BigEndianWrite;
 use crate::BlockRead;
 use crate::BlockWrite;
 use crate::Blocks;
+use crate::ErrorContext;
 
 /// A pointer to a regular block.
 ///
@@ -54,8 +55,10 @@ impl<T: BlockRead<Context>> BlockRead<Context> for Ptr<T> {
         context: &mut Context,
     ) -> Result<Self, Error> {
         let reader = blocks.slice(i, file)?;
-        let i = u32::read_be(reader)?;
-        let value = T::read_block(i, file, blocks, context)?;
+        let j = u32::read_be(reader).in_block(i)?;
+        let value = T::read_block(j, file, blocks, context)
+            .in_block(j)
+            .in_block(i)?;
         Ok(value.into())
     }
 }
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::io::Error;
+use std::io::Read;
+use std::io::Seek;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+
+use crate::receipt::read_component_value;
+use crate::receipt::EntryType;
+use crate::receipt::FileDigest;
+use crate::receipt::Metadata;
+use crate::receipt::PathComponent;
+use crate::receipt::RawPathComponent;
+use crate::receipt::Receipt;
+use crate::BigEndianRead;
+use crate::BomReader;
+use crate::Warnings;
+
+/// Receipt reader that reads path components on demand.
+///
+/// Unlike [`Receipt::read`] the file is not loaded into memory:
+/// only the block index and sequential numbers of the paths are.
+/// Each path component is read with a few seeks,
+/// hence this reader is suitable for files on slow disks or inside archives.
+pub struct ReceiptReader<R> {
+    reader: BomReader<R>,
+    /// Sorted by sequential number.
+    entries: Vec<RawPathComponent>,
+    /// Metadata block index to 64-bit file size mapping.
+    file_sizes: HashMap<u32, u64>,
+    /// Metadata block index to file digest mapping.
+    file_digests: HashMap<u32, FileDigest>,
+}
+
+impl<R: Read + Seek> ReceiptReader<R> {
+    /// Read BOM header and the structure of the paths tree from `reader`.
+    pub fn new(reader: R) -> Result<Self, Error> {
+        let mut reader = BomReader::new(reader)?;
+        let mut file_sizes = HashMap::new();
+        if let Some(i) = reader.get_named(Receipt::SIZE_64) {
+            for (size, block) in reader.read_tree_entries(i)? {
+                let size = u64::read_be(&reader.read_block(size)?[..])?;
+                let block = u32::read_be(&reader.read_block(block)?[..])?;
+                file_sizes.insert(block, size);
+            }
+        }
+        let mut file_digests = HashMap::new();
+        if let Some(i) = reader.get_named(Receipt::SHA_256) {
+            for (digest, block) in reader.read_tree_entries(i)? {
+                let digest = FileDigest::read_be(&reader.read_block(digest)?[..])?;
+                let block = u32::read_be(&reader.read_block(block)?[..])?;
+                file_digests.insert(block, digest);
+            }
+        }
+        let paths = reader
+            .get_named(Receipt::PATHS)
+            .ok_or_else(|| crate::Error::named_block_not_found(Receipt::PATHS))?;
+        let raw_entries = reader.read_tree_entries(paths)?;
+        let mut entries = Vec::with_capacity(raw_entries.len());
+        for (key, value) in raw_entries.into_iter() {
+            entries.push(RawPathComponent::new(&reader.read_block(key)?, value)?);
+        }
+        entries.sort_unstable_by_key(|entry| entry.seq_no);
+        Ok(Self {
+            reader,
+            entries,
+            file_sizes,
+            file_digests,
+        })
+    }
+
+    /// Get the number of path components.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Is there are no path components?
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Read path component by its sequential number.
+    pub fn get(&mut self, seq_no: u32) -> Result<Option<PathComponent>, Error> {
+        match self.position(seq_no) {
+            Some(i) => self.read(i).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Read full path of the path component with sequential number `seq_no`, e.g. `./usr/bin/ls`.
+    ///
+    /// Only the names of the parent directories are read.
+    pub fn path(&mut self, seq_no: u32) -> Result<PathBuf, Error> {
+        self.path_with_cache(seq_no, &HashMap::new())
+    }
+
+    /// Iterate over full paths and path components in the order of their sequential numbers.
+    ///
+    /// Full paths of the directories are cached to not read parent names again.
+    pub fn entries(&mut self) -> Entries<'_, R> {
+        Entries {
+            reader: self,
+            position: 0,
+            directories: HashMap::new(),
+        }
+    }
+
+    /// Transform into the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+
+    fn position(&self, seq_no: u32) -> Option<usize> {
+        self.entries
+            .binary_search_by_key(&seq_no, |entry| entry.seq_no)
+            .ok()
+    }
+
+    fn read(&mut self, i: usize) -> Result<PathComponent, Error> {
+        let Some(entry) = self.entries.get(i) else {
+            return Err(Error::other("invalid path component index"));
+        };
+        let (seq_no, metadata_block, value) = (entry.seq_no, entry.metadata, entry.value);
+        let mut metadata = Metadata::read(
+            &self.reader.read_block(metadata_block)?[..],
+            Some(metadata_block),
+            &mut Warnings::default(),
+        )?;
+        if let Some(size) = self.file_sizes.get(&metadata_block) {
+            metadata.set_size(*size);
+        }
+        if let Some(digest) = self.file_digests.get(&metadata_block) {
+            metadata.set_digest(Some(digest.clone()));
+        }
+        let value = self.reader.read_block(value)?;
+        let (parent, name) = read_component_value(&value)?;
+        Ok(PathComponent {
+            seq_no,
+            parent,
+            metadata,
+            name: name.into(),
+        })
+    }
+
+    fn path_with_cache(
+        &mut self,
+        mut seq_no: u32,
+        directories: &HashMap<u32, PathBuf>,
+    ) -> Result<PathBuf, Error> {
+        let mut names = Vec::new();
+        let mut prefix = PathBuf::new();
+        while seq_no != 0 {
+            if let Some(path) = directories.get(&seq_no) {
+                prefix = path.clone();
+                break;
+            }
+            if names.len() > self.entries.len() {
+                return Err(Error::other("file system loop"));
+            }
+            let Some(i) = self.position(seq_no) else {
+                break;
+            };
+            let value = self.reader.read_block(self.entries[i].value)?;
+            let (parent, name) = read_component_value(&value)?;
+            names.push(name.to_bytes().to_vec());
+            seq_no = parent;
+        }
+        prefix.extend(names.iter().rev().map(|name| OsStr::from_bytes(name)));
+        Ok(prefix)
+    }
+}
+
+/// Iterator over full paths and path components of [`ReceiptReader`].
+pub struct Entries<'a, R> {
+    reader: &'a mut ReceiptReader<R>,
+    position: usize,
+    directories: HashMap<u32, PathBuf>,
+}
+
+impl<R: Read + Seek> Iterator for Entries<'_, R> {
+    type Item = Result<(PathBuf, PathComponent), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.reader.entries.len() {
+            return None;
+        }
+        let i = self.position;
+        self.position += 1;
+        let component = match self.reader.read(i) {
+            Ok(component) => component,
+            Err(e) => return Some(Err(e)),
+        };
+        let mut path = match self
+            .reader
+            .path_with_cache(component.parent, &self.directories)
+        {
+            Ok(path) => path,
+            Err(e) => return Some(Err(e)),
+        };
+        path.push(OsStr::from_bytes(component.name.to_bytes()));
+        if component.metadata.entry_type() == EntryType::Directory {
+            self.directories.insert(component.seq_no, path.clone());
+        }
+        Some(Ok((path, component)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.reader.entries.len() - self.position;
+        (n, Some(n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use arbtest::arbtest;
+    use random_dir::DirBuilder;
+
+    use super::*;
+    use crate::receipt::ReceiptBuilder;
+
+    #[test]
+    fn read_lazily() {
+        arbtest(|u| {
+            let receipt: Receipt = u.arbitrary()?;
+            check_reader(&receipt);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn read_lazily_sha256() {
+        arbtest(|u| {
+            use random_dir::FileType::*;
+            let directory = DirBuilder::new()
+                .file_types([Regular, Directory, Symlink, HardLink])
+                .create(u)?;
+            let receipt = ReceiptBuilder::new()
+                .sha256(true)
+                .create(directory.path())
+                .unwrap();
+            check_reader(&receipt);
+            Ok(())
+        });
+    }
+
+    fn check_reader(receipt: &Receipt) {
+        let mut writer = Cursor::new(Vec::new());
+        receipt.write(&mut writer).unwrap();
+        let bytes = writer.into_inner();
+        let expected = Receipt::read(&bytes[..]).unwrap();
+        let mut reader = ReceiptReader::new(Cursor::new(&bytes[..])).unwrap();
+        assert_eq!(expected.entries().unwrap().len(), reader.len());
+        let actual = reader.entries().collect::<Result<Vec<_>, _>>().unwrap();
+        let mut paths = Vec::new();
+        for (path, component) in actual.into_iter() {
+            assert_eq!(Some(&component), expected.get(&path));
+            assert_eq!(path, reader.path(component.seq_no).unwrap());
+            paths.push((path, component.metadata));
+        }
+        assert_eq!(expected.entries().unwrap(), paths);
+        assert!(reader.get(0).unwrap().is_none());
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::io::Error;
+use std::io::ErrorKind;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::receipt::changes;
+use crate::receipt::Change;
+use crate::receipt::EntryType;
+use crate::receipt::Metadata;
+use crate::receipt::Receipt;
+
+/// Configuration for verifying a receipt against the file system.
+pub struct Verifier {
+    check_mtime: bool,
+    check_owner: bool,
+    check_extra: bool,
+}
+
+impl Verifier {
+    /// Create verifier with the default parameters.
+    ///
+    /// All checks are enabled by default.
+    pub fn new() -> Self {
+        Self {
+            check_mtime: true,
+            check_owner: true,
+            check_extra: true,
+        }
+    }
+
+    /// Compare modification times.
+    pub fn check_mtime(mut self, value: bool) -> Self {
+        self.check_mtime = value;
+        self
+    }
+
+    /// Compare user and group ids.
+    pub fn check_owner(mut self, value: bool) -> Self {
+        self.check_owner = value;
+        self
+    }
+
+    /// Report files that are not in the receipt.
+    ///
+    /// Only the directories that are in the receipt are checked for extra files.
+    /// Extra directories are reported without their contents.
+    pub fn check_extra(mut self, value: bool) -> Self {
+        self.check_extra = value;
+        self
+    }
+
+    /// Compare each path in the receipt with the file under `root` directory.
+    ///
+    /// Returns the list of problems in depth-first order of the receipt paths.
+    /// Extra files of a directory are reported right after the directory itself
+    /// in the order of their names.
+    pub fn verify<P: AsRef<Path>>(
+        &self,
+        receipt: &Receipt,
+        root: P,
+    ) -> Result<Vec<Problem>, Error> {
+        let root = root.as_ref();
+        let mut problems = Vec::new();
+        for (path, component) in receipt.walk() {
+            let file_path = root.join(&path);
+            let expected = &component.metadata;
+            let paths_only = matches!(expected, Metadata::Entry(..));
+            // Digests are computed only if the receipt has them.
+            let actual = Metadata::new(&file_path, paths_only).and_then(|mut actual| {
+                if expected.digest().is_some() {
+                    actual.compute_digest(&file_path)?;
+                }
+                Ok(actual)
+            });
+            let actual = match actual {
+                Ok(actual) => actual,
+                Err(e) if e.kind() == ErrorKind::NotFound => {
+                    problems.push(Problem::Missing { path });
+                    continue;
+                }
+                Err(e) => {
+                    return Err(Error::new(
+                        e.kind(),
+                        format!("failed to read {}: {}", file_path.display(), e),
+                    ))
+                }
+            };
+            if expected.entry_type() != actual.entry_type() {
+                problems.push(Problem::TypeMismatch {
+                    path,
+                    expected: expected.entry_type(),
+                    actual: actual.entry_type(),
+                });
+                continue;
+            }
+            let is_directory = expected.entry_type() == EntryType::Directory;
+            let changes: Vec<_> = changes(expected, &actual)
+                .into_iter()
+                .filter(|change| match change {
+                    Change::Mtime { .. } => self.check_mtime,
+                    Change::Uid { .. } | Change::Gid { .. } => self.check_owner,
+                    // Directory sizes depend on the file system.
+                    Change::Size { .. } => !is_directory,
+                    _ => true,
+                })
+                .collect();
+            if !changes.is_empty() {
+                problems.push(Problem::Mismatch {
+                    path: path.clone(),
+                    changes,
+                });
+            }
+            if self.check_extra && is_directory {
+                let names: HashSet<&[u8]> = receipt
+                    .children(&path)
+                    .map(|child| child.name.to_bytes())
+                    .collect();
+                let mut extra: Vec<OsString> = Vec::new();
+                for entry in std::fs::read_dir(&file_path)? {
+                    let name = entry?.file_name();
+                    if !names.contains(name.as_bytes()) {
+                        extra.push(name);
+                    }
+                }
+                extra.sort_unstable();
+                problems.extend(extra.into_iter().map(|name| Problem::Extra {
+                    path: path.join(name),
+                }));
+            }
+        }
+        Ok(problems)
+    }
+}
+
+impl Default for Verifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A difference between the receipt and the file system.
+///
+/// Returned by [`Verifier::verify`].
+#[derive(Debug)]
+#[cfg_attr(test, derive(PartialEq, Eq))]
+pub enum Problem {
+    /// The file is in the receipt but not on the file system.
+    Missing {
+        /// Full path.
+        path: PathBuf,
+    },
+    /// The file is on the file system but not in the receipt.
+    Extra {
+        /// Full path.
+        path: PathBuf,
+    },
+    /// The file has different type on the file system.
+    TypeMismatch {
+        /// Full path.
+        path: PathBuf,
+        /// The type in the receipt.
+        expected: EntryType,
+        /// The type on the file system.
+        actual: EntryType,
+    },
+    /// The file has different metadata on the file system.
+    Mismatch {
+        /// Full path.
+        path: PathBuf,
+        /// Changed fields.
+        ///
+        /// Old values come from the receipt, new values come from the file system.
+        changes: Vec<Change>,
+    },
+}
+
+impl Problem {
+    /// Get full path.
+    pub fn path(&self) -> &Path {
+        match self {
+            Problem::Missing { path }
+            | Problem::Extra { path }
+            | Problem::TypeMismatch { path, .. }
+            | Problem::Mismatch { path, .. } => path.as_path(),
+        }
+    }
+}
+
+impl Receipt {
+    /// Compare the receipt with the files under `root` directory using the default parameters.
+    ///
+    /// Use [`Verifier`] to change the parameters.
+    pub fn verify<P: AsRef<Path>>(&self, root: P) -> Result<Vec<Problem>, Error> {
+        Verifier::new().verify(self, root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use arbtest::arbtest;
+    use random_dir::DirBuilder;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::receipt::sha256;
+    use crate::receipt::ReceiptBuilder;
+
+    #[test]
+    fn verify_unchanged() {
+        arbtest(|u| {
+            use random_dir::FileType::*;
+            let directory = DirBuilder::new()
+                .file_types([Regular, Directory, Symlink, HardLink])
+                .create(u)?;
+            let paths_only = u.arbitrary()?;
+            let receipt = ReceiptBuilder::new()
+                .paths_only(paths_only)
+                .sha256(u.arbitrary()?)
+                .create(directory.path())
+                .unwrap();
+            assert_eq!(
+                Vec::<Problem>::new(),
+                receipt.verify(directory.path()).unwrap()
+            );
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn verify_changed() {
+        let directory = TempDir::new().unwrap();
+        let root = directory.path();
+        std::fs::create_dir(root.join("bin")).unwrap();
+        std::fs::write(root.join("bin/a"), "a").unwrap();
+        std::fs::write(root.join("bin/b"), "b").unwrap();
+        std::fs::write(root.join("c"), "c").unwrap();
+        std::os::unix::fs::symlink("a", root.join("bin/link")).unwrap();
+        let receipt = ReceiptBuilder::new().create(root).unwrap();
+        std::fs::remove_file(root.join("bin/b")).unwrap();
+        std::fs::write(root.join("bin/extra"), "").unwrap();
+        std::fs::set_permissions(root.join("bin/a"), PermissionsExt::from_mode(0o700)).unwrap();
+        std::fs::remove_file(root.join("c")).unwrap();
+        std::fs::create_dir(root.join("c")).unwrap();
+        std::fs::remove_file(root.join("bin/link")).unwrap();
+        std::os::unix::fs::symlink("b", root.join("bin/link")).unwrap();
+        let problems = Verifier::new()
+            .check_mtime(false)
+            .verify(&receipt, root)
+            .unwrap();
+        let a = receipt.get("bin/a").unwrap();
+        let link = receipt.get("bin/link").unwrap();
+        let mut expected = vec![
+            Problem::Extra {
+                path: "./bin/extra".into(),
+            },
+            Problem::Mismatch {
+                path: "./bin/a".into(),
+                changes: vec![Change::Mode {
+                    old: a.metadata.mode(),
+                    new: 0o100700,
+                }],
+            },
+            Problem::Missing {
+                path: "./bin/b".into(),
+            },
+            Problem::Mismatch {
+                path: "./bin/link".into(),
+                changes: vec![
+                    Change::Checksum {
+                        old: link.metadata.checksum(),
+                        new: Metadata::new(&root.join("bin/link"), false)
+                            .unwrap()
+                            .checksum(),
+                    },
+                    Change::Target {
+                        old: "a".into(),
+                        new: "b".into(),
+                    },
+                ],
+            },
+            Problem::TypeMismatch {
+                path: "./c".into(),
+                expected: EntryType::File,
+                actual: EntryType::Directory,
+            },
+        ];
+        let mut problems = problems;
+        let key = |p: &Problem| p.path().to_path_buf();
+        problems.sort_unstable_by_key(key);
+        expected.sort_unstable_by_key(key);
+        assert_eq!(expected, problems);
+    }
+
+    #[test]
+    fn verify_order() {
+        let directory = TempDir::new().unwrap();
+        let root = directory.path();
+        std::fs::create_dir(root.join("d")).unwrap();
+        std::fs::write(root.join("d/a"), "a").unwrap();
+        let receipt = ReceiptBuilder::new().create(root).unwrap();
+        std::fs::set_permissions(root.join("d"), PermissionsExt::from_mode(0o700)).unwrap();
+        std::fs::set_permissions(root.join("d/a"), PermissionsExt::from_mode(0o600)).unwrap();
+        std::fs::write(root.join("d/extra"), "").unwrap();
+        let problems = Verifier::new()
+            .check_mtime(false)
+            .verify(&receipt, root)
+            .unwrap();
+        let paths: Vec<_> = problems.iter().map(|p| p.path().to_path_buf()).collect();
+        assert_eq!(
+            vec![
+                PathBuf::from("./d"),
+                PathBuf::from("./d/extra"),
+                PathBuf::from("./d/a"),
+            ],
+            paths
+        );
+    }
+
+    #[test]
+    fn verify_sha256() {
+        let directory = TempDir::new().unwrap();
+        let root = directory.path();
+        std::fs::write(root.join("a"), "ab").unwrap();
+        let receipt = ReceiptBuilder::new().sha256(true).create(root).unwrap();
+        assert_eq!(Vec::<Problem>::new(), receipt.verify(root).unwrap());
+        // same size, different contents
+        std::fs::write(root.join("a"), "ba").unwrap();
+        let problems = Verifier::new()
+            .check_mtime(false)
+            .verify(&receipt, root)
+            .unwrap();
+        let expected = Change::Sha256 {
+            old: sha256(&b"ab"[..]).unwrap(),
+            new: sha256(&b"ba"[..]).unwrap(),
+        };
+        assert!(
+            matches!(
+                &problems[..],
+                [Problem::Mismatch { path, changes }]
+                    if path == Path::new("./a") && changes.contains(&expected)
+            ),
+            "{:?}",
+            problems
+        );
+    }
+}
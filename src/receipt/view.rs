@@ -0,0 +1,504 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ffi::CStr;
+use std::ffi::OsStr;
+use std::io::Error;
+use std::io::Read;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::read_tree_entries;
+use crate::receipt::read_component_value;
+use crate::receipt::Context;
+use crate::receipt::EntryType;
+use crate::receipt::FileDigest;
+use crate::receipt::FileSizes64;
+use crate::receipt::Metadata;
+use crate::receipt::RawPathComponent;
+use crate::receipt::Receipt;
+use crate::BigEndianRead;
+use crate::Bom;
+use crate::ErrorContext;
+use crate::Warnings;
+
+/// Read-only receipt that borrows the contents of the file.
+///
+/// Unlike [`Receipt::read`] the file is not copied and the path components are decoded only
+/// when they are accessed. Only sequential numbers and block indices are kept in memory.
+///
+/// Use [`MappedReceipt`] to view memory-mapped files (requires `mmap` feature).
+pub struct ReceiptView<'a> {
+    file: &'a [u8],
+    bom: Bom,
+    /// Sorted by sequential number.
+    entries: Vec<RawPathComponent>,
+    file_sizes: FileSizes64,
+    /// Metadata block index to digest block index mapping.
+    file_digests: HashMap<u32, u32>,
+}
+
+impl<'a> ReceiptView<'a> {
+    /// Create a view of the receipt stored in `file`.
+    ///
+    /// Reads the block index, 64-bit file sizes, the structure of the paths tree
+    /// and the indices of file digests' blocks.
+    pub fn new(file: &'a [u8]) -> Result<Self, Error> {
+        let mut bom = Bom::read(file)?;
+        let mut context = Context::new();
+        let file_sizes = match bom.get_named(Receipt::SIZE_64) {
+            Some(_) => bom.read_named(Receipt::SIZE_64, file, &mut context)?,
+            None => FileSizes64::default(),
+        };
+        let paths = bom
+            .get_named(Receipt::PATHS)
+            .ok_or_else(|| crate::Error::named_block_not_found(Receipt::PATHS))?;
+        let blocks = bom.blocks();
+        let mut file_digests = HashMap::new();
+        if let Some(i) = bom.get_named(Receipt::SHA_256) {
+            let raw_entries = blocks
+                .get_slice(i, file)
+                .and_then(|tree| read_tree_entries(tree, |i| blocks.get_slice(i, file)))
+                .in_block(i)
+                .in_named_block(Receipt::SHA_256)?;
+            for (digest, value) in raw_entries.into_iter() {
+                let metadata = blocks
+                    .get_slice(value, file)
+                    .and_then(u32::read_be)
+                    .in_block(value)
+                    .in_block(i)
+                    .in_named_block(Receipt::SHA_256)?;
+                file_digests.insert(metadata, digest);
+            }
+        }
+        let raw_entries = blocks
+            .get_slice(paths, file)
+            .and_then(|tree| read_tree_entries(tree, |i| blocks.get_slice(i, file)))
+            .in_block(paths)
+            .in_named_block(Receipt::PATHS)?;
+        let mut entries = Vec::with_capacity(raw_entries.len());
+        for (key, value) in raw_entries.into_iter() {
+            let entry = blocks
+                .get_slice(key, file)
+                .and_then(|block| RawPathComponent::new(block, value))
+                .in_block(key)
+                .in_block(paths)
+                .in_named_block(Receipt::PATHS)?;
+            entries.push(entry);
+        }
+        entries.sort_unstable_by_key(|entry| entry.seq_no);
+        Ok(Self {
+            file,
+            bom,
+            entries,
+            file_sizes,
+            file_digests,
+        })
+    }
+
+    /// Get the number of path components.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Is there are no path components?
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get path component by its sequential number.
+    pub fn get(&self, seq_no: u32) -> Result<Option<PathComponentView<'a>>, Error> {
+        match self.position(seq_no) {
+            Some(i) => self.decode(&self.entries[i]).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Iterate over path components in the order of their sequential numbers.
+    pub fn iter(&self) -> impl Iterator<Item = Result<PathComponentView<'a>, Error>> + '_ {
+        self.entries.iter().map(|entry| self.decode(entry))
+    }
+
+    /// Get full path of the path component, e.g. `./usr/bin/ls`.
+    ///
+    /// Only the names of the parent directories are decoded.
+    pub fn path(&self, component: &PathComponentView<'a>) -> Result<PathBuf, Error> {
+        let mut visited = HashSet::new();
+        let mut names = vec![component.name];
+        let mut seq_no = component.parent;
+        while seq_no != 0 {
+            if !visited.insert(seq_no) {
+                return Err(Error::other("file system loop"));
+            }
+            let Some(i) = self.position(seq_no) else {
+                break;
+            };
+            let (parent, name) = self.read_value(self.entries[i].value)?;
+            names.push(name);
+            seq_no = parent;
+        }
+        let mut path = PathBuf::new();
+        path.extend(
+            names
+                .into_iter()
+                .rev()
+                .map(|name| OsStr::from_bytes(name.to_bytes())),
+        );
+        Ok(path)
+    }
+
+    fn position(&self, seq_no: u32) -> Option<usize> {
+        self.entries
+            .binary_search_by_key(&seq_no, |entry| entry.seq_no)
+            .ok()
+    }
+
+    fn decode(&self, entry: &RawPathComponent) -> Result<PathComponentView<'a>, Error> {
+        let (parent, name) = self.read_value(entry.value)?;
+        let blocks = self.bom.blocks();
+        let digest = match self.file_digests.get(&entry.metadata) {
+            Some(i) => Some(blocks.get_slice(*i, self.file)?),
+            None => None,
+        };
+        let metadata = MetadataView::new(
+            blocks.get_slice(entry.metadata, self.file)?,
+            self.file_sizes.get(&entry.metadata).copied(),
+            digest,
+        )?;
+        Ok(PathComponentView {
+            seq_no: entry.seq_no,
+            parent,
+            name,
+            metadata,
+        })
+    }
+
+    fn read_value(&self, i: u32) -> Result<(u32, &'a CStr), Error> {
+        read_component_value(self.bom.blocks().get_slice(i, self.file)?)
+    }
+}
+
+/// Path component that borrows the contents of the file.
+///
+/// Returned by [`ReceiptView`].
+#[derive(Debug, Clone, Copy)]
+pub struct PathComponentView<'a> {
+    /// Sequential number of the path in the tree.
+    pub seq_no: u32,
+    /// Parent path.
+    ///
+    /// Equals zero for paths with no parent.
+    pub parent: u32,
+    /// File name.
+    ///
+    /// This includes only the last component of the path.
+    pub name: &'a CStr,
+    /// File metadata.
+    pub metadata: MetadataView<'a>,
+}
+
+/// File metadata that borrows the contents of the file.
+///
+/// The fields are decoded on access.
+/// Getters return zero if the field is not applicable to the file type,
+/// the same way as [`Metadata`] getters do.
+#[derive(Debug, Clone, Copy)]
+pub struct MetadataView<'a> {
+    bytes: &'a [u8],
+    entry_type: EntryType,
+    paths_only: bool,
+    size: Option<u64>,
+    /// The contents of the digest block.
+    digest: Option<&'a [u8]>,
+}
+
+impl<'a> MetadataView<'a> {
+    fn new(bytes: &'a [u8], size: Option<u64>, digest: Option<&'a [u8]>) -> Result<Self, Error> {
+        let mut reader = bytes;
+        let entry_type = EntryType::read_be(reader.by_ref())?;
+        let _x0 = u8::read_be(reader.by_ref())?;
+        let flags = u16::read_be(reader.by_ref())?;
+        let paths_only = (flags & 0xf) == 0;
+        let view = Self {
+            bytes,
+            entry_type,
+            paths_only,
+            size,
+            digest,
+        };
+        if !paths_only {
+            let min_len = match entry_type {
+                EntryType::Directory => COMMON_END,
+                EntryType::File | EntryType::Device => CHECKSUM_END,
+                EntryType::Link => TARGET_OFFSET + view.u32_at(CHECKSUM_END) as usize,
+            };
+            if bytes.len() < min_len {
+                return Err(crate::Error::invalid_data("metadata block is too small"));
+            }
+        }
+        Ok(view)
+    }
+
+    /// Get entry type.
+    pub fn entry_type(&self) -> EntryType {
+        self.entry_type
+    }
+
+    /// Returns `true` if the receipt contains only paths and their entry types.
+    pub fn is_paths_only(&self) -> bool {
+        self.paths_only
+    }
+
+    /// Get file mode.
+    pub fn mode(&self) -> u16 {
+        self.common_field(MODE_OFFSET, 2) as u16
+    }
+
+    /// Get file owner's user id.
+    pub fn uid(&self) -> u32 {
+        self.common_field(UID_OFFSET, 4)
+    }
+
+    /// Get file owner's group id.
+    pub fn gid(&self) -> u32 {
+        self.common_field(GID_OFFSET, 4)
+    }
+
+    /// Get file's last modification time.
+    pub fn mtime(&self) -> u32 {
+        self.common_field(MTIME_OFFSET, 4)
+    }
+
+    /// Get file size.
+    ///
+    /// Sizes larger than 4 GiB are taken from `Size64` named block.
+    pub fn size(&self) -> u64 {
+        match self.size {
+            Some(size) => size,
+            None => self.common_field(SIZE_OFFSET, 4) as u64,
+        }
+    }
+
+    /// Get CRC32 checksum of the file contents or the symbolic link target.
+    pub fn checksum(&self) -> u32 {
+        match self.entry_type {
+            EntryType::File | EntryType::Link => self.common_field(COMMON_END, 4),
+            _ => 0,
+        }
+    }
+
+    /// Get symbolic link target.
+    pub fn target(&self) -> Option<&'a Path> {
+        if self.paths_only || self.entry_type != EntryType::Link {
+            return None;
+        }
+        let len = self.u32_at(CHECKSUM_END) as usize;
+        let target = self.bytes.get(TARGET_OFFSET..TARGET_OFFSET + len)?;
+        let target = CStr::from_bytes_until_nul(target).ok()?;
+        Some(Path::new(OsStr::from_bytes(target.to_bytes())))
+    }
+
+    /// Get device number.
+    pub fn rdev(&self) -> i32 {
+        match self.entry_type {
+            EntryType::Device => self.common_field(COMMON_END, 4) as i32,
+            _ => 0,
+        }
+    }
+
+    /// Get SHA-256 digest.
+    ///
+    /// Returns `None` for the receipts created without
+    /// [`sha256`](crate::receipt::ReceiptBuilder::sha256) and if the digest can not be decoded.
+    pub fn digest(&self) -> Option<FileDigest> {
+        FileDigest::read_be(self.digest?).ok()
+    }
+
+    /// Decode all fields into owned metadata.
+    pub fn to_metadata(&self) -> Result<Metadata, Error> {
+        let mut metadata = Metadata::read(self.bytes, None, &mut Warnings::default())?;
+        if let Some(size) = self.size {
+            metadata.set_size(size);
+        }
+        if let Some(digest) = self.digest {
+            metadata.set_digest(Some(FileDigest::read_be(digest)?));
+        }
+        Ok(metadata)
+    }
+
+    fn common_field(&self, offset: usize, len: usize) -> u32 {
+        if self.paths_only {
+            return 0;
+        }
+        match len {
+            2 => self
+                .bytes
+                .get(offset..offset + 2)
+                .and_then(|bytes| bytes.try_into().ok())
+                .map(u16::from_be_bytes)
+                .unwrap_or(0) as u32,
+            _ => self.u32_at(offset),
+        }
+    }
+
+    fn u32_at(&self, offset: usize) -> u32 {
+        self.bytes
+            .get(offset..offset + 4)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u32::from_be_bytes)
+            .unwrap_or(0)
+    }
+}
+
+/// Memory-mapped receipt file.
+///
+/// Use [`view`](Self::view) to access the receipt.
+#[cfg(feature = "mmap")]
+pub struct MappedReceipt {
+    mmap: memmap2::Mmap,
+}
+
+#[cfg(feature = "mmap")]
+impl MappedReceipt {
+    /// Map the file under `path` into memory.
+    ///
+    /// The file must not be modified or truncated while it is mapped,
+    /// otherwise the behaviour is undefined.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: The mapping is read-only. Modifying the file while it is mapped
+        // is documented as undefined behaviour above.
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(Self { mmap })
+    }
+
+    /// Create a view of the receipt.
+    pub fn view(&self) -> Result<ReceiptView<'_>, Error> {
+        ReceiptView::new(&self.mmap[..])
+    }
+}
+
+// Metadata block layout: entry type (1), 1 (1), flags (2), mode (2), uid (4), gid (4),
+// mtime (4), size (4), 1 (1), then type-specific fields.
+const MODE_OFFSET: usize = 4;
+const UID_OFFSET: usize = MODE_OFFSET + 2;
+const GID_OFFSET: usize = UID_OFFSET + 4;
+const MTIME_OFFSET: usize = GID_OFFSET + 4;
+const SIZE_OFFSET: usize = MTIME_OFFSET + 4;
+const COMMON_END: usize = SIZE_OFFSET + 4 + 1;
+/// Checksum for files and links, device number for devices.
+const CHECKSUM_END: usize = COMMON_END + 4;
+/// Link target length is stored right after the checksum.
+const TARGET_OFFSET: usize = CHECKSUM_END + 4;
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use arbtest::arbtest;
+    use random_dir::DirBuilder;
+
+    use super::*;
+    use crate::receipt::ReceiptBuilder;
+
+    #[test]
+    fn view() {
+        arbtest(|u| {
+            let receipt: Receipt = u.arbitrary()?;
+            check_view(&receipt);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn view_sha256() {
+        arbtest(|u| {
+            use random_dir::FileType::*;
+            let directory = DirBuilder::new()
+                .file_types([Regular, Directory, Symlink, HardLink])
+                .create(u)?;
+            let receipt = ReceiptBuilder::new()
+                .sha256(true)
+                .create(directory.path())
+                .unwrap();
+            check_view(&receipt);
+            Ok(())
+        });
+    }
+
+    fn check_view(receipt: &Receipt) {
+        let mut writer = Cursor::new(Vec::new());
+        receipt.write(&mut writer).unwrap();
+        let bytes = writer.into_inner();
+        let view = ReceiptView::new(&bytes[..]).unwrap();
+        let expected = receipt.entries().unwrap();
+        assert_eq!(expected.len(), view.len());
+        for ((expected_path, expected), actual) in expected.iter().zip(view.iter()) {
+            let actual = actual.unwrap();
+            assert_eq!(expected_path, &view.path(&actual).unwrap());
+            let metadata = &actual.metadata;
+            assert_eq!(expected, &metadata.to_metadata().unwrap());
+            assert_eq!(expected.entry_type(), metadata.entry_type());
+            assert_eq!(
+                (
+                    expected.mode(),
+                    expected.uid(),
+                    expected.gid(),
+                    expected.mtime(),
+                    expected.size(),
+                    expected.checksum(),
+                ),
+                (
+                    metadata.mode(),
+                    metadata.uid(),
+                    metadata.gid(),
+                    metadata.mtime(),
+                    metadata.size(),
+                    metadata.checksum(),
+                )
+            );
+            match expected {
+                Metadata::Link(link) => assert_eq!(Some(link.target()), metadata.target()),
+                _ => assert_eq!(None, metadata.target()),
+            }
+            match expected {
+                Metadata::Device(device) => assert_eq!(device.rdev(), metadata.rdev()),
+                _ => assert_eq!(0, metadata.rdev()),
+            }
+            assert_eq!(expected.digest(), metadata.digest().as_ref());
+            let component = view.get(actual.seq_no).unwrap().unwrap();
+            assert_eq!(actual.name, component.name);
+        }
+        assert!(view.get(0).unwrap().is_none());
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mapped() {
+        let directory = tempfile::TempDir::new().unwrap();
+        std::fs::write(directory.path().join("file"), "hello").unwrap();
+        let path = directory.path().join("receipt.bom");
+        let receipt = ReceiptBuilder::new()
+            .sha256(true)
+            .create(directory.path())
+            .unwrap();
+        receipt
+            .write(std::fs::File::create(&path).unwrap())
+            .unwrap();
+        let mapped = MappedReceipt::open(&path).unwrap();
+        let view = mapped.view().unwrap();
+        let paths = view
+            .iter()
+            .map(|component| view.path(&component.unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        assert!(paths.contains(&PathBuf::from("./file")));
+        assert_eq!(receipt.entries().unwrap().len(), view.len());
+        let seq_no = receipt.get("./file").unwrap().seq_no;
+        let component = view.get(seq_no).unwrap().unwrap();
+        assert_eq!(
+            receipt.get("./file").unwrap().metadata.digest(),
+            component.metadata.digest().as_ref()
+        );
+        assert!(component.metadata.digest().is_some());
+    }
+}
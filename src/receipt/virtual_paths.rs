@@ -11,12 +11,18 @@ use crate::BigEndianWrite;
 use crate::BlockRead;
 use crate::BlockWrite;
 use crate::Blocks;
+use crate::ErrorContext;
 
 /// Directory name to regex mapping.
 #[derive(Debug)]
 #[cfg_attr(test, derive(arbitrary::Arbitrary, PartialEq, Eq))]
 pub struct VirtualPathTree {
     tree: VecTree<Option<VecTree<(), CString>>, CString>,
+    // Unknown fields are kept as is to write the block back unchanged.
+    #[cfg_attr(test, arbitrary(default))]
+    x0: u32,
+    #[cfg_attr(test, arbitrary(value = DEFAULT_X1))]
+    x1: u8,
 }
 
 impl VirtualPathTree {
@@ -26,6 +32,8 @@ impl VirtualPathTree {
     pub fn new() -> Self {
         Self {
             tree: Default::default(),
+            x0: 0,
+            x1: DEFAULT_X1,
         }
     }
 }
@@ -40,17 +48,19 @@ impl BlockRead<Context> for VirtualPathTree {
         let mut reader = blocks.slice(i, file)?;
         let version = u32::read_be(reader.by_ref())?;
         if version != Self::VERSION {
-            return Err(Error::other("unsupported vindex version"));
+            return Err(crate::Error::unsupported_version("virtual paths", version)).in_block(i);
         }
-        let i = u32::read_be(reader.by_ref())?;
-        let _x0 = u32::read_be(reader.by_ref())?;
-        // TODO
-        //debug_assert!(_x0 == 0, "x0 = {}", _x0);
-        let _x1 = u8::read_be(reader.by_ref())?;
-        // TODO
-        //debug_assert!(_x1 == DEFAULT_X1, "x1 = {_x1}");
-        let tree = VecTree::read_block(i, file, blocks, context)?;
-        Ok(Self { tree })
+        let tree_index = u32::read_be(reader.by_ref())?;
+        let x0 = u32::read_be(reader.by_ref())?;
+        let x1 = u8::read_be(reader.by_ref())?;
+        if x0 != 0 || x1 != DEFAULT_X1 {
+            context.warnings.report(
+                Some(i),
+                format!("unexpected values {} and {} in virtual paths", x0, x1),
+            )?;
+        }
+        let tree = VecTree::read_block(tree_index, file, blocks, context)?;
+        Ok(Self { tree, x0, x1 })
     }
 }
 
@@ -65,8 +75,8 @@ impl BlockWrite<Context> for VirtualPathTree {
         let i = blocks.append(writer.by_ref(), |writer| {
             Self::VERSION.write_be(writer.by_ref())?;
             tree_index.write_be(writer.by_ref())?;
-            0_u32.write_be(writer.by_ref())?;
-            DEFAULT_X1.write_be(writer.by_ref())?;
+            self.x0.write_be(writer.by_ref())?;
+            self.x1.write_be(writer.by_ref())?;
             Ok(())
         })?;
         Ok(i)
@@ -8,6 +8,8 @@ use crate::receipt::Context;
 use crate::BlockRead;
 use crate::BlockWrite;
 use crate::Blocks;
+use crate::ReadOptions;
+use crate::Warnings;
 
 pub fn block_io_symmetry<
     T: for<'a> Arbitrary<'a> + Debug + Eq + BlockRead<Context> + BlockWrite<Context>,
@@ -40,6 +42,7 @@ pub fn test_block_io_symmetry_convert<
 ) {
     let mut blocks = Blocks::new();
     let mut context = Context::new();
+    context.warnings = Warnings::new(ReadOptions::new().strict(true));
     let expected: T = expected.into();
     let mut writer = Cursor::new(Vec::new());
     let i = expected